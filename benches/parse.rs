@@ -0,0 +1,44 @@
+// compares from_path with parsing the same files one after the other on a single parser,
+// run with `cargo bench --bench parse`
+use std::time::Instant;
+
+use kotlin_ls::kotlin;
+
+const FILES: usize = 500;
+
+fn main() {
+    let root = std::env::temp_dir().join(format!("kotlin-ls-bench-{}", std::process::id()));
+    std::fs::create_dir_all(&root).unwrap();
+    for i in 0..FILES {
+        // a few files are much larger than the rest, like in real projects
+        let count = if i % 10 == 0 { 500 } else { 50 };
+        let functions: String = (0..count)
+            .map(|j| format!("    fun f{j}(x: Int): Int {{\n        val y = x * {j}\n        return y + {i}\n    }}\n"))
+            .collect();
+        std::fs::write(
+            root.join(format!("File{i}.kt")),
+            format!("package bench\n\nclass File{i} {{\n{functions}}}\n"),
+        )
+        .unwrap();
+    }
+    let root_path = root.to_str().unwrap();
+
+    let started = Instant::now();
+    let mut parser = kotlin::new_parser().unwrap();
+    for path in kotlin::kotlin_paths(root_path) {
+        kotlin::from_path_with_parser(&mut parser, &path).unwrap();
+    }
+    let sequential = started.elapsed();
+
+    let started = Instant::now();
+    let files = kotlin::from_path(root_path).unwrap();
+    let parallel = started.elapsed();
+
+    std::fs::remove_dir_all(&root).unwrap();
+    assert_eq!(files.len(), FILES);
+    assert!(files.values().all(Result::is_ok));
+    println!(
+        "{FILES} files on {} threads, sequential: {sequential:?}, from_path: {parallel:?}",
+        std::thread::available_parallelism().map_or(1, |n| n.get())
+    );
+}
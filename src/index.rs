@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use dashmap::DashMap;
+use tower_lsp::lsp_types::SymbolKind;
+
+use crate::kotlin::{ClassType, Declaration, KotlinFile, Span};
+
+#[derive(Debug, Clone)]
+pub struct SymbolLocation {
+    pub path: PathBuf,
+    // span of the declared name
+    pub span: Span,
+    pub kind: SymbolKind,
+}
+
+// declarations of all files by name, kept up to date on every reparse so lookups
+// don't have to walk every file
+#[derive(Debug, Default)]
+pub struct SymbolIndex {
+    symbols: HashMap<String, Vec<SymbolLocation>>,
+}
+
+impl SymbolIndex {
+    pub fn build(files: &DashMap<PathBuf, KotlinFile>) -> SymbolIndex {
+        let mut index = SymbolIndex::default();
+        for file in files.iter() {
+            index.insert(file.key(), file.value());
+        }
+
+        index
+    }
+
+    // replaces everything previously indexed for the file
+    pub fn update(&mut self, path: &Path, file: &KotlinFile) {
+        self.remove(path);
+        self.insert(path, file);
+    }
+
+    pub fn remove(&mut self, path: &Path) {
+        self.symbols.retain(|_, locations| {
+            locations.retain(|location| location.path != path);
+            !locations.is_empty()
+        });
+    }
+
+    pub fn get(&self, name: &str) -> &[SymbolLocation] {
+        self.symbols.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &SymbolLocation)> {
+        self.symbols.iter().flat_map(|(name, locations)| {
+            locations
+                .iter()
+                .map(move |location| (name.as_str(), location))
+        })
+    }
+
+    fn insert(&mut self, path: &Path, file: &KotlinFile) {
+        file.walk_declarations(&mut |declaration| {
+            let location = SymbolLocation {
+                path: path.to_path_buf(),
+                span: declaration.name_span(),
+                kind: symbol_kind(file, declaration),
+            };
+            self.symbols
+                .entry(declaration.name().to_string())
+                .or_default()
+                .push(location);
+        });
+    }
+}
+
+// top level declarations of all files by fully qualified name, used to resolve imports.
+// Files without a package header can't be imported from and are left out. Several files
+// can declare the same name, e.g. for different source sets
+#[derive(Debug, Default)]
+pub struct PackageIndex {
+    paths: HashMap<String, Vec<PathBuf>>,
+}
+
+impl PackageIndex {
+    pub fn build(files: &DashMap<PathBuf, KotlinFile>) -> PackageIndex {
+        let mut index = PackageIndex::default();
+        for file in files.iter() {
+            index.insert(file.key(), file.value());
+        }
+
+        index
+    }
+
+    // replaces everything previously indexed for the file
+    pub fn update(&mut self, path: &Path, file: &KotlinFile) {
+        self.remove(path);
+        self.insert(path, file);
+    }
+
+    pub fn remove(&mut self, path: &Path) {
+        self.paths.retain(|_, paths| {
+            paths.retain(|indexed| indexed != path);
+            !paths.is_empty()
+        });
+    }
+
+    pub fn get(&self, qualified_name: &str) -> &[PathBuf] {
+        self.paths.get(qualified_name).map_or(&[], Vec::as_slice)
+    }
+
+    // fully qualified names whose last segment is the given name
+    pub fn qualified_names<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> {
+        self.paths
+            .keys()
+            .map(String::as_str)
+            .filter(move |qualified| {
+                qualified
+                    .rsplit_once('.')
+                    .is_some_and(|(_, simple)| simple == name)
+            })
+    }
+
+    fn insert(&mut self, path: &Path, file: &KotlinFile) {
+        let package = file.package.name();
+        if package.is_empty() {
+            return;
+        }
+
+        let names = file
+            .classes
+            .iter()
+            .map(|class| class.name.as_str())
+            .chain(file.objects.iter().map(|object| object.name.as_str()))
+            .chain(file.functions.iter().map(|function| function.name.as_str()))
+            .chain(file.properties.iter().flat_map(|property| property.names()))
+            .chain(file.type_aliases.iter().map(|alias| alias.name.as_str()));
+        for name in names {
+            let paths = self.paths.entry(format!("{package}.{name}")).or_default();
+            if !paths.iter().any(|indexed| indexed == path) {
+                paths.push(path.to_path_buf());
+            }
+        }
+    }
+}
+
+pub fn symbol_kind(file: &KotlinFile, declaration: Declaration) -> SymbolKind {
+    match declaration {
+        Declaration::Class(class) => match class.class_type {
+            ClassType::Interface | ClassType::SealedInterface => SymbolKind::INTERFACE,
+            ClassType::Enum => SymbolKind::ENUM,
+            ClassType::Class | ClassType::Sealed | ClassType::Annotation => SymbolKind::CLASS,
+        },
+        Declaration::Object(_) => SymbolKind::OBJECT,
+        // anything that isn't a top level function is a member of a class or object
+        Declaration::Function(function) => {
+            if file.functions.iter().any(|f| std::ptr::eq(f, function)) {
+                SymbolKind::FUNCTION
+            } else {
+                SymbolKind::METHOD
+            }
+        }
+        Declaration::Property(..) => SymbolKind::PROPERTY,
+        // lsp has no kind for aliases, they stand in for a class
+        Declaration::TypeAlias(_) => SymbolKind::CLASS,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use dashmap::DashMap;
+    use tower_lsp::lsp_types::SymbolKind;
+    use tree_sitter::Point;
+
+    use crate::kotlin::{KotlinFile, Span};
+
+    use super::{PackageIndex, SymbolIndex};
+
+    fn files(files: &[(&str, &str)]) -> DashMap<PathBuf, KotlinFile> {
+        files
+            .iter()
+            .map(|(path, content)| (PathBuf::from(path), content.parse().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn symbol_index() {
+        let files = files(&[
+            ("/a.kt", "class Foo\nfun bar() {}\n"),
+            ("/b.kt", "class Foo {\n    val baz = 1\n}\n"),
+        ]);
+        let mut index = SymbolIndex::build(&files);
+
+        let mut paths: Vec<&Path> = index.get("Foo").iter().map(|l| l.path.as_path()).collect();
+        paths.sort();
+        assert_eq!(paths, [Path::new("/a.kt"), Path::new("/b.kt")]);
+        assert_eq!(index.get("baz").len(), 1);
+
+        index.remove(Path::new("/a.kt"));
+        assert_eq!(index.get("Foo").len(), 1);
+        assert_eq!(index.get("Foo")[0].path, Path::new("/b.kt"));
+        assert!(index.get("bar").is_empty());
+
+        index.update(Path::new("/b.kt"), &"class Qux\n".parse().unwrap());
+        assert!(index.get("Foo").is_empty());
+        assert_eq!(index.get("Qux").len(), 1);
+    }
+
+    #[test]
+    fn symbol_locations() {
+        let files = files(&[(
+            "/a.kt",
+            "interface Shape\nenum class Color { RED }\nobject Registry {\n    fun register() {}\n}\nval count = 0\n",
+        )]);
+        let index = SymbolIndex::build(&files);
+
+        let kinds: Vec<(&str, SymbolKind, Span)> =
+            ["Shape", "Color", "Registry", "register", "count"]
+                .into_iter()
+                .map(|name| {
+                    let location = &index.get(name)[0];
+                    (name, location.kind, location.span)
+                })
+                .collect();
+        let range = |row, start, end| Span {
+            start: Point::new(row, start),
+            end: Point::new(row, end),
+        };
+        assert_eq!(
+            kinds,
+            vec![
+                ("Shape", SymbolKind::INTERFACE, range(0, 10, 15)),
+                ("Color", SymbolKind::ENUM, range(1, 11, 16)),
+                ("Registry", SymbolKind::OBJECT, range(2, 7, 15)),
+                ("register", SymbolKind::METHOD, range(3, 8, 16)),
+                ("count", SymbolKind::PROPERTY, range(5, 4, 9)),
+            ]
+        );
+    }
+
+    #[test]
+    fn package_index() {
+        let files = files(&[
+            ("/main/a.kt", "package foo\n\nclass Foo\nval bar = 1\n"),
+            ("/test/a.kt", "package foo\n\nclass Foo\n"),
+            ("/b.kt", "class Unpackaged\n"),
+        ]);
+        let mut index = PackageIndex::build(&files);
+
+        let mut paths = index.get("foo.Foo").to_vec();
+        paths.sort();
+        assert_eq!(
+            paths,
+            [PathBuf::from("/main/a.kt"), PathBuf::from("/test/a.kt")]
+        );
+        assert_eq!(index.get("foo.bar"), [PathBuf::from("/main/a.kt")]);
+        assert!(index.get("Unpackaged").is_empty());
+        assert_eq!(
+            index.qualified_names("Foo").collect::<Vec<_>>(),
+            ["foo.Foo"]
+        );
+
+        index.remove(Path::new("/test/a.kt"));
+        assert_eq!(index.get("foo.Foo"), [PathBuf::from("/main/a.kt")]);
+
+        index.remove(Path::new("/main/a.kt"));
+        assert!(index.get("foo.Foo").is_empty());
+        assert_eq!(index.qualified_names("Foo").count(), 0);
+    }
+}
@@ -3,23 +3,29 @@ use tree_sitter::Node;
 
 use crate::kotlin::expression::Expression;
 
-use super::types::Type;
+use super::{delegation::Delegation, literal::Literal, span::Span, types::Type};
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub struct TypeProjection {
-    data_type: Type,
+    pub data_type: Type,
 }
 
 impl TypeProjection {
     fn new(node: &Node, content: &[u8]) -> Result<TypeProjection> {
+        let mut data_type = None;
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            match child.kind() {
+                "line_comment" | "multiline_comment" | "type_projection_modifiers" => {}
+                _ => data_type = Some(Type::new(&child, content)?),
+            }
+        }
+
         Ok(TypeProjection {
-            data_type: Type::new(
-                &node.child(node.child_count() - 1).context(format!(
-                    "[TypeProjection] no child at {}",
-                    node.start_position()
-                ))?,
-                content,
-            )?,
+            data_type: data_type.context(format!(
+                "[TypeProjection] no type at {}",
+                node.start_position()
+            ))?,
         })
     }
 }
@@ -29,7 +35,8 @@ pub enum Argument {
     Value {
         annotation: Option<String>,
         identifier: Option<String>,
-        expression: Expression,
+        expression: Box<Expression>,
+        span: Span,
     },
     Type {
         type_projections: Vec<TypeProjection>,
@@ -37,6 +44,12 @@ pub enum Argument {
 }
 
 impl Argument {
+    pub fn walk_expressions<'a>(&'a self, f: &mut impl FnMut(&'a Expression)) {
+        if let Argument::Value { expression, .. } = self {
+            expression.walk_expressions(f);
+        }
+    }
+
     fn new_value_argument(node: &Node, content: &[u8]) -> Result<Argument> {
         let mut identifier = None;
         let mut annotation = None;
@@ -60,13 +73,14 @@ impl Argument {
         Ok(Argument::Value {
             annotation,
             identifier,
-            expression: Expression::new(
+            expression: Box::new(Expression::new(
                 &node.child(node.child_count() - 1).context(format!(
                     "[ValueArgument] no child at {}",
                     node.start_position()
                 ))?,
                 content,
-            )?,
+            )?),
+            span: Span::new(node),
         })
     }
 }
@@ -76,8 +90,11 @@ pub fn get_value_arguments(node: &Node, content: &[u8]) -> Result<Vec<Argument>>
     let mut cursor = node.walk();
     for child in node.children(&mut cursor) {
         match child.kind() {
-            "(" | ")" | "," => {}
+            "(" | ")" | "," | "line_comment" | "multiline_comment" => {}
             "value_argument" => arguments.push(Argument::new_value_argument(&child, content)?),
+            "ERROR" if child.child(0).is_some_and(|first| first.kind() == "object") => {
+                arguments.push(bodyless_object_argument(&child, content)?)
+            }
             _ => {
                 bail!(
                     "[get_value_arguments] unhandled child {} '{}' at {}",
@@ -92,12 +109,43 @@ pub fn get_value_arguments(node: &Node, content: &[u8]) -> Result<Vec<Argument>>
     Ok(arguments)
 }
 
+// the grammar can't parse `object : Foo` without a body as an argument, the error node
+// holds the supertypes as plain identifiers
+fn bodyless_object_argument(node: &Node, content: &[u8]) -> Result<Argument> {
+    let mut delegations = Vec::new();
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        match child.kind() {
+            "object" | ":" | "," => {}
+            "simple_identifier" => delegations.push(Delegation::Type(Type::NonNullable(
+                Vec::new(),
+                child.utf8_text(content)?.to_string(),
+            ))),
+            _ => {
+                bail!(
+                    "[bodyless_object_argument] unhandled child {} '{}' at {}",
+                    child.kind(),
+                    child.utf8_text(content)?,
+                    child.start_position(),
+                )
+            }
+        }
+    }
+
+    Ok(Argument::Value {
+        annotation: None,
+        identifier: None,
+        expression: Box::new(Expression::Literal(Literal::Object(None, delegations))),
+        span: Span::new(node),
+    })
+}
+
 pub fn get_type_argument(node: &Node, content: &[u8]) -> Result<Argument> {
     let mut type_projections = Vec::new();
     let mut cursor = node.walk();
     for child in node.children(&mut cursor) {
         match child.kind() {
-            "<" | ">" | "," => {}
+            "<" | ">" | "," | "line_comment" | "multiline_comment" => {}
             "type_projection" => type_projections.push(TypeProjection::new(&child, content)?),
             _ => {
                 bail!(
@@ -112,3 +160,106 @@ pub fn get_type_argument(node: &Node, content: &[u8]) -> Result<Argument> {
 
     Ok(Argument::Type { type_projections })
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::kotlin::{
+        expression::Expression,
+        literal::Literal,
+        test_utils::{find_node, parse},
+        types::Type,
+    };
+
+    use super::{get_type_argument, get_value_arguments, Argument, TypeProjection};
+
+    fn spread_arguments(content: &str) -> Vec<bool> {
+        let tree = parse(content);
+        let node = find_node(&tree, "value_arguments");
+
+        get_value_arguments(&node, content.as_bytes())
+            .unwrap()
+            .iter()
+            .map(|argument| match argument {
+                Argument::Value { expression, .. } => {
+                    matches!(expression.as_ref(), Expression::Spread(_))
+                }
+                Argument::Type { .. } => false,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn spread_call_argument() {
+        assert_eq!(
+            spread_arguments("val x = foo(*listOf(1, 2, 3))"),
+            vec![true]
+        );
+    }
+
+    #[test]
+    fn spread_between_arguments() {
+        assert_eq!(
+            spread_arguments("val x = foo(a, *b, c)"),
+            vec![false, true, false]
+        );
+    }
+
+    #[test]
+    fn type_projection_with_comments() {
+        let content = "val n = foo<out /* c */ Int, // key\n    String>()";
+        let tree = parse(content);
+        let node = find_node(&tree, "type_arguments");
+
+        assert_eq!(
+            get_type_argument(&node, content.as_bytes()).unwrap(),
+            Argument::Type {
+                type_projections: vec![
+                    TypeProjection {
+                        data_type: Type::NonNullable(Vec::new(), "Int".to_string()),
+                    },
+                    TypeProjection {
+                        data_type: Type::NonNullable(Vec::new(), "String".to_string()),
+                    },
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn object_arguments() {
+        let content = "fun f() {\n    foo(object : Bar {\n        override fun x() {}\n    })\n    foo(object : SomeInterface)\n}";
+        let tree = parse(content);
+        let root = tree.root_node();
+
+        let mut objects = Vec::new();
+        let mut cursor = root.walk();
+        let mut pending = vec![root];
+        while let Some(node) = pending.pop() {
+            if node.kind() == "value_arguments" {
+                for argument in get_value_arguments(&node, content.as_bytes()).unwrap() {
+                    let Argument::Value { expression, .. } = argument else {
+                        panic!("expected value argument");
+                    };
+                    let Expression::Literal(Literal::Object(body, delegations)) = *expression
+                    else {
+                        panic!("expected object literal");
+                    };
+                    objects.push((
+                        body.is_some(),
+                        delegations[0].type_name().unwrap().to_string(),
+                    ));
+                }
+            }
+            pending.extend(node.children(&mut cursor));
+        }
+        objects.sort();
+
+        assert_eq!(
+            objects,
+            vec![
+                (false, "SomeInterface".to_string()),
+                (true, "Bar".to_string()),
+            ]
+        );
+    }
+}
@@ -59,4 +59,9 @@ impl Assignment {
             )?,
         })
     }
+
+    pub fn walk_expressions<'a>(&'a self, f: &mut impl FnMut(&'a Expression)) {
+        self.left.walk_expressions(f);
+        self.right.walk_expressions(f);
+    }
 }
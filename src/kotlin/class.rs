@@ -4,12 +4,16 @@ use tree_sitter::{Node, Tree};
 
 use super::{
     argument::{self, Argument},
+    declaration::Declaration,
     delegation::Delegation,
     expression::Expression,
-    function::{Function, Parameter},
+    function::{self, Function, Parameter},
+    kdoc::{self, KDoc},
     object::Object,
     property::Property,
+    span::Span,
     statement::{self, Statement},
+    type_alias::TypeAlias,
     types::{Type, TypeParameter},
 };
 
@@ -22,7 +26,8 @@ pub struct EnumEntry {
 }
 
 impl EnumEntry {
-    fn new(node: &Node, content: &[u8]) -> Result<EnumEntry> {
+    // entries the grammar recovered without a name, e.g. a lone annotation, are skipped
+    fn new(node: &Node, content: &[u8]) -> Result<Option<EnumEntry>> {
         let mut identifier = None;
         let mut value_arguments = None;
         let mut modifiers = Vec::new();
@@ -35,7 +40,10 @@ impl EnumEntry {
                         modifiers.push(Modifier::new(&child, content)?);
                     }
                 }
-                "simple_identifier" => identifier = Some(child.utf8_text(content)?.to_string()),
+                // a missing name is an empty identifier
+                "simple_identifier" if child.start_byte() < child.end_byte() => {
+                    identifier = Some(child.utf8_text(content)?.to_string())
+                }
                 "value_arguments" => {
                     value_arguments = Some(argument::get_value_arguments(&child, content)?)
                 }
@@ -44,15 +52,21 @@ impl EnumEntry {
             }
         }
 
-        Ok(EnumEntry {
+        Ok(identifier.map(|identifier| EnumEntry {
             modifiers,
-            identifier: identifier.context(format!(
-                "[EnumEntry] no identifier at {}",
-                node.start_position()
-            ))?,
+            identifier,
             value_arguments,
             class_body,
-        })
+        }))
+    }
+
+    fn walk_expressions<'a>(&'a self, f: &mut impl FnMut(&'a Expression)) {
+        for argument in self.value_arguments.iter().flatten() {
+            argument.walk_expressions(f);
+        }
+        if let Some(class_body) = &self.class_body {
+            class_body.walk_expressions(f);
+        }
     }
 }
 
@@ -63,12 +77,13 @@ pub struct AnonymousInitializer {
 
 impl AnonymousInitializer {
     fn new(node: &Node, content: &[u8]) -> Result<AnonymousInitializer> {
-        let mut statements = None;
+        // an empty `init {}` has no statements node
+        let mut statements = Vec::new();
         let mut cursor = node.walk();
         for child in node.children(&mut cursor) {
             match child.kind() {
-                "init" | "{" | "}" => {}
-                "statements" => statements = Some(statement::get_statements(&child, content)?),
+                "init" | "{" | "}" | "line_comment" | "multiline_comment" => {}
+                "statements" => statements = statement::get_statements(&child, content)?,
                 _ => {
                     bail!(
                         "[AnonymousInitializer] unhandled child {} '{}' at {}",
@@ -80,12 +95,7 @@ impl AnonymousInitializer {
             }
         }
 
-        Ok(AnonymousInitializer {
-            statements: statements.context(format!(
-                "[AnonymousInitializer] no statements at {}",
-                node.start_position()
-            ))?,
-        })
+        Ok(AnonymousInitializer { statements })
     }
 }
 
@@ -99,6 +109,7 @@ pub enum ClassBody {
         companion_objects: Vec<CompanionObject>,
         anonymous_initializers: Vec<AnonymousInitializer>,
         secondary_constructors: Vec<SecondaryConstructor>,
+        type_aliases: Vec<TypeAlias>,
     },
     Enum {
         entries: Vec<EnumEntry>,
@@ -121,6 +132,7 @@ impl ClassBody {
         let mut companion_objects = Vec::new();
         let mut anonymous_initializers = Vec::new();
         let mut secondary_constructors = Vec::new();
+        let mut type_aliases = Vec::new();
         let mut cursor = node.walk();
         for child in node.children(&mut cursor) {
             match child.kind() {
@@ -146,6 +158,11 @@ impl ClassBody {
                 "secondary_constructor" => {
                     secondary_constructors.push(SecondaryConstructor::new(&child, content)?);
                 }
+                "type_alias" => {
+                    type_aliases.push(TypeAlias::new(&child, content)?);
+                }
+                // picked up by the function that follows
+                "ERROR" if function::context_receivers(&child, content).is_some() => {}
                 _ => {
                     bail!(
                         "[ClassBody::Class] unhandled child {} '{}' at {}",
@@ -165,6 +182,7 @@ impl ClassBody {
             companion_objects,
             anonymous_initializers,
             secondary_constructors,
+            type_aliases,
         })
     }
 
@@ -180,8 +198,9 @@ impl ClassBody {
         let mut cursor = node.walk();
         for child in node.children(&mut cursor) {
             match child.kind() {
-                "{" | "," | "}" | ";" | "getter" | "setter" | "line_comment" => {}
-                "enum_entry" => entries.push(EnumEntry::new(&child, content)?),
+                "{" | "," | "}" | ";" | "getter" | "setter" | "line_comment"
+                | "multiline_comment" => {}
+                "enum_entry" => entries.extend(EnumEntry::new(&child, content)?),
                 "property_declaration" => {
                     properties.push(Property::new(&child, content)?);
                 }
@@ -203,6 +222,7 @@ impl ClassBody {
                 "secondary_constructor" => {
                     secondary_constructors.push(SecondaryConstructor::new(&child, content)?);
                 }
+                "ERROR" if function::context_receivers(&child, content).is_some() => {}
                 _ => {
                     bail!(
                         "[ClassBody::Enum] unhandled child {} '{}' at {}",
@@ -225,20 +245,130 @@ impl ClassBody {
             secondary_constructors,
         })
     }
+
+    pub fn functions(&self) -> &[Function] {
+        match self {
+            ClassBody::Class { functions, .. } | ClassBody::Enum { functions, .. } => functions,
+        }
+    }
+
+    pub fn properties(&self) -> &[Property] {
+        match self {
+            ClassBody::Class { properties, .. } | ClassBody::Enum { properties, .. } => properties,
+        }
+    }
+
+    pub fn classes(&self) -> &[Class] {
+        match self {
+            ClassBody::Class { classes, .. } | ClassBody::Enum { classes, .. } => classes,
+        }
+    }
+
+    // named members declared in this body, including nested classes and objects
+    pub fn walk_declarations<'a>(&'a self, f: &mut impl FnMut(Declaration<'a>)) {
+        let (ClassBody::Class {
+            objects,
+            companion_objects,
+            ..
+        }
+        | ClassBody::Enum {
+            objects,
+            companion_objects,
+            ..
+        }) = self;
+        for function in self.functions() {
+            f(Declaration::Function(function));
+        }
+        for property in self.properties() {
+            property.walk_declarations(f);
+        }
+        for class in self.classes() {
+            class.walk_declarations(f);
+        }
+        for object in objects {
+            object.walk_declarations(f);
+        }
+        for companion_object in companion_objects {
+            companion_object.body.walk_declarations(f);
+        }
+        if let ClassBody::Class { type_aliases, .. } = self {
+            for alias in type_aliases {
+                f(Declaration::TypeAlias(alias));
+            }
+        }
+    }
+
+    pub fn walk_expressions<'a>(&'a self, f: &mut impl FnMut(&'a Expression)) {
+        let (ClassBody::Class {
+            objects,
+            classes,
+            companion_objects,
+            anonymous_initializers,
+            secondary_constructors,
+            ..
+        }
+        | ClassBody::Enum {
+            objects,
+            classes,
+            companion_objects,
+            anonymous_initializers,
+            secondary_constructors,
+            ..
+        }) = self;
+        if let ClassBody::Enum { entries, .. } = self {
+            for entry in entries {
+                entry.walk_expressions(f);
+            }
+        }
+        for property in self.properties() {
+            property.walk_expressions(f);
+        }
+        for function in self.functions() {
+            function.walk_expressions(f);
+        }
+        for object in objects {
+            object.walk_expressions(f);
+        }
+        for class in classes {
+            class.walk_expressions(f);
+        }
+        for companion_object in companion_objects {
+            companion_object.body.walk_expressions(f);
+        }
+        let default_values = secondary_constructors
+            .iter()
+            .flat_map(|c| &c.parameters)
+            .flat_map(|p| &p.default_value);
+        for default_value in default_values {
+            default_value.walk_expressions(f);
+        }
+        let initializer_statements = anonymous_initializers.iter().flat_map(|i| &i.statements);
+        let constructor_statements = secondary_constructors.iter().flat_map(|c| &c.block);
+        for statement in initializer_statements.chain(constructor_statements) {
+            statement.walk_expressions(f);
+        }
+    }
 }
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub struct CompanionObject {
+    modifiers: Vec<Modifier>,
     body: ClassBody,
 }
 
 impl CompanionObject {
     fn new(node: &Node, content: &[u8]) -> Result<CompanionObject> {
+        let mut modifiers = Vec::new();
         let mut body = None;
         let mut cursor = node.walk();
-        for child in node.children(&mut cursor) {
+        for child in node.children(&mut cursor.clone()) {
             match child.kind() {
-                "companion" | "object" => {}
+                "companion" | "object" | "line_comment" | "multiline_comment" => {}
+                "modifiers" => {
+                    for child in child.children(&mut cursor) {
+                        modifiers.push(Modifier::new(&child, content)?);
+                    }
+                }
                 "class_body" => body = Some(ClassBody::new_class_body(&child, content)?),
                 _ => {
                     bail!(
@@ -252,6 +382,7 @@ impl CompanionObject {
         }
 
         Ok(CompanionObject {
+            modifiers,
             body: body.context("no class body found")?,
         })
     }
@@ -265,11 +396,11 @@ pub enum ClassParameterMutability {
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub struct ClassParameter {
-    mutability: Option<ClassParameterMutability>,
-    name: String,
-    data_type: Type,
+    pub mutability: Option<ClassParameterMutability>,
+    pub name: String,
+    pub data_type: Type,
     modifiers: Vec<Modifier>,
-    expression: Option<Expression>,
+    default_value: Option<Expression>,
 }
 
 impl ClassParameter {
@@ -278,7 +409,7 @@ impl ClassParameter {
         let mut name = None;
         let mut data_type = None;
         let mut modifiers = Vec::new();
-        let mut expression = None;
+        let mut default_value = None;
         let mut cursor = node.walk();
         for child in node.children(&mut cursor.clone()) {
             match child.kind() {
@@ -295,7 +426,7 @@ impl ClassParameter {
                 }
                 ":" => {}
                 "=" => {
-                    expression = Some(Expression::new(
+                    default_value = Some(Expression::new(
                         &child.next_sibling().context(format!(
                             "[ClassParameter] no sibling at {}",
                             child.start_position()
@@ -326,15 +457,29 @@ impl ClassParameter {
                 node.start_position()
             ))?,
             modifiers,
-            expression,
+            default_value,
         })
     }
+
+    // e.g. "x: Int = …", the same as a function parameter
+    pub fn label(&self) -> String {
+        let default = if self.default_value.is_some() {
+            " = …"
+        } else {
+            ""
+        };
+        format!("{}: {}{default}", self.name, self.data_type)
+    }
+
+    pub fn has_default(&self) -> bool {
+        self.default_value.is_some()
+    }
 }
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub struct Constructor {
     modifiers: Vec<Modifier>,
-    parameters: Vec<ClassParameter>,
+    pub parameters: Vec<ClassParameter>,
 }
 
 impl Constructor {
@@ -344,7 +489,7 @@ impl Constructor {
         let mut cursor = node.walk();
         for child in node.children(&mut cursor.clone()) {
             match child.kind() {
-                "(" | "," | ")" | "constructor" | "line_comment" => {}
+                "(" | "," | ")" | "constructor" | "line_comment" | "multiline_comment" => {}
                 "modifiers" => {
                     for child in child.children(&mut cursor) {
                         modifiers.push(Modifier::new(&child, content)?);
@@ -374,21 +519,71 @@ pub enum ClassType {
     Class,
     Interface,
     Enum,
+    Sealed,
+    SealedInterface,
+    Annotation,
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone)]
+pub struct SynthesizedFunction {
+    pub name: String,
+    pub parameters: Vec<Parameter>,
+    pub return_type: Type,
+}
+
+impl SynthesizedFunction {
+    // e.g. "fun copy(x: Int): Point"
+    pub fn signature(&self) -> String {
+        let parameters: Vec<String> = self.parameters.iter().map(Parameter::label).collect();
+        format!(
+            "fun {}({}): {}",
+            self.name,
+            parameters.join(", "),
+            self.return_type
+        )
+    }
 }
 
+/// A class, interface, enum class or annotation class declaration.
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub struct Class {
     pub class_type: ClassType,
     pub name: String,
+    pub name_span: Span,
     pub modifiers: Vec<Modifier>,
     pub type_parameters: Vec<TypeParameter>,
     pub constructor: Option<Constructor>,
     pub delegations: Vec<Delegation>,
     pub body: Option<ClassBody>,
+    // direct subclasses in the same file, only filled for sealed classes
+    pub subclasses: Vec<String>,
+    pub span: Span,
+    pub kdoc: Option<KDoc>,
 }
 
 impl Class {
-    fn new(node: &Node, content: &[u8]) -> Result<Class> {
+    pub fn walk_declarations<'a>(&'a self, f: &mut impl FnMut(Declaration<'a>)) {
+        f(Declaration::Class(self));
+        if let Some(body) = &self.body {
+            body.walk_declarations(f);
+        }
+    }
+
+    pub fn walk_expressions<'a>(&'a self, f: &mut impl FnMut(&'a Expression)) {
+        if let Some(constructor) = &self.constructor {
+            for expression in constructor.parameters.iter().flat_map(|p| &p.default_value) {
+                expression.walk_expressions(f);
+            }
+        }
+        for delegation in &self.delegations {
+            delegation.walk_expressions(f);
+        }
+        if let Some(body) = &self.body {
+            body.walk_expressions(f);
+        }
+    }
+
+    pub fn new(node: &Node, content: &[u8]) -> Result<Class> {
         let mut modifiers = Vec::new();
         let mut class_type = None;
         let mut name = None;
@@ -405,10 +600,13 @@ impl Class {
                         modifiers.push(Modifier::new(&child, content)?);
                     }
                 }
-                "class" => class_type = Some(ClassType::Class),
+                // `enum class` is an enum
+                "class" => class_type = class_type.or(Some(ClassType::Class)),
                 "interface" => class_type = Some(ClassType::Interface),
                 "enum" => class_type = Some(ClassType::Enum),
-                "type_identifier" => name = Some(child.utf8_text(content)?.to_string()),
+                "type_identifier" => {
+                    name = Some((child.utf8_text(content)?.to_string(), Span::new(&child)))
+                }
                 "primary_constructor" => constructor = Some(Constructor::new(&child, content)?),
                 "delegation_specifier" => delegations.push(Delegation::new(&child, content)?),
                 "class_body" => body = Some(ClassBody::new_class_body(&child, content)?),
@@ -431,83 +629,509 @@ impl Class {
             }
         }
 
+        let sealed = modifiers.contains(&Modifier::Class("sealed".to_string()));
+        let annotation = modifiers.contains(&Modifier::Class("annotation".to_string()));
+        let class_type = match class_type.context("[Class] no class type found")? {
+            ClassType::Class if annotation => ClassType::Annotation,
+            ClassType::Class if sealed => ClassType::Sealed,
+            ClassType::Interface if sealed => ClassType::SealedInterface,
+            class_type => class_type,
+        };
+
+        let (name, name_span) = name.context("[Class] no class name found")?;
         Ok(Class {
-            class_type: class_type.context("[Class] no class type found")?,
-            name: name.context("[Class] no class name found")?,
+            class_type,
+            name,
+            name_span,
             modifiers,
             type_parameters,
             delegations,
             constructor,
             body,
+            subclasses: Vec::new(),
+            span: Span::new(node),
+            kdoc: kdoc::get_kdoc(node, content)?,
         })
     }
+
+    pub fn is_data(&self) -> bool {
+        self.modifiers
+            .contains(&Modifier::Class("data".to_string()))
+    }
+
+    pub fn is_expect(&self) -> bool {
+        self.modifiers
+            .contains(&Modifier::Platform("expect".to_string()))
+    }
+
+    pub fn is_actual(&self) -> bool {
+        self.modifiers
+            .contains(&Modifier::Platform("actual".to_string()))
+    }
+
+    // members the compiler generates for a data class from its primary constructor
+    pub fn synthesized_functions(&self) -> Vec<SynthesizedFunction> {
+        if !self.is_data() {
+            return Vec::new();
+        }
+
+        let properties: Vec<&ClassParameter> = self
+            .constructor
+            .iter()
+            .flat_map(|c| &c.parameters)
+            .filter(|p| p.mutability.is_some())
+            .collect();
+
+        let mut functions: Vec<SynthesizedFunction> = properties
+            .iter()
+            .enumerate()
+            .map(|(i, p)| SynthesizedFunction {
+                name: format!("component{}", i + 1),
+                parameters: Vec::new(),
+                return_type: p.data_type.clone(),
+            })
+            .collect();
+
+        functions.push(SynthesizedFunction {
+            name: "copy".to_string(),
+            parameters: properties
+                .iter()
+                .map(|p| Parameter {
+                    name: p.name.clone(),
+                    span: self.name_span,
+                    type_identifier: p.data_type.clone(),
+                    vararg: false,
+                    modifiers: Vec::new(),
+                    default_value: None,
+                })
+                .collect(),
+            return_type: Type::NonNullable(Vec::new(), self.name.clone()),
+        });
+        functions.push(SynthesizedFunction {
+            name: "equals".to_string(),
+            parameters: vec![Parameter {
+                name: "other".to_string(),
+                span: self.name_span,
+                type_identifier: Type::Nullable(Vec::new(), "Any?".to_string()),
+                vararg: false,
+                modifiers: Vec::new(),
+                default_value: None,
+            }],
+            return_type: Type::NonNullable(Vec::new(), "Boolean".to_string()),
+        });
+        functions.push(SynthesizedFunction {
+            name: "hashCode".to_string(),
+            parameters: Vec::new(),
+            return_type: Type::NonNullable(Vec::new(), "Int".to_string()),
+        });
+        functions.push(SynthesizedFunction {
+            name: "toString".to_string(),
+            parameters: Vec::new(),
+            return_type: Type::NonNullable(Vec::new(), "String".to_string()),
+        });
+
+        functions
+    }
+
+    pub fn is_sealed(&self) -> bool {
+        matches!(
+            self.class_type,
+            ClassType::Sealed | ClassType::SealedInterface
+        )
+    }
+}
+
+// (supertype, subtype) pairs of the given classes and objects and everything nested in them
+fn collect_subtypes(classes: &[Class], objects: &[Object], subtypes: &mut Vec<(String, String)>) {
+    let mut add_subtype = |delegations: &[Delegation], name: &str| {
+        for delegation in delegations {
+            if let Some(supertype) = delegation.type_name() {
+                subtypes.push((supertype.to_string(), name.to_string()));
+            }
+        }
+    };
+
+    for class in classes {
+        add_subtype(&class.delegations, &class.name);
+    }
+    for object in objects {
+        add_subtype(&object.delegations, &object.name);
+    }
+
+    let bodies = classes
+        .iter()
+        .filter_map(|c| c.body.as_ref())
+        .chain(objects.iter().filter_map(|o| o.class_body.as_ref()));
+    for body in bodies {
+        let (ClassBody::Class {
+            classes, objects, ..
+        }
+        | ClassBody::Enum {
+            classes, objects, ..
+        }) = body;
+        collect_subtypes(classes, objects, subtypes);
+    }
+}
+
+fn set_subclasses(classes: &mut [Class], subtypes: &[(String, String)]) {
+    for class in classes {
+        if class.is_sealed() {
+            class.subclasses = subtypes
+                .iter()
+                .filter(|(supertype, _)| *supertype == class.name)
+                .map(|(_, subtype)| subtype.clone())
+                .collect();
+        }
+
+        if let Some(ClassBody::Class { classes, .. } | ClassBody::Enum { classes, .. }) =
+            &mut class.body
+        {
+            set_subclasses(classes, subtypes);
+        }
+    }
+}
+
+pub fn set_sealed_subclasses(classes: &mut [Class], objects: &[Object]) {
+    let mut subtypes = Vec::new();
+    collect_subtypes(classes, objects, &mut subtypes);
+    set_subclasses(classes, &subtypes);
 }
 
 pub fn get_classes(tree: &Tree, content: &[u8]) -> Result<Vec<Class>> {
     let mut classes = Vec::new();
-    let mut cursor = tree.walk();
-    loop {
-        let node = cursor.node();
-        if node.kind() == "class_declaration" {
-            classes.push(Class::new(&node, content)?);
+    let root = tree.root_node();
+    let mut cursor = root.walk();
+    for child in root.children(&mut cursor) {
+        if child.kind() == "class_declaration" {
+            classes.push(Class::new(&child, content)?);
         }
+    }
 
-        if cursor.goto_first_child() {
-            continue;
-        }
+    Ok(classes)
+}
 
-        loop {
-            if cursor.goto_next_sibling() {
-                break;
-            }
+/// The `this(...)` or `super(...)` call after the parameters of a secondary constructor.
+#[derive(Debug, Hash, PartialEq, Eq, Clone)]
+pub enum ConstructorDelegationCall {
+    This(Vec<Argument>),
+    Super(Vec<Argument>),
+}
 
-            if !cursor.goto_parent() {
-                return Ok(classes);
+impl ConstructorDelegationCall {
+    fn new(node: &Node, content: &[u8]) -> Result<ConstructorDelegationCall> {
+        let mut this = None;
+        let mut arguments = Vec::new();
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            match child.kind() {
+                "this" => this = Some(true),
+                "super" => this = Some(false),
+                "value_arguments" => arguments = argument::get_value_arguments(&child, content)?,
+                _ => {
+                    bail!(
+                        "[ConstructorDelegationCall] unhandled child {} '{}' at {}",
+                        child.kind(),
+                        child.utf8_text(content)?,
+                        child.start_position(),
+                    )
+                }
             }
         }
+
+        match this.context(format!(
+            "[ConstructorDelegationCall] no this or super at {}",
+            node.start_position()
+        ))? {
+            true => Ok(ConstructorDelegationCall::This(arguments)),
+            false => Ok(ConstructorDelegationCall::Super(arguments)),
+        }
     }
 }
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub struct SecondaryConstructor {
     pub parameters: Vec<Parameter>,
+    pub delegation: Option<ConstructorDelegationCall>,
     pub block: Vec<Statement>,
 }
 
 impl SecondaryConstructor {
     fn new(node: &Node, content: &[u8]) -> Result<SecondaryConstructor> {
         let mut parameters = Vec::new();
+        let mut delegation = None;
         let mut block = Vec::new();
         let mut cursor = node.walk();
         for child in node.children(&mut cursor) {
             match child.kind() {
                 "statements" => block = statement::get_statements(&child, content)?,
                 "function_value_parameters" => {
-                    let mut cursor = child.walk();
-                    for child in child.children(&mut cursor) {
-                        if child.kind() == "parameter" {
-                            parameters.push(Parameter {
-                                name: child
-                                    .child(0)
-                                    .context("no parameter name found")?
-                                    .utf8_text(content)?
-                                    .to_string(),
-                                type_identifier: Type::new(
-                                    &child
-                                        .child(2)
-                                        .filter(|c| c.kind() != "type_modifiers")
-                                        .or_else(|| child.child(3))
-                                        .context("no type identifier found")?,
-                                    content,
-                                )?,
-                            })
-                        }
-                    }
+                    parameters = function::get_parameters(&child, content)?
+                }
+                "constructor_delegation_call" => {
+                    delegation = Some(ConstructorDelegationCall::new(&child, content)?)
                 }
                 _ => {}
             }
         }
 
-        Ok(SecondaryConstructor { parameters, block })
+        Ok(SecondaryConstructor {
+            parameters,
+            delegation,
+            block,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kotlin::{
+        function::FunctionModifier,
+        test_utils::{find_node, parse},
+        types::Type,
+        KotlinFile,
+    };
+
+    use super::{
+        AnonymousInitializer, Class, ClassBody, ClassType, ConstructorDelegationCall,
+        SecondaryConstructor,
+    };
+
+    #[test]
+    fn sealed_hierarchy() {
+        let content = "sealed class Result {\n    class Ok(val value: Int) : Result()\n    object Failed : Result()\n}\nsealed interface Shape\nclass Circle : Shape\nobject Empty : Shape\nclass Other : Circle()\n";
+        let file: KotlinFile = content.parse().unwrap();
+
+        let sealed: Vec<(&str, &ClassType, &[String])> = file
+            .classes
+            .iter()
+            .filter(|class| class.is_sealed())
+            .map(|class| {
+                (
+                    class.name.as_str(),
+                    &class.class_type,
+                    class.subclasses.as_slice(),
+                )
+            })
+            .collect();
+        // only direct subclasses
+        assert_eq!(
+            sealed,
+            vec![
+                (
+                    "Result",
+                    &ClassType::Sealed,
+                    &["Ok".to_string(), "Failed".to_string()][..]
+                ),
+                (
+                    "Shape",
+                    &ClassType::SealedInterface,
+                    &["Circle".to_string(), "Empty".to_string()][..]
+                ),
+            ]
+        );
+        assert!(file.classes[2].subclasses.is_empty());
+    }
+
+    #[test]
+    fn data_class_members() {
+        let content = "data class Point(val x: Int, var y: String?, z: Long)";
+        let tree = parse(content);
+        let node = find_node(&tree, "class_declaration");
+
+        let class = Class::new(&node, content.as_bytes()).unwrap();
+        assert!(class.is_data());
+        let functions: Vec<(String, Vec<String>, Type)> = class
+            .synthesized_functions()
+            .into_iter()
+            .map(|function| {
+                (
+                    function.name,
+                    function
+                        .parameters
+                        .into_iter()
+                        .map(|parameter| parameter.name)
+                        .collect(),
+                    function.return_type,
+                )
+            })
+            .collect();
+        // `z` is only a constructor parameter
+        assert_eq!(
+            functions,
+            vec![
+                (
+                    "component1".to_string(),
+                    Vec::new(),
+                    Type::NonNullable(Vec::new(), "Int".to_string())
+                ),
+                (
+                    "component2".to_string(),
+                    Vec::new(),
+                    Type::Nullable(Vec::new(), "String?".to_string())
+                ),
+                (
+                    "copy".to_string(),
+                    vec!["x".to_string(), "y".to_string()],
+                    Type::NonNullable(Vec::new(), "Point".to_string())
+                ),
+                (
+                    "equals".to_string(),
+                    vec!["other".to_string()],
+                    Type::NonNullable(Vec::new(), "Boolean".to_string())
+                ),
+                (
+                    "hashCode".to_string(),
+                    Vec::new(),
+                    Type::NonNullable(Vec::new(), "Int".to_string())
+                ),
+                (
+                    "toString".to_string(),
+                    Vec::new(),
+                    Type::NonNullable(Vec::new(), "String".to_string())
+                ),
+            ]
+        );
+
+        let content = "class Point(val x: Int)";
+        let tree = parse(content);
+        let node = find_node(&tree, "class_declaration");
+        assert!(Class::new(&node, content.as_bytes())
+            .unwrap()
+            .synthesized_functions()
+            .is_empty());
+    }
+
+    #[test]
+    fn anonymous_initializers() {
+        let content = "class A {\n    init {}\n    init {\n        val x = 1\n    }\n}";
+        let tree = parse(content);
+        let body = find_node(&tree, "class_body");
+
+        let mut cursor = body.walk();
+        let statements: Vec<usize> = body
+            .children(&mut cursor)
+            .filter(|child| child.kind() == "anonymous_initializer")
+            .map(|child| {
+                AnonymousInitializer::new(&child, content.as_bytes())
+                    .unwrap()
+                    .statements
+                    .len()
+            })
+            .collect();
+        assert_eq!(statements, vec![0, 1]);
+    }
+
+    #[test]
+    fn constructor_delegation_call() {
+        let content = "class A {\n    constructor(x: Int) : this(x, 1) {}\n}";
+        let tree = parse(content);
+        let node = find_node(&tree, "secondary_constructor");
+
+        let constructor = SecondaryConstructor::new(&node, content.as_bytes()).unwrap();
+        assert!(matches!(
+            constructor.delegation,
+            Some(ConstructorDelegationCall::This(ref arguments)) if arguments.len() == 2
+        ));
+    }
+
+    #[test]
+    fn super_delegation_call() {
+        let content = "class A : B {\n    constructor() : super()\n    constructor(x: Int)\n}";
+        let tree = parse(content);
+        let body = find_node(&tree, "class_body");
+
+        let mut cursor = body.walk();
+        let delegations: Vec<Option<ConstructorDelegationCall>> = body
+            .children(&mut cursor)
+            .filter(|child| child.kind() == "secondary_constructor")
+            .map(|child| {
+                SecondaryConstructor::new(&child, content.as_bytes())
+                    .unwrap()
+                    .delegation
+            })
+            .collect();
+        assert_eq!(
+            delegations,
+            vec![Some(ConstructorDelegationCall::Super(Vec::new())), None]
+        );
+    }
+
+    #[test]
+    fn nested_type_alias() {
+        let content = "class Foo {\n    typealias Bar = Int\n    val x: Bar = 1\n}";
+        let tree = parse(content);
+        let node = find_node(&tree, "class_body");
+
+        let body = ClassBody::new_class_body(&node, content.as_bytes()).unwrap();
+        let ClassBody::Class {
+            type_aliases,
+            properties,
+            ..
+        } = body
+        else {
+            panic!("not a class body");
+        };
+        assert_eq!(type_aliases.len(), 1);
+        assert_eq!(type_aliases[0].name, "Bar");
+        assert_eq!(properties.len(), 1);
+    }
+
+    #[test]
+    fn abstract_enum_function() {
+        let content = "enum class Op {\n    ADD {\n        override fun apply(a: Int, b: Int) = a + b\n    };\n\n    abstract fun apply(a: Int, b: Int): Int\n}";
+        let tree = parse(content);
+        let node = find_node(&tree, "class_declaration");
+
+        let class = Class::new(&node, content.as_bytes()).unwrap();
+        let Some(ClassBody::Enum {
+            entries, functions, ..
+        }) = class.body
+        else {
+            panic!("not an enum body");
+        };
+        assert_eq!(functions.len(), 1);
+        assert_eq!(
+            functions[0].modifiers,
+            vec![FunctionModifier::Inheritance("abstract".to_string())]
+        );
+        assert!(functions[0].body.is_none());
+
+        let entry_functions = entries[0].class_body.as_ref().unwrap().functions();
+        assert_eq!(entry_functions.len(), 1);
+        assert!(entry_functions[0].body.is_some());
+    }
+
+    #[test]
+    fn enum_entries_with_trailing_semicolon() {
+        let content = "enum class A {\n    X,\n    Y,\n    ;\n\n    fun f() {}\n}";
+        let tree = parse(content);
+        let node = find_node(&tree, "class_declaration");
+
+        let class = Class::new(&node, content.as_bytes()).unwrap();
+        let Some(ClassBody::Enum {
+            entries, functions, ..
+        }) = class.body
+        else {
+            panic!("not an enum body");
+        };
+        let names: Vec<&str> = entries
+            .iter()
+            .map(|entry| entry.identifier.as_str())
+            .collect();
+        assert_eq!(names, vec!["X", "Y"]);
+        assert_eq!(functions.len(), 1);
+    }
+
+    #[test]
+    fn enum_entry_without_identifier() {
+        let content = "enum class A {\n    @Foo\n}";
+        let tree = parse(content);
+        let node = find_node(&tree, "class_declaration");
+
+        let class = Class::new(&node, content.as_bytes()).unwrap();
+        assert!(matches!(
+            class.body,
+            Some(ClassBody::Enum { ref entries, .. }) if entries.is_empty()
+        ));
     }
 }
@@ -8,8 +8,8 @@ use super::{
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub struct ConstructorInvocation {
-    data_type: Type,
-    arguments: Vec<Argument>,
+    pub data_type: Type,
+    pub arguments: Vec<Argument>,
 }
 
 impl ConstructorInvocation {
@@ -19,6 +19,7 @@ impl ConstructorInvocation {
         let mut cursor = node.walk();
         for child in node.children(&mut cursor) {
             match child.kind() {
+                "line_comment" | "multiline_comment" => {}
                 "user_type" => data_type = Some(Type::new(&child, content)?),
                 "value_arguments" => {
                     arguments = Some(argument::get_value_arguments(&child, content)?)
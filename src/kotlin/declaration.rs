@@ -0,0 +1,236 @@
+use super::{
+    class::{Class, ClassParameter, ClassType},
+    delegation::Delegation,
+    function::{Function, Parameter},
+    kdoc::KDoc,
+    object::Object,
+    property::{Property, PropertyMutability},
+    span::Span,
+    type_alias::TypeAlias,
+    types::TypeParameter,
+    variable_declaration::VariableDeclaration,
+};
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub enum Declaration<'a> {
+    Class(&'a Class),
+    Object(&'a Object),
+    Function(&'a Function),
+    // a destructuring property yields one declaration per variable
+    Property(&'a Property, &'a VariableDeclaration),
+    TypeAlias(&'a TypeAlias),
+}
+
+impl<'a> Declaration<'a> {
+    pub fn name(&self) -> &'a str {
+        match self {
+            Declaration::Class(class) => &class.name,
+            Declaration::Object(object) => &object.name,
+            Declaration::Function(function) => &function.name,
+            Declaration::Property(_, declaration) => &declaration.identifier,
+            Declaration::TypeAlias(alias) => &alias.name,
+        }
+    }
+
+    pub fn name_span(&self) -> Span {
+        match self {
+            Declaration::Class(class) => class.name_span,
+            Declaration::Object(object) => object.name_span,
+            Declaration::Function(function) => function.name_span,
+            Declaration::Property(_, declaration) => declaration.identifier_span,
+            Declaration::TypeAlias(alias) => alias.name_span,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            Declaration::Class(class) => class.span,
+            Declaration::Object(object) => object.span,
+            Declaration::Function(function) => function.span,
+            Declaration::Property(property, _) => property.span,
+            Declaration::TypeAlias(alias) => alias.span,
+        }
+    }
+
+    // names of the classes and interfaces a class or object extends or implements
+    pub fn supertypes(&self) -> Vec<&'a str> {
+        let delegations = match self {
+            Declaration::Class(class) => &class.delegations,
+            Declaration::Object(object) => &object.delegations,
+            Declaration::Function(_) | Declaration::Property(..) | Declaration::TypeAlias(_) => {
+                return Vec::new()
+            }
+        };
+
+        delegations
+            .iter()
+            .filter_map(|delegation| delegation.type_name())
+            .collect()
+    }
+
+    pub fn kdoc(&self) -> Option<&'a KDoc> {
+        match self {
+            Declaration::Class(class) => class.kdoc.as_ref(),
+            Declaration::Object(object) => object.kdoc.as_ref(),
+            Declaration::Function(function) => function.kdoc.as_ref(),
+            Declaration::Property(property, _) => property.kdoc.as_ref(),
+            Declaration::TypeAlias(alias) => alias.kdoc.as_ref(),
+        }
+    }
+
+    // short kotlin-like header, e.g. "sealed interface Foo : Bar", "fun String.foo(x: Int): Int",
+    // "typealias Foo<T> = Bar<T>" or "val bar"
+    pub fn signature(&self) -> String {
+        match self {
+            Declaration::Class(class) => {
+                let keyword = match class.class_type {
+                    ClassType::Class => "class",
+                    ClassType::Interface => "interface",
+                    ClassType::Enum => "enum class",
+                    ClassType::Sealed => "sealed class",
+                    ClassType::SealedInterface => "sealed interface",
+                    ClassType::Annotation => "annotation class",
+                };
+                let header = if class.type_parameters.is_empty() {
+                    format!("{keyword} {}", class.name)
+                } else {
+                    format!(
+                        "{keyword} {}<{}>",
+                        class.name,
+                        type_parameter_list(&class.type_parameters)
+                    )
+                };
+                format!("{header}{}", supertype_list(&class.delegations))
+            }
+            Declaration::Object(object) => {
+                format!(
+                    "object {}{}",
+                    object.name,
+                    supertype_list(&object.delegations)
+                )
+            }
+            Declaration::Function(function) => {
+                let mut keywords: Vec<&str> = function
+                    .modifiers
+                    .iter()
+                    .filter_map(|modifier| modifier.keyword())
+                    .collect();
+                keywords.push("fun");
+                let context = if function.context_receivers.is_empty() {
+                    String::new()
+                } else {
+                    let receivers: Vec<String> = function
+                        .context_receivers
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect();
+                    format!("context({}) ", receivers.join(", "))
+                };
+                let type_parameters = if function.type_parameters.is_empty() {
+                    String::new()
+                } else {
+                    format!("<{}> ", type_parameter_list(&function.type_parameters))
+                };
+                let receiver = function
+                    .receiver_type
+                    .as_ref()
+                    .map(|receiver| format!("{receiver}."))
+                    .unwrap_or_default();
+                let parameters: Vec<String> =
+                    function.parameters.iter().map(Parameter::label).collect();
+                let mut signature = format!(
+                    "{context}{} {type_parameters}{receiver}{}({})",
+                    keywords.join(" "),
+                    function.name,
+                    parameters.join(", ")
+                );
+                if let Some(return_type) = &function.return_type {
+                    signature.push_str(&format!(": {return_type}"));
+                }
+                signature
+            }
+            Declaration::Property(property, declaration) => {
+                let mut keywords: Vec<&str> = property
+                    .property_modifiers()
+                    .iter()
+                    .map(|modifier| modifier.keyword())
+                    .collect();
+                keywords.push(match property.mutability {
+                    PropertyMutability::Val => "val",
+                    PropertyMutability::Var => "var",
+                });
+                format!("{} {}", keywords.join(" "), declaration.identifier)
+            }
+            Declaration::TypeAlias(alias) => {
+                if alias.type_parameters.is_empty() {
+                    format!("typealias {} = {}", alias.name, alias.aliased_type)
+                } else {
+                    format!(
+                        "typealias {}<{}> = {}",
+                        alias.name,
+                        type_parameter_list(&alias.type_parameters),
+                        alias.aliased_type
+                    )
+                }
+            }
+        }
+    }
+
+    // label and parameter labels of a call to this declaration, e.g.
+    // "fun foo(x: Int, y: Int = …): String" and [("x: Int", false), ("y: Int = …", true)]
+    // where the flag tells whether the parameter has a default value. Classes are called
+    // through their primary constructor
+    pub fn call_signature(&self) -> Option<(String, Vec<(String, bool)>)> {
+        match self {
+            Declaration::Function(function) => Some((
+                self.signature(),
+                function
+                    .parameters
+                    .iter()
+                    .map(|parameter| (parameter.label(), parameter.default_value.is_some()))
+                    .collect(),
+            )),
+            Declaration::Class(class) if class.class_type == ClassType::Class => {
+                let parameters: Vec<&ClassParameter> = class
+                    .constructor
+                    .iter()
+                    .flat_map(|constructor| &constructor.parameters)
+                    .collect();
+                let labels: Vec<String> = parameters.iter().map(|p| p.label()).collect();
+                Some((
+                    format!("{}({})", class.name, labels.join(", ")),
+                    labels
+                        .into_iter()
+                        .zip(parameters)
+                        .map(|(label, parameter)| (label, parameter.has_default()))
+                        .collect(),
+                ))
+            }
+            _ => None,
+        }
+    }
+}
+
+// e.g. " : Foo<Int>, Bar" for a class that extends Foo and implements Bar
+fn supertype_list(delegations: &[Delegation]) -> String {
+    if delegations.is_empty() {
+        return String::new();
+    }
+
+    let supertypes: Vec<String> = delegations
+        .iter()
+        .map(|delegation| match delegation {
+            Delegation::Type(data_type) => data_type.to_string(),
+            Delegation::ConstructorInvocation(invocation) => invocation.data_type.to_string(),
+        })
+        .collect();
+    format!(" : {}", supertypes.join(", "))
+}
+
+fn type_parameter_list(type_parameters: &[TypeParameter]) -> String {
+    type_parameters
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<String>>()
+        .join(", ")
+}
@@ -1,7 +1,7 @@
 use anyhow::{bail, Context, Result};
 use tree_sitter::Node;
 
-use super::{constructor_invocation::ConstructorInvocation, types::Type};
+use super::{constructor_invocation::ConstructorInvocation, expression::Expression, types::Type};
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub enum Delegation {
@@ -27,4 +27,25 @@ impl Delegation {
             }
         }
     }
+
+    pub fn walk_expressions<'a>(&'a self, f: &mut impl FnMut(&'a Expression)) {
+        if let Delegation::ConstructorInvocation(invocation) = self {
+            for argument in &invocation.arguments {
+                argument.walk_expressions(f);
+            }
+        }
+    }
+
+    // name of the delegated type without type arguments
+    pub fn type_name(&self) -> Option<&str> {
+        let data_type = match self {
+            Delegation::Type(data_type) => data_type,
+            Delegation::ConstructorInvocation(invocation) => &invocation.data_type,
+        };
+
+        match data_type {
+            Type::NonNullable(_, name) | Type::Nullable(_, name) => name.split('<').next(),
+            Type::Function { .. } => None,
+        }
+    }
 }
@@ -22,7 +22,14 @@ pub fn expression(node: &Node, content: &[u8]) -> Result<Expression> {
                 ))?,
                 content,
             )?)),
-            "return" => Expression::JumpReturn(None, None),
+            "return" => Expression::JumpReturn(
+                None,
+                if let Some(child) = &node.child(1) {
+                    Some(Box::new(Expression::new(child, content)?))
+                } else {
+                    None
+                },
+            ),
             "return@" => Expression::JumpReturn(
                 Some(Label::new(
                     &node.child(1).context(format!(
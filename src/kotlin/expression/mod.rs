@@ -1,15 +1,17 @@
 use anyhow::{bail, Context, Result};
-use tree_sitter::Node;
+use tree_sitter::{Node, Point};
 
 use self::r#try::{CatchBlock, FinallyBlock};
 
 use super::{
     argument::{self, Argument},
+    function::{self, FunctionBody, Parameter},
     label::Label,
     lambda::AnnotatedLambda,
     literal::Literal,
+    span::Span,
     statement::{self, Statement},
-    types::Type,
+    types::{Type, TYPES},
 };
 
 mod jump;
@@ -144,11 +146,13 @@ impl EqualityOperator {
     }
 }
 
+/// Any kotlin expression, nested expressions are boxed.
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub enum Expression {
     Call {
         expression: Box<Expression>,
         call_suffix: CallSuffix,
+        span: Span,
     },
     Navigation {
         expression: Box<Expression>,
@@ -182,6 +186,7 @@ pub enum Expression {
     },
     Identifier {
         identifier: String,
+        span: Span,
     },
     Infix {
         left: Box<Expression>,
@@ -256,8 +261,20 @@ pub enum Expression {
     This {
         identifier: Option<String>,
     },
-    Super,
+    // `super<Base>` names the supertype with its span, `super@Outer` the label
+    Super {
+        qualifier: Option<(String, Span)>,
+        label: Option<String>,
+    },
     Spread(Box<Expression>),
+    // `[a, b]`, only valid in annotation arguments
+    Collection(Vec<Expression>),
+    AnonymousFunction {
+        receiver_type: Option<Type>,
+        parameters: Vec<Parameter>,
+        return_type: Option<Type>,
+        body: FunctionBody,
+    },
 }
 
 impl Expression {
@@ -276,6 +293,7 @@ impl Expression {
             "postfix_expression" => postfix_expression(node, content),
             "simple_identifier" => Ok(Expression::Identifier {
                 identifier: node.utf8_text(content)?.to_string(),
+                span: Span::new(node),
             }),
             "try_expression" => r#try::expression(node, content),
             "infix_expression" => infix_expression(node, content),
@@ -283,7 +301,7 @@ impl Expression {
             "elvis_expression" => elvis_expression(node, content),
             "range_expression" => range_expression(node, content),
             "check_expression" => check_expression(node, content),
-            "super_expression" => Ok(Expression::Super),
+            "super_expression" => super_expression(node, content),
             "callable_reference" => callable_reference(node, content),
             "boolean_literal" | "string_literal" | "integer_literal" | "object_literal"
             | "character_literal" | "lambda_literal" | "long_literal" | "real_literal"
@@ -291,6 +309,16 @@ impl Expression {
             "when_expression" => when_expression(node, content),
             "user_type" => Ok(Expression::Type(Type::new(node, content)?)),
             "jump_expression" => jump::expression(node, content),
+            // `a.b = c` assigns to the navigation, not to `a`
+            "directly_assignable_expression"
+                if node
+                    .child(1)
+                    .is_some_and(|child| child.kind() == "navigation_suffix") =>
+            {
+                Ok(Expression::DirectlyAssignable(Box::new(
+                    navigation_expression(node, content)?,
+                )))
+            }
             "directly_assignable_expression" => {
                 Ok(Expression::DirectlyAssignable(Box::new(Expression::new(
                     &node.child(0).context(format!(
@@ -310,6 +338,8 @@ impl Expression {
             "indexing_expression" => indexing_expression(node, content),
             "this_expression" => this_expression(node, content),
             "spread_expression" => spread_expression(node, content),
+            "anonymous_function" => anonymous_function(node, content),
+            "collection_literal" => collection_literal(node, content),
             _ => {
                 bail!(
                     "[Expression] unhandled child {} '{}' at {}",
@@ -320,12 +350,134 @@ impl Expression {
             }
         }
     }
+
+    // name of the called function and the arguments it is called with
+    pub fn call(&self) -> Option<(&str, &CallSuffix)> {
+        match self {
+            Expression::Call {
+                expression,
+                call_suffix,
+                ..
+            } => expression
+                .identifier()
+                .map(|(identifier, _)| (identifier, call_suffix)),
+            _ => None,
+        }
+    }
+
+    // name and position of the identifier this expression refers to, if any
+    pub fn identifier(&self) -> Option<(&str, Span)> {
+        match self {
+            Expression::Identifier { identifier, span } => Some((identifier, *span)),
+            Expression::Navigation {
+                navigation_suffix, ..
+            } => Some((&navigation_suffix.identifier, navigation_suffix.span)),
+            Expression::Super {
+                qualifier: Some((qualifier, span)),
+                ..
+            } => Some((qualifier, *span)),
+            _ => None,
+        }
+    }
+
+    // visits this expression and all expressions nested in it, parents first
+    pub fn walk_expressions<'a>(&'a self, f: &mut impl FnMut(&'a Expression)) {
+        f(self);
+        match self {
+            Expression::Call {
+                expression,
+                call_suffix,
+                ..
+            } => {
+                expression.walk_expressions(f);
+                call_suffix.walk_expressions(f);
+            }
+            Expression::Navigation { expression, .. }
+            | Expression::Prefix { expression, .. }
+            | Expression::Postfix { expression, .. }
+            | Expression::CheckIs {
+                left: expression, ..
+            }
+            | Expression::CheckNotIs {
+                left: expression, ..
+            }
+            | Expression::JumpThrow(expression)
+            | Expression::JumpReturn(_, Some(expression))
+            | Expression::DirectlyAssignable(expression)
+            | Expression::Parenthesized(expression)
+            | Expression::Spread(expression) => expression.walk_expressions(f),
+            Expression::If { expression, body } => {
+                expression.walk_expressions(f);
+                body.walk_expressions(f);
+            }
+            Expression::Equality { left, right, .. }
+            | Expression::Multiplicative { left, right, .. }
+            | Expression::Disjunction { left, right }
+            | Expression::Conjunction { left, right }
+            | Expression::Additive { left, right }
+            | Expression::Infix { left, right, .. }
+            | Expression::As { left, right }
+            | Expression::CheckIn { left, right }
+            | Expression::CheckNotIn { left, right }
+            | Expression::Elvis { left, right }
+            | Expression::Range { left, right }
+            | Expression::Comparison { left, right, .. } => {
+                left.walk_expressions(f);
+                right.walk_expressions(f);
+            }
+            Expression::Literal(literal) => literal.walk_expressions(f),
+            Expression::When { subject, entries } => {
+                if let Some(subject) = subject {
+                    subject.expression.walk_expressions(f);
+                }
+                for entry in entries {
+                    entry.walk_expressions(f);
+                }
+            }
+            Expression::Try {
+                block,
+                catch_blocks,
+                finally_block,
+            } => {
+                let catch_statements = catch_blocks.iter().flat_map(|c| &c.block);
+                let finally_statements = finally_block.iter().flat_map(|f| &f.block);
+                for statement in block
+                    .iter()
+                    .chain(catch_statements)
+                    .chain(finally_statements)
+                {
+                    statement.walk_expressions(f);
+                }
+            }
+            Expression::Indexing(expression, suffix) => {
+                expression.walk_expressions(f);
+                for expression in &suffix.expressions {
+                    expression.walk_expressions(f);
+                }
+            }
+            Expression::AnonymousFunction { body, .. } => body.walk_expressions(f),
+            Expression::Collection(expressions) => {
+                for expression in expressions {
+                    expression.walk_expressions(f);
+                }
+            }
+            Expression::Identifier { .. }
+            | Expression::Type(_)
+            | Expression::JumpReturn(_, None)
+            | Expression::JumpContinue(_)
+            | Expression::JumpBreak(_)
+            | Expression::CallableReference { .. }
+            | Expression::This { .. }
+            | Expression::Super { .. } => {}
+        }
+    }
 }
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub struct CallSuffix {
     arguments: Option<Vec<Argument>>,
     annotated_lambda: Option<AnnotatedLambda>,
+    span: Span,
 }
 
 impl CallSuffix {
@@ -335,6 +487,7 @@ impl CallSuffix {
         let mut cursor = node.walk();
         for child in node.children(&mut cursor) {
             match child.kind() {
+                "line_comment" | "multiline_comment" => {}
                 "value_arguments" => {
                     arguments = Some(argument::get_value_arguments(&child, content)?)
                 }
@@ -360,8 +513,38 @@ impl CallSuffix {
         Ok(CallSuffix {
             arguments,
             annotated_lambda,
+            span: Span::new(node),
         })
     }
+
+    // `name:` in front of every positional argument, all arguments from a vararg on
+    // belong to it
+    pub fn parameter_hints(&self, parameters: &[Parameter]) -> Vec<(Point, String)> {
+        let mut hints = Vec::new();
+        for (argument, parameter) in self.arguments.iter().flatten().zip(parameters) {
+            if let Argument::Value {
+                identifier: None,
+                span,
+                ..
+            } = argument
+            {
+                hints.push((span.start, format!("{}:", parameter.name)));
+            }
+            if parameter.vararg {
+                break;
+            }
+        }
+        hints
+    }
+
+    pub fn walk_expressions<'a>(&'a self, f: &mut impl FnMut(&'a Expression)) {
+        for argument in self.arguments.iter().flatten() {
+            argument.walk_expressions(f);
+        }
+        if let Some(annotated_lambda) = &self.annotated_lambda {
+            annotated_lambda.walk_expressions(f);
+        }
+    }
 }
 
 fn call_expression(node: &Node, content: &[u8]) -> Result<Expression> {
@@ -380,32 +563,60 @@ fn call_expression(node: &Node, content: &[u8]) -> Result<Expression> {
             ))?,
             content,
         )?,
+        span: Span::new(node),
     })
 }
 
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub enum NavigationOperator {
+    Dot,
+    // `?.`, evaluates to null instead of throwing
+    SafeCall,
+    // `!!.`, throws when the receiver is null
+    NullAssertion,
+    // `::` of a bound callable reference
+    Reference,
+}
+
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub struct NavigationSuffix {
-    identifier: String,
+    operator: NavigationOperator,
+    pub identifier: String,
+    pub span: Span,
 }
 
 impl NavigationSuffix {
     pub fn new(node: &Node, content: &[u8]) -> Result<NavigationSuffix> {
+        let mut operator = NavigationOperator::Dot;
         let mut identifier = None;
         let mut cursor = node.walk();
         for child in node.children(&mut cursor) {
-            if child.kind() == "simple_identifier" {
-                identifier = Some(child.utf8_text(content)?.to_string());
+            match child.kind() {
+                "safe_nav" | "?." => operator = NavigationOperator::SafeCall,
+                "!!" | "!!." => operator = NavigationOperator::NullAssertion,
+                "::" => operator = NavigationOperator::Reference,
+                "simple_identifier" => {
+                    identifier = Some((child.utf8_text(content)?.to_string(), Span::new(&child)))
+                }
+                _ => {}
             }
         }
 
+        let (identifier, span) = identifier.context(format!(
+            "[NavigationSuffix] no identifier at {} - {}",
+            node.start_position(),
+            node.end_position()
+        ))?;
         Ok(NavigationSuffix {
-            identifier: identifier.context(format!(
-                "[NavigationSuffix] no identifier at {} - {}",
-                node.start_position(),
-                node.end_position()
-            ))?,
+            operator,
+            identifier,
+            span,
         })
     }
+
+    pub fn operator(&self) -> NavigationOperator {
+        self.operator
+    }
 }
 
 fn navigation_expression(node: &Node, content: &[u8]) -> Result<Expression> {
@@ -416,25 +627,40 @@ fn navigation_expression(node: &Node, content: &[u8]) -> Result<Expression> {
             suffix = Some(NavigationSuffix::new(&child, content)?);
         }
     }
-    Ok(Expression::Navigation {
-        expression: Box::new(Expression::new(
-            &node.child(0).context(format!(
-                "[Expression::Navigation] no expression found at {}",
-                node.start_position()
-            ))?,
-            content,
-        )?),
-        navigation_suffix: suffix.context(format!(
-            "[Expression::Navigation] no identifier at {} - {}",
-            node.start_position(),
-            node.end_position()
+    let mut navigation_suffix = suffix.context(format!(
+        "[Expression::Navigation] no identifier at {} - {}",
+        node.start_position(),
+        node.end_position()
+    ))?;
+
+    let expression = Expression::new(
+        &node.child(0).context(format!(
+            "[Expression::Navigation] no expression found at {}",
+            node.start_position()
         ))?,
+        content,
+    )?;
+    // the grammar parses `foo!!.bar` as `(foo!!).bar`
+    if let Expression::Postfix {
+        operator: PostfixUnaryOperator::NullAssertion,
+        ..
+    } = expression
+    {
+        if navigation_suffix.operator == NavigationOperator::Dot {
+            navigation_suffix.operator = NavigationOperator::NullAssertion;
+        }
+    }
+
+    Ok(Expression::Navigation {
+        expression: Box::new(expression),
+        navigation_suffix,
     })
 }
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub struct ControlStructureBody {
-    statements: Vec<Statement>,
+    pub statements: Vec<Statement>,
+    pub span: Span,
 }
 
 impl ControlStructureBody {
@@ -454,12 +680,21 @@ impl ControlStructureBody {
                     content,
                 )
                 .unwrap_or_default(),
+                span: Span::new(node),
             }),
+            // a single statement without braces is the only child
             _ => Ok(ControlStructureBody {
-                statements: statement::get_statements(&child, content).unwrap_or_default(),
+                statements: statement::get_statements(node, content).unwrap_or_default(),
+                span: Span::new(node),
             }),
         }
     }
+
+    pub fn walk_expressions<'a>(&'a self, f: &mut impl FnMut(&'a Expression)) {
+        for statement in &self.statements {
+            statement.walk_expressions(f);
+        }
+    }
 }
 
 fn if_expression(node: &Node, content: &[u8]) -> Result<Expression> {
@@ -644,27 +879,19 @@ fn callable_reference(node: &Node, content: &[u8]) -> Result<Expression> {
         node.start_position()
     ))?;
 
-    let (left, right) = match first_node.kind() {
-        "::" => (
-            None,
-            node.child(1)
-                .context(format!(
-                    "[Expression::CallableReference] too little children at {}",
-                    node.start_position()
-                ))?
-                .utf8_text(content)?
-                .to_string(),
-        ),
-        _ => (
-            Some(first_node.utf8_text(content)?.to_string()),
-            node.child(1)
-                .context(format!(
-                    "[Expression::CallableReference] too little children at {}",
-                    node.start_position()
-                ))?
-                .utf8_text(content)?
-                .to_string(),
-        ),
+    // the referenced name is always last, `Foo::bar`, `::bar` and `Foo::class`
+    let right = node
+        .child(node.child_count() - 1)
+        .filter(|last| last.kind() != "::")
+        .context(format!(
+            "[Expression::CallableReference] too little children at {}",
+            node.start_position()
+        ))?
+        .utf8_text(content)?
+        .to_string();
+    let left = match first_node.kind() {
+        "::" => None,
+        _ => Some(first_node.utf8_text(content)?.to_string()),
     };
     Ok(Expression::CallableReference { left, right })
 }
@@ -906,29 +1133,49 @@ impl WhenCondition {
         ))?;
 
         Ok(match child.kind() {
-            "range_test" => WhenCondition::RangeTest(Expression::new(
-                &child.child(1).context(format!(
-                    "[WhenCondition] no child 1 at {}",
-                    node.start_position(),
-                ))?,
-                content,
-            )?),
-            "type_test" => WhenCondition::TypeTest(Type::new(
-                &child.child(1).context(format!(
-                    "[WhenCondition] no child 1 at {}",
-                    node.start_position(),
-                ))?,
-                content,
-            )?),
+            "range_test" => range_test(&child, content)?,
+            "type_test" => type_test(&child, content)?,
             _ => WhenCondition::Expression(Expression::new(&child, content)?),
         })
     }
 }
 
+fn range_test(node: &Node, content: &[u8]) -> Result<WhenCondition> {
+    let mut expression = None;
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        match child.kind() {
+            "in" | "!in" | "line_comment" | "multiline_comment" => {}
+            _ => expression = Some(Expression::new(&child, content)?),
+        }
+    }
+
+    Ok(WhenCondition::RangeTest(expression.context(format!(
+        "[WhenCondition::RangeTest] no expression at {}",
+        node.start_position(),
+    ))?))
+}
+
+fn type_test(node: &Node, content: &[u8]) -> Result<WhenCondition> {
+    let mut data_type = None;
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        match child.kind() {
+            "is" | "!is" | "line_comment" | "multiline_comment" | "type_modifiers" => {}
+            _ => data_type = Some(Type::new(&child, content)?),
+        }
+    }
+
+    Ok(WhenCondition::TypeTest(data_type.context(format!(
+        "[WhenCondition::TypeTest] no type at {}",
+        node.start_position(),
+    ))?))
+}
+
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub struct WhenEntry {
     // condition is empty for "else" case
-    condition: Option<WhenCondition>,
+    condition: Option<Vec<WhenCondition>>,
     body: ControlStructureBody,
 }
 
@@ -939,7 +1186,9 @@ impl WhenEntry {
         let mut cursor = node.walk();
         for child in node.children(&mut cursor) {
             match child.kind() {
-                "when_condition" => condition = Some(WhenCondition::new(&child, content)?),
+                "when_condition" => condition
+                    .get_or_insert_with(Vec::new)
+                    .push(WhenCondition::new(&child, content)?),
                 "control_structure_body" => {
                     body = Some(ControlStructureBody::new(&child, content)?)
                 }
@@ -952,6 +1201,18 @@ impl WhenEntry {
             body: body.context(format!("[WhenEntry] no body at {}", node.start_position()))?,
         })
     }
+
+    fn walk_expressions<'a>(&'a self, f: &mut impl FnMut(&'a Expression)) {
+        for condition in self.condition.iter().flatten() {
+            match condition {
+                WhenCondition::Expression(expression) | WhenCondition::RangeTest(expression) => {
+                    expression.walk_expressions(f)
+                }
+                WhenCondition::TypeTest(_) => {}
+            }
+        }
+        self.body.walk_expressions(f);
+    }
 }
 
 fn when_expression(node: &Node, content: &[u8]) -> Result<Expression> {
@@ -999,7 +1260,7 @@ impl IndexingSuffix {
         let mut cursor = node.walk();
         for child in node.children(&mut cursor) {
             match child.kind() {
-                "[" | "," | "]" => {}
+                "[" | "," | "]" | "line_comment" | "multiline_comment" => {}
                 _ => expressions.push(Expression::new(&child, content)?),
             }
         }
@@ -1009,36 +1270,52 @@ impl IndexingSuffix {
 }
 
 fn this_expression(node: &Node, content: &[u8]) -> Result<Expression> {
-    Ok(
-        match node
-            .child(0)
-            .context(format!(
-                "[Expression::This] no child at {}",
-                node.start_position()
-            ))?
-            .kind()
-        {
-            "this" => Expression::This { identifier: None },
-            "this@" => Expression::This {
-                identifier: Some(
-                    node.child(1)
-                        .context(format!(
-                            "[Expression::This] no child at {}",
-                            node.start_position()
-                        ))?
-                        .utf8_text(content)?
-                        .to_string(),
-                ),
-            },
-            this => {
+    let mut identifier = None;
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        match child.kind() {
+            "this" | "this@" | "line_comment" | "multiline_comment" => {}
+            // the label is a type_identifier in the grammar, but any name can be one
+            "type_identifier" | "simple_identifier" => {
+                identifier = Some(child.utf8_text(content)?.to_string())
+            }
+            _ => {
                 bail!(
-                    "[Expression::This] unhandled this {} at {}",
-                    this,
-                    node.start_position(),
+                    "[Expression::This] unhandled child {} '{}' at {}",
+                    child.kind(),
+                    child.utf8_text(content)?,
+                    child.start_position(),
                 )
             }
-        },
-    )
+        }
+    }
+
+    Ok(Expression::This { identifier })
+}
+
+fn super_expression(node: &Node, content: &[u8]) -> Result<Expression> {
+    let mut qualifier = None;
+    let mut label = None;
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        match child.kind() {
+            "super" | "super@" | "<" | ">" | "@" => {}
+            "user_type" => {
+                qualifier = Some((child.utf8_text(content)?.to_string(), Span::new(&child)))
+            }
+            "type_identifier" => label = Some(child.utf8_text(content)?.to_string()),
+            _ => {
+                bail!(
+                    "[Expression::Super] unhandled child {} '{}' at {}",
+                    child.kind(),
+                    child.utf8_text(content)?,
+                    child.start_position(),
+                )
+            }
+        }
+    }
+
+    Ok(Expression::Super { qualifier, label })
 }
 
 fn spread_expression(node: &Node, content: &[u8]) -> Result<Expression> {
@@ -1050,3 +1327,394 @@ fn spread_expression(node: &Node, content: &[u8]) -> Result<Expression> {
         content,
     )?)))
 }
+
+fn collection_literal(node: &Node, content: &[u8]) -> Result<Expression> {
+    let mut expressions = Vec::new();
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        match child.kind() {
+            "[" | "]" | "," | "line_comment" | "multiline_comment" => {}
+            _ => expressions.push(Expression::new(&child, content)?),
+        }
+    }
+
+    Ok(Expression::Collection(expressions))
+}
+
+// `fun Receiver.(parameters): ReturnType { ... }`, the receiver is before the parameters
+fn anonymous_function(node: &Node, content: &[u8]) -> Result<Expression> {
+    let mut receiver_type = None;
+    let mut parameters = None;
+    let mut return_type = None;
+    let mut body = None;
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        match child.kind() {
+            "fun" | "." | ":" | "line_comment" | "multiline_comment" => {}
+            "function_value_parameters" => {
+                parameters = Some(function::get_parameters(&child, content)?)
+            }
+            "function_body" => body = Some(FunctionBody::new(&child, content)?),
+            kind if kind == "type_identifier" || TYPES.contains(&kind) => {
+                if parameters.is_none() {
+                    receiver_type = Some(Type::new(&child, content)?);
+                } else {
+                    return_type = Some(Type::new(&child, content)?);
+                }
+            }
+            _ => {
+                bail!(
+                    "[Expression::AnonymousFunction] unhandled child {} '{}' at {}",
+                    child.kind(),
+                    child.utf8_text(content)?,
+                    child.start_position(),
+                )
+            }
+        }
+    }
+
+    Ok(Expression::AnonymousFunction {
+        receiver_type,
+        parameters: parameters.context(format!(
+            "[Expression::AnonymousFunction] no parameters at {}",
+            node.start_position()
+        ))?,
+        return_type,
+        body: body.context(format!(
+            "[Expression::AnonymousFunction] no body at {}",
+            node.start_position()
+        ))?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tree_sitter::Point;
+
+    use crate::kotlin::{
+        function::Function,
+        literal::Literal,
+        span::Span,
+        test_utils::{find_node, parse},
+        types::{Type, TypeModifier},
+    };
+
+    use super::{Expression, FunctionBody, NavigationOperator, Statement, WhenCondition};
+
+    #[test]
+    fn check_not_is() {
+        let content = "val y = x !is String";
+        let tree = parse(content);
+        let node = find_node(&tree, "check_expression");
+
+        assert_eq!(
+            Expression::new(&node, content.as_bytes()).unwrap(),
+            Expression::CheckNotIs {
+                left: Box::new(Expression::Identifier {
+                    identifier: "x".to_string(),
+                    span: Span {
+                        start: Point::new(0, 8),
+                        end: Point::new(0, 9),
+                    },
+                }),
+                right: Type::NonNullable(Vec::new(), "String".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn check_not_in() {
+        let content = "val y = x !in list";
+        let tree = parse(content);
+        let node = find_node(&tree, "check_expression");
+
+        assert_eq!(
+            Expression::new(&node, content.as_bytes()).unwrap(),
+            Expression::CheckNotIn {
+                left: Box::new(Expression::Identifier {
+                    identifier: "x".to_string(),
+                    span: Span {
+                        start: Point::new(0, 8),
+                        end: Point::new(0, 9),
+                    },
+                }),
+                right: Box::new(Expression::Identifier {
+                    identifier: "list".to_string(),
+                    span: Span {
+                        start: Point::new(0, 14),
+                        end: Point::new(0, 18),
+                    },
+                }),
+            }
+        );
+    }
+
+    #[test]
+    fn when_multiple_conditions() {
+        let content = "val y = when (x) {\n    1, 2 -> a\n    else -> b\n}";
+        let tree = parse(content);
+        let node = find_node(&tree, "when_expression");
+
+        let Expression::When { entries, .. } = Expression::new(&node, content.as_bytes()).unwrap()
+        else {
+            panic!("expected when expression");
+        };
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries[0].condition,
+            Some(vec![
+                WhenCondition::Expression(Expression::Literal(Literal::Integer("1".to_string()))),
+                WhenCondition::Expression(Expression::Literal(Literal::Integer("2".to_string()))),
+            ])
+        );
+        assert_eq!(entries[1].condition, None);
+    }
+
+    #[test]
+    fn when_range_conditions() {
+        let content = "val y = when (x) {\n    in /* lower */ 0..limit(1) -> a\n    !in // none\n        list -> b\n    else -> c\n}";
+        let tree = parse(content);
+        let node = find_node(&tree, "when_expression");
+
+        let Expression::When { entries, .. } = Expression::new(&node, content.as_bytes()).unwrap()
+        else {
+            panic!("expected when expression");
+        };
+        assert!(matches!(
+            entries[0].condition.as_deref(),
+            Some([WhenCondition::RangeTest(Expression::Range { .. })])
+        ));
+        assert_eq!(
+            entries[1].condition,
+            Some(vec![WhenCondition::RangeTest(Expression::Identifier {
+                identifier: "list".to_string(),
+                span: Span {
+                    start: Point::new(3, 8),
+                    end: Point::new(3, 12),
+                },
+            })])
+        );
+    }
+
+    #[test]
+    fn when_type_conditions() {
+        let content = "val y = when (x) {\n    is /* map */ Map<String, List<Int>> -> a\n    !is Foo? -> b\n    is @Ann Bar -> c\n    else -> d\n}";
+        let tree = parse(content);
+        let node = find_node(&tree, "when_expression");
+
+        let Expression::When { entries, .. } = Expression::new(&node, content.as_bytes()).unwrap()
+        else {
+            panic!("expected when expression");
+        };
+        let conditions: Vec<_> = entries
+            .iter()
+            .filter_map(|entry| entry.condition.clone())
+            .collect();
+        assert_eq!(
+            conditions,
+            vec![
+                vec![WhenCondition::TypeTest(Type::NonNullable(
+                    Vec::new(),
+                    "Map<String, List<Int>>".to_string()
+                ))],
+                vec![WhenCondition::TypeTest(Type::Nullable(
+                    Vec::new(),
+                    "Foo?".to_string()
+                ))],
+                vec![WhenCondition::TypeTest(Type::NonNullable(
+                    vec![TypeModifier::Annotation("@Ann".to_string())],
+                    "Bar".to_string()
+                ))],
+            ]
+        );
+    }
+
+    #[test]
+    fn when_boolean_conditions() {
+        let content = "val y = when {\n    x > 0 && x < 10 -> \"small\"\n    x is Foo || x.bar > 0 -> \"foo\"\n    x < 0 -> \"neg\"\n    else -> \"zero\"\n}";
+        let tree = parse(content);
+        let node = find_node(&tree, "when_expression");
+
+        let Expression::When { subject, entries } =
+            Expression::new(&node, content.as_bytes()).unwrap()
+        else {
+            panic!("expected when expression");
+        };
+        assert_eq!(subject, None);
+        let conditions: Vec<Option<&WhenCondition>> = entries
+            .iter()
+            .map(|entry| entry.condition.as_ref().map(|conditions| &conditions[0]))
+            .collect();
+        assert!(matches!(
+            conditions[0],
+            Some(WhenCondition::Expression(Expression::Conjunction { .. }))
+        ));
+        assert!(matches!(
+            conditions[1],
+            Some(WhenCondition::Expression(Expression::Disjunction { .. }))
+        ));
+        assert!(matches!(
+            conditions[2],
+            Some(WhenCondition::Expression(Expression::Comparison { .. }))
+        ));
+        assert_eq!(conditions[3], None);
+    }
+
+    #[test]
+    fn when_entry_property_declaration() {
+        let content =
+            "val y = when (x) {\n    1 -> {\n        val z = 2\n        z\n    }\n    else -> 0\n}";
+        let tree = parse(content);
+        let node = find_node(&tree, "when_expression");
+
+        let Expression::When { entries, .. } = Expression::new(&node, content.as_bytes()).unwrap()
+        else {
+            panic!("expected when expression");
+        };
+        let statements = &entries[0].body.statements;
+        assert_eq!(statements.len(), 2);
+        let Statement::PropertyDeclaration(property) = &statements[0] else {
+            panic!("expected property declaration");
+        };
+        assert_eq!(property.names(), vec!["z"]);
+        assert_eq!(
+            property.expression,
+            Some(Expression::Literal(Literal::Integer("2".to_string())))
+        );
+    }
+
+    #[test]
+    fn anonymous_function() {
+        let content = "val f = fun Int.(x: Int): String { return \"\" }";
+        let tree = parse(content);
+        let node = find_node(&tree, "anonymous_function");
+
+        let Expression::AnonymousFunction {
+            receiver_type,
+            parameters,
+            return_type,
+            body,
+        } = Expression::new(&node, content.as_bytes()).unwrap()
+        else {
+            panic!("expected anonymous function");
+        };
+        assert_eq!(
+            receiver_type,
+            Some(Type::NonNullable(Vec::new(), "Int".to_string()))
+        );
+        assert_eq!(parameters.len(), 1);
+        assert_eq!(parameters[0].name, "x");
+        assert_eq!(
+            return_type,
+            Some(Type::NonNullable(Vec::new(), "String".to_string()))
+        );
+        assert!(matches!(body, FunctionBody::Block(statements) if statements.len() == 1));
+    }
+
+    #[test]
+    fn collection_literal() {
+        let content = "val x = [Foo::class, Bar::class]";
+        let tree = parse(content);
+        let node = find_node(&tree, "collection_literal");
+
+        assert_eq!(
+            Expression::new(&node, content.as_bytes()).unwrap(),
+            Expression::Collection(vec![
+                Expression::CallableReference {
+                    left: Some("Foo".to_string()),
+                    right: "class".to_string(),
+                },
+                Expression::CallableReference {
+                    left: Some("Bar".to_string()),
+                    right: "class".to_string(),
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn navigation_operators() {
+        let content = "fun f() {\n    a.b\n    a?.b\n    a!!.b\n}";
+        let tree = parse(content);
+        let node = find_node(&tree, "statements");
+
+        let statements =
+            crate::kotlin::statement::get_statements(&node, content.as_bytes()).unwrap();
+        let operators: Vec<NavigationOperator> = statements
+            .iter()
+            .map(|statement| match statement {
+                Statement::Expression(Expression::Navigation {
+                    navigation_suffix, ..
+                }) => navigation_suffix.operator(),
+                _ => panic!("expected a navigation"),
+            })
+            .collect();
+        assert_eq!(
+            operators,
+            vec![
+                NavigationOperator::Dot,
+                NavigationOperator::SafeCall,
+                NavigationOperator::NullAssertion,
+            ]
+        );
+    }
+
+    #[test]
+    fn super_qualifier() {
+        let content = "fun f() {\n    super<Base>.f()\n    super<Base>@Outer.f()\n    super.f()\n}";
+        let tree = parse(content);
+        let node = find_node(&tree, "function_declaration");
+
+        let function = Function::new(&node, content.as_bytes()).unwrap();
+        let mut supers = Vec::new();
+        function.walk_expressions(&mut |expression| {
+            if let Expression::Super { .. } = expression {
+                supers.push(expression.clone());
+            }
+        });
+        let base = Some((
+            "Base".to_string(),
+            Span {
+                start: Point::new(1, 10),
+                end: Point::new(1, 14),
+            },
+        ));
+        assert_eq!(
+            supers[0],
+            Expression::Super {
+                qualifier: base,
+                label: None,
+            }
+        );
+        assert!(matches!(
+            &supers[1],
+            Expression::Super { qualifier: Some((qualifier, _)), label: Some(label) }
+                if qualifier == "Base" && label == "Outer"
+        ));
+        assert_eq!(
+            supers[2],
+            Expression::Super {
+                qualifier: None,
+                label: None,
+            }
+        );
+        assert_eq!(supers[0].identifier().map(|(name, _)| name), Some("Base"));
+    }
+
+    #[test]
+    fn labeled_this() {
+        let content =
+            "class Outer {\n    fun f() {\n        run {\n            this@Outer.foo()\n            this.bar()\n        }\n    }\n}";
+        let tree = parse(content);
+        let node = find_node(&tree, "function_declaration");
+
+        let function = Function::new(&node, content.as_bytes()).unwrap();
+        let mut receivers = Vec::new();
+        function.walk_expressions(&mut |expression| {
+            if let Expression::This { identifier } = expression {
+                receivers.push(identifier.clone());
+            }
+        });
+        assert_eq!(receivers, vec![Some("Outer".to_string()), None]);
+    }
+}
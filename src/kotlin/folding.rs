@@ -0,0 +1,102 @@
+use tree_sitter::{Node, Tree};
+
+use super::span::Span;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FoldingKind {
+    Region,
+    Imports,
+    Comment,
+}
+
+// ranges that span more than one line, braces are part of the range
+pub fn folding_ranges(tree: &Tree) -> Vec<(Span, FoldingKind)> {
+    let mut ranges = Vec::new();
+    let mut cursor = tree.walk();
+    loop {
+        let node = cursor.node();
+        if let Some(range) = folding_range(&node) {
+            if range.0.start.row < range.0.end.row {
+                ranges.push(range);
+            }
+        }
+
+        if cursor.goto_first_child() {
+            continue;
+        }
+
+        while !cursor.goto_next_sibling() {
+            if !cursor.goto_parent() {
+                return ranges;
+            }
+        }
+    }
+}
+
+fn folding_range(node: &Node) -> Option<(Span, FoldingKind)> {
+    match node.kind() {
+        "class_body" | "enum_class_body" | "function_body" | "when_expression" | "catch_block"
+        | "finally_block" => braces(node, true).map(|span| (span, FoldingKind::Region)),
+        // the try block is followed by catch and finally blocks which fold on their own
+        "try_expression" => braces(node, false).map(|span| (span, FoldingKind::Region)),
+        "import_list" => {
+            let first = node.named_child(0)?;
+            let last = node.named_child(node.named_child_count().checked_sub(1)?)?;
+            Some((
+                Span {
+                    start: first.start_position(),
+                    end: last.end_position(),
+                },
+                FoldingKind::Imports,
+            ))
+        }
+        "multiline_comment" => Some((Span::new(node), FoldingKind::Comment)),
+        _ => None,
+    }
+}
+
+// from the first `{` to the last or first `}` among the direct children
+fn braces(node: &Node, last: bool) -> Option<Span> {
+    let mut cursor = node.walk();
+    let children: Vec<Node> = node.children(&mut cursor).collect();
+    let open = children.iter().find(|child| child.kind() == "{")?;
+    let mut closing = children.iter().filter(|child| child.kind() == "}");
+    let close = if last {
+        closing.next_back()?
+    } else {
+        closing.next()?
+    };
+
+    Some(Span {
+        start: open.start_position(),
+        end: close.end_position(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tree_sitter::Point;
+
+    use crate::kotlin::test_utils::parse;
+
+    use super::{folding_ranges, FoldingKind};
+
+    #[test]
+    fn try_catch_folding() {
+        let content = "fun f() {\n    try {\n        g()\n    } catch (e: Exception) {\n        h()\n    }\n}";
+        let tree = parse(content);
+
+        let ranges: Vec<(Point, Point, FoldingKind)> = folding_ranges(&tree)
+            .into_iter()
+            .map(|(span, kind)| (span.start, span.end, kind))
+            .collect();
+        assert_eq!(
+            ranges,
+            vec![
+                (Point::new(0, 8), Point::new(6, 1), FoldingKind::Region),
+                (Point::new(1, 8), Point::new(3, 5), FoldingKind::Region),
+                (Point::new(3, 27), Point::new(5, 5), FoldingKind::Region),
+            ]
+        );
+    }
+}
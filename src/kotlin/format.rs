@@ -0,0 +1,324 @@
+use std::collections::HashSet;
+
+use tree_sitter::{Node, Point, Tree};
+
+use super::span::Span;
+
+// nodes whose children start a new line at the indentation of their block
+const BLOCKS: [&str; 6] = [
+    "source_file",
+    "statements",
+    "class_body",
+    "enum_class_body",
+    "import_list",
+    "when_expression",
+];
+
+const DECLARATIONS: [&str; 5] = [
+    "class_declaration",
+    "object_declaration",
+    "function_declaration",
+    "property_declaration",
+    "type_alias",
+];
+
+#[derive(Default)]
+struct Layout {
+    // rows of each `{` and its matching `}`
+    braces: Vec<(usize, usize)>,
+    // rows that start a statement, declaration or entry, or close a block
+    anchors: HashSet<usize>,
+    // rows inside multiline strings, left exactly as they are
+    verbatim: HashSet<usize>,
+    // rows that need a blank line before them
+    blank_before: HashSet<usize>,
+    // `{` moved up to the end of the declaration, with the span that is replaced by a space
+    joins: Vec<Span>,
+}
+
+// edits that normalize indentation, strip trailing whitespace, put the `{` of class and
+// function bodies on the line of their declaration and separate top level declarations
+// by a blank line, nothing is changed for trees with syntax errors
+pub fn format_edits(
+    tree: &Tree,
+    content: &[u8],
+    tab_size: usize,
+    insert_spaces: bool,
+) -> Vec<(Span, String)> {
+    let root = tree.root_node();
+    if root.has_error() {
+        return Vec::new();
+    }
+
+    let mut layout = Layout::default();
+    collect_layout(&root, false, content, &mut layout);
+    mark_top_level_separation(&root, &mut layout);
+
+    let mut skip_trailing = HashSet::new();
+    let mut skip_indent = HashSet::new();
+    let mut edits = Vec::new();
+    for join in &layout.joins {
+        skip_trailing.insert(join.start.row);
+        skip_indent.extend(join.start.row + 1..=join.end.row);
+        edits.push((*join, " ".to_string()));
+        for (open, _) in layout.braces.iter_mut() {
+            if *open == join.end.row {
+                *open = join.start.row;
+            }
+        }
+    }
+
+    let unit = if insert_spaces {
+        " ".repeat(tab_size)
+    } else {
+        "\t".to_string()
+    };
+    // new indentation width of every row so far
+    let mut widths: Vec<usize> = Vec::new();
+    // shift applied to the last anchor row, carried over to continuation lines
+    let mut delta: isize = 0;
+    for (row, line) in content.split(|&b| b == b'\n').enumerate() {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let old_len = line.iter().take_while(|b| is_blank(**b)).count();
+        let old_width = width(&line[..old_len], tab_size);
+        widths.push(old_width);
+        if layout.verbatim.contains(&row) {
+            continue;
+        }
+
+        let end = line.len() - line.iter().rev().take_while(|b| is_blank(**b)).count();
+        if end < line.len() && !skip_trailing.contains(&row) {
+            edits.push((span(row, end, line.len()), String::new()));
+        }
+        if end == 0 || skip_indent.contains(&row) {
+            continue;
+        }
+
+        // a block is indented one level deeper than the line its `{` is on, a closing `}`
+        // is back at the level of that line
+        let new_width = if layout.anchors.contains(&row) {
+            let closes = line[old_len] == b'}';
+            let enclosing = layout
+                .braces
+                .iter()
+                .filter(|(open, close)| *open < row && (*close > row || (closes && *close == row)))
+                .map(|(open, close)| (*open, *close))
+                .max_by_key(|(open, close)| (*open, std::cmp::Reverse(*close)));
+            let new_width = match enclosing {
+                Some((open, close)) if closes && close == row => widths[open],
+                Some((open, _)) => widths[open] + tab_size,
+                None => 0,
+            };
+            delta = new_width as isize - old_width as isize;
+            new_width
+        } else {
+            (old_width as isize + delta).max(0) as usize
+        };
+        widths[row] = new_width;
+
+        let mut indent = indentation(new_width, tab_size, &unit);
+        if layout.blank_before.contains(&row) {
+            indent.insert(0, '\n');
+        }
+
+        if indent.as_bytes() != &line[..old_len] {
+            edits.push((span(row, 0, old_len), indent));
+        }
+    }
+
+    edits
+}
+
+fn collect_layout(node: &Node, in_block: bool, content: &[u8], layout: &mut Layout) {
+    let start = node.start_position();
+    let end = node.end_position();
+    match node.kind() {
+        // the braces of interpolations don't open blocks
+        "string_literal" => {
+            layout.verbatim.extend(start.row + 1..=end.row);
+            if in_block && starts_line(node, content) {
+                layout.anchors.insert(start.row);
+            }
+            return;
+        }
+        "class_body" | "enum_class_body" | "function_body" => {
+            if let Some(join) = brace_join(node, content) {
+                layout.joins.push(join);
+            }
+        }
+        _ => {}
+    }
+
+    if in_block && node.is_named() && starts_line(node, content) {
+        layout.anchors.insert(start.row);
+    }
+
+    let is_block = BLOCKS.contains(&node.kind());
+    let mut open = Vec::new();
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        match child.kind() {
+            "{" => open.push(child.start_position().row),
+            "}" => {
+                if starts_line(&child, content) {
+                    layout.anchors.insert(child.start_position().row);
+                }
+                if let Some(row) = open.pop() {
+                    layout.braces.push((row, child.start_position().row));
+                }
+            }
+            _ => collect_layout(&child, is_block, content, layout),
+        }
+    }
+}
+
+// the whitespace between a declaration and the `{` of its body on a later line
+fn brace_join(body: &Node, content: &[u8]) -> Option<Span> {
+    let brace = body.child(0).filter(|child| child.kind() == "{")?;
+    let previous = body.prev_sibling()?;
+    let between = content.get(previous.end_byte()..brace.start_byte())?;
+    let separated = previous.end_position().row < brace.start_position().row
+        && between.iter().all(|b| b.is_ascii_whitespace());
+
+    separated.then(|| Span {
+        start: previous.end_position(),
+        end: brace.start_position(),
+    })
+}
+
+// comments directly above a declaration belong to it, so the blank line goes before them
+fn mark_top_level_separation(root: &Node, layout: &mut Layout) {
+    let mut previous_end = None;
+    let mut group_start = None;
+    let mut cursor = root.walk();
+    for child in root.named_children(&mut cursor) {
+        match child.kind() {
+            "line_comment" | "multiline_comment" => {
+                group_start.get_or_insert(child.start_position().row);
+            }
+            kind if DECLARATIONS.contains(&kind) => {
+                let start = group_start.take().unwrap_or(child.start_position().row);
+                if previous_end.is_some_and(|end: usize| start == end + 1) {
+                    layout.blank_before.insert(start);
+                }
+                previous_end = Some(child.end_position().row);
+            }
+            _ => {
+                previous_end = None;
+                group_start = None;
+            }
+        }
+    }
+}
+
+// whether only whitespace comes before the node on its first line
+fn starts_line(node: &Node, content: &[u8]) -> bool {
+    let start = node.start_byte();
+    content[start - node.start_position().column..start]
+        .iter()
+        .all(|b| is_blank(*b))
+}
+
+fn is_blank(b: u8) -> bool {
+    b == b' ' || b == b'\t'
+}
+
+fn width(indent: &[u8], tab_size: usize) -> usize {
+    indent
+        .iter()
+        .map(|&b| if b == b'\t' { tab_size } else { 1 })
+        .sum()
+}
+
+// continuation lines keep their alignment, so widths that are not a multiple of the
+// indent unit are padded with spaces
+fn indentation(width: usize, tab_size: usize, unit: &str) -> String {
+    let tab_size = tab_size.max(1);
+    let mut indent = unit.repeat(width / tab_size);
+    indent.push_str(&" ".repeat(width % tab_size));
+    indent
+}
+
+fn span(row: usize, start: usize, end: usize) -> Span {
+    Span {
+        start: Point::new(row, start),
+        end: Point::new(row, end),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kotlin::test_utils::parse;
+
+    use super::format_edits;
+
+    fn format(content: &str) -> String {
+        let tree = parse(content);
+        let mut edits = format_edits(&tree, content.as_bytes(), 4, true);
+        edits.sort_by_key(|(span, _)| span.start);
+
+        let mut lines: Vec<String> = content.split('\n').map(str::to_string).collect();
+        for (span, text) in edits.into_iter().rev() {
+            let end_line = lines[span.end.row].clone();
+            let start_line = &mut lines[span.start.row];
+            let joined = format!(
+                "{}{text}{}",
+                &start_line[..span.start.column],
+                &end_line[span.end.column..]
+            );
+            lines.splice(span.start.row..=span.end.row, [joined]);
+        }
+
+        lines.join("\n")
+    }
+
+    #[test]
+    fn normalizes_layout() {
+        let content =
+            "class A\n{\n  fun f()   \n  {\n        g(1,\n          2)\n  }\n}\nfun h() {\n}\n";
+
+        assert_eq!(
+            format(content),
+            "class A {\n    fun f() {\n        g(1,\n          2)\n    }\n}\n\nfun h() {\n}\n"
+        );
+    }
+
+    #[test]
+    fn keeps_formatted_code() {
+        let content = r#"package a
+
+import b.C
+
+/** Does things. */
+class A(private val x: Int) : C() {
+    fun f(y: Int): String {
+        val z = listOf(1, 2)
+            .map { it * 2 }
+            .filter {
+                it > y
+            }
+        if (z.isEmpty()) {
+            return "none"
+        } else {
+            println("${z.size} items")
+        }
+        return when (x) {
+            1 -> "one"
+            else -> {
+                "many"
+            }
+        }
+    }
+}
+"#;
+
+        assert_eq!(format(content), content);
+    }
+
+    #[test]
+    fn keeps_multiline_strings() {
+        let content = "val s = \"\"\"\n  a  \n\"\"\"\n";
+
+        assert_eq!(format(content), content);
+    }
+}
@@ -1,9 +1,12 @@
-use crate::kotlin::types::Type;
+use crate::kotlin::types::{Type, TypeParameter};
 use anyhow::{bail, Context, Result};
-use tree_sitter::Node;
+use tree_sitter::{Node, Tree};
 
 use super::{
     expression::Expression,
+    kdoc::{self, KDoc},
+    property::Property,
+    span::Span,
     statement::{self, Statement},
     types::TYPES,
 };
@@ -13,26 +16,167 @@ pub enum FunctionModifier {
     Annotation(String),
     Member(String),
     Visibility(String),
-    Function(String),
+    Inline,
+    Infix,
+    Operator,
+    Tailrec,
+    Suspend,
+    External,
+    // only on lambda parameters of inline functions
+    Crossinline,
+    Noinline,
     Inheritance(String),
+    Platform(String),
+}
+
+impl FunctionModifier {
+    fn new_function_modifier(node: &Node, content: &[u8]) -> Result<FunctionModifier> {
+        Ok(match node.utf8_text(content)? {
+            "inline" => FunctionModifier::Inline,
+            "infix" => FunctionModifier::Infix,
+            "operator" => FunctionModifier::Operator,
+            "tailrec" => FunctionModifier::Tailrec,
+            "suspend" => FunctionModifier::Suspend,
+            "external" => FunctionModifier::External,
+            text => bail!(
+                "[FunctionModifier] unknown function modifier {} at {}",
+                text,
+                node.start_position()
+            ),
+        })
+    }
+
+    pub fn keyword(&self) -> Option<&'static str> {
+        match self {
+            FunctionModifier::Inline => Some("inline"),
+            FunctionModifier::Infix => Some("infix"),
+            FunctionModifier::Operator => Some("operator"),
+            FunctionModifier::Tailrec => Some("tailrec"),
+            FunctionModifier::Suspend => Some("suspend"),
+            FunctionModifier::External => Some("external"),
+            FunctionModifier::Crossinline => Some("crossinline"),
+            FunctionModifier::Noinline => Some("noinline"),
+            FunctionModifier::Member(member) if member == "override" => Some("override"),
+            FunctionModifier::Platform(platform) => match platform.as_str() {
+                "expect" => Some("expect"),
+                "actual" => Some("actual"),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub struct Parameter {
     pub name: String,
+    // span of the name, synthesized parameters use the span of what they are derived from
+    pub span: Span,
     pub type_identifier: Type,
+    pub vararg: bool,
+    pub modifiers: Vec<FunctionModifier>,
+    pub default_value: Option<Expression>,
 }
 
-#[derive(Debug, Hash, PartialEq, Eq, Clone)]
-pub struct Identifier {
-    pub name: String,
-    pub data_type: Option<Type>,
+impl Parameter {
+    fn new(
+        node: &Node,
+        vararg: bool,
+        modifiers: Vec<FunctionModifier>,
+        content: &[u8],
+    ) -> Result<Parameter> {
+        let name = node.child(0).context("no parameter name found")?;
+        Ok(Parameter {
+            name: name.utf8_text(content)?.to_string(),
+            span: Span::new(&name),
+            type_identifier: Type::new(
+                &node
+                    .child(2)
+                    .filter(|c| c.kind() != "type_modifiers")
+                    .or_else(|| node.child(3))
+                    .context("no type identifier found")?,
+                content,
+            )?,
+            vararg,
+            modifiers,
+            default_value: None,
+        })
+    }
+
+    // how the parameter is written in a signature, e.g. "vararg xs: String" or
+    // "x: Int = …", default values are left out
+    pub fn label(&self) -> String {
+        let mut keywords: Vec<&str> = self
+            .modifiers
+            .iter()
+            .filter_map(|modifier| modifier.keyword())
+            .collect();
+        if self.vararg {
+            keywords.push("vararg");
+        }
+        keywords.push(&self.name);
+        let default = if self.default_value.is_some() {
+            " = …"
+        } else {
+            ""
+        };
+        format!("{}: {}{default}", keywords.join(" "), self.type_identifier)
+    }
+}
+
+// parameter modifiers are siblings that precede the parameter they belong to,
+// default values are siblings that follow it
+pub fn get_parameters(node: &Node, content: &[u8]) -> Result<Vec<Parameter>> {
+    let mut parameters = Vec::new();
+    let mut vararg = false;
+    let mut modifiers = Vec::new();
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        match child.kind() {
+            "parameter_modifiers" => {
+                let mut cursor = child.walk();
+                for modifier in child.children(&mut cursor) {
+                    match modifier.utf8_text(content)? {
+                        "vararg" => vararg = true,
+                        "crossinline" => modifiers.push(FunctionModifier::Crossinline),
+                        "noinline" => modifiers.push(FunctionModifier::Noinline),
+                        _ => {}
+                    }
+                }
+            }
+            "parameter" => {
+                parameters.push(Parameter::new(
+                    &child,
+                    vararg,
+                    std::mem::take(&mut modifiers),
+                    content,
+                )?);
+                vararg = false;
+            }
+            "=" => {
+                let parameter = parameters.last_mut().context(format!(
+                    "[Parameter] no parameter for default value at {}",
+                    child.start_position()
+                ))?;
+                parameter.default_value = Some(Expression::new(
+                    &child.next_sibling().context(format!(
+                        "[Parameter] no default value at {}",
+                        child.start_position()
+                    ))?,
+                    content,
+                )?);
+            }
+            _ => {}
+        }
+    }
+
+    Ok(parameters)
 }
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub enum FunctionBody {
     Block(Vec<Statement>),
-    Expression(Expression),
+    Expression(Box<Expression>),
 }
 
 impl FunctionBody {
@@ -47,25 +191,64 @@ impl FunctionBody {
         ))?;
 
         Ok(match first.kind() {
-            "=" => FunctionBody::Expression(Expression::new(&second, content)?),
-            _ => FunctionBody::Block(statement::get_statements(&second, content)?),
+            "=" => FunctionBody::Expression(Box::new(Expression::new(&second, content)?)),
+            // comments before the first statement are outside of the statements node
+            _ => {
+                let mut statements = Vec::new();
+                let mut cursor = node.walk();
+                for child in node.children(&mut cursor) {
+                    match child.kind() {
+                        "line_comment" | "multiline_comment" => statements
+                            .push(Statement::Comment(child.utf8_text(content)?.to_string())),
+                        "statements" => {
+                            statements.extend(statement::get_statements(&child, content)?)
+                        }
+                        _ => {}
+                    }
+                }
+                FunctionBody::Block(statements)
+            }
         })
     }
+
+    pub fn walk_expressions<'a>(&'a self, f: &mut impl FnMut(&'a Expression)) {
+        match self {
+            FunctionBody::Block(statements) => {
+                for statement in statements {
+                    statement.walk_expressions(f);
+                }
+            }
+            FunctionBody::Expression(expression) => expression.walk_expressions(f),
+        }
+    }
 }
 
+/// A `fun` declaration, either top level, a member or local to a block.
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub struct Function {
+    pub context_receivers: Vec<Type>,
     pub modifiers: Vec<FunctionModifier>,
+    pub type_parameters: Vec<TypeParameter>,
+    pub receiver_type: Option<Type>,
     pub name: String,
+    pub name_span: Span,
     pub parameters: Vec<Parameter>,
     pub return_type: Option<String>,
     pub body: Option<FunctionBody>,
+    pub span: Span,
+    pub kdoc: Option<KDoc>,
 }
 
 impl Function {
     pub fn new(node: &Node, content: &[u8]) -> Result<Function> {
+        let context_receivers = node
+            .prev_sibling()
+            .and_then(|previous| context_receivers(&previous, content))
+            .unwrap_or_default();
         let mut modifiers: Vec<FunctionModifier> = Vec::new();
         let mut parameters: Vec<Parameter> = Vec::new();
+        let mut type_parameters = Vec::new();
+        let mut receiver_type = None;
         let mut name = None;
         let mut return_type = None;
         let mut body = None;
@@ -83,10 +266,12 @@ impl Function {
                         "visibility_modifier" => modifiers.push(FunctionModifier::Visibility(
                             child.utf8_text(content)?.to_string(),
                         )),
-                        "function_modifier" => modifiers.push(FunctionModifier::Function(
+                        "function_modifier" => modifiers
+                            .push(FunctionModifier::new_function_modifier(&child, content)?),
+                        "inheritance_modifier" => modifiers.push(FunctionModifier::Inheritance(
                             child.utf8_text(content)?.to_string(),
                         )),
-                        "inheritance_modifier" => modifiers.push(FunctionModifier::Inheritance(
+                        "platform_modifier" => modifiers.push(FunctionModifier::Platform(
                             child.utf8_text(content)?.to_string(),
                         )),
                         _ => bail!("unknown modifier {}", child.kind()),
@@ -94,34 +279,29 @@ impl Function {
                 }
             }
 
+            if child.kind() == "type_parameters" {
+                for child in child.children(&mut cursor) {
+                    if child.kind() == "type_parameter" {
+                        type_parameters.push(TypeParameter::new(&child, content)?)
+                    }
+                }
+            }
+
             if child.kind() == "simple_identifier" {
-                name = Some(child.utf8_text(content)?.to_string());
+                name = Some((child.utf8_text(content)?.to_string(), Span::new(&child)));
             }
 
             if child.kind() == "function_value_parameters" {
-                for child in child.children(&mut cursor) {
-                    if child.kind() == "parameter" {
-                        parameters.push(Parameter {
-                            name: child
-                                .child(0)
-                                .context("no parameter name found")?
-                                .utf8_text(content)?
-                                .to_string(),
-                            type_identifier: Type::new(
-                                &child
-                                    .child(2)
-                                    .filter(|c| c.kind() != "type_modifiers")
-                                    .or_else(|| child.child(3))
-                                    .context("no type identifier found")?,
-                                content,
-                            )?,
-                        })
-                    }
-                }
+                parameters = get_parameters(&child, content)?;
             }
 
+            // the receiver type of an extension function comes before the name
             if child.kind() == "user_type" || child.kind() == "nullable_type" {
-                return_type = Some(child.utf8_text(content)?.to_string());
+                if name.is_none() {
+                    receiver_type = Some(Type::new(&child, content)?);
+                } else {
+                    return_type = Some(child.utf8_text(content)?.to_string());
+                }
             }
 
             if child.kind() == "function_body" {
@@ -129,14 +309,85 @@ impl Function {
             }
         }
 
+        let (name, name_span) = name.context("no name found for function")?;
         Ok(Function {
+            context_receivers,
             modifiers,
-            name: name.context("no name found for function")?,
+            type_parameters,
+            receiver_type,
+            name,
+            name_span,
             parameters,
             return_type,
             body,
+            span: Span::new(node),
+            kdoc: kdoc::get_kdoc(node, content)?,
+        })
+    }
+
+    // properties declared directly in a block body, nested blocks are not visited
+    pub fn local_properties(&self) -> Vec<&Property> {
+        match &self.body {
+            Some(FunctionBody::Block(statements)) => statements
+                .iter()
+                .filter_map(|statement| match statement {
+                    Statement::PropertyDeclaration(property) => Some(property.as_ref()),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    // annotated with JUnit's `@Test` or `@ParameterizedTest`, qualified or not
+    pub fn is_test(&self) -> bool {
+        self.modifiers.iter().any(|modifier| {
+            let FunctionModifier::Annotation(annotation) = modifier else {
+                return false;
+            };
+            let name = annotation.trim_start_matches('@');
+            let name = name.split('(').next().unwrap_or(name);
+            matches!(name.rsplit('.').next(), Some("Test" | "ParameterizedTest"))
         })
     }
+
+    // `override` is a member modifier in the grammar
+    pub fn is_override(&self) -> bool {
+        self.modifiers
+            .contains(&FunctionModifier::Member("override".to_string()))
+    }
+
+    pub fn is_expect(&self) -> bool {
+        self.modifiers
+            .contains(&FunctionModifier::Platform("expect".to_string()))
+    }
+
+    pub fn is_actual(&self) -> bool {
+        self.modifiers
+            .contains(&FunctionModifier::Platform("actual".to_string()))
+    }
+
+    pub fn walk_expressions<'a>(&'a self, f: &mut impl FnMut(&'a Expression)) {
+        for default_value in self.parameters.iter().flat_map(|p| &p.default_value) {
+            default_value.walk_expressions(f);
+        }
+        if let Some(body) = &self.body {
+            body.walk_expressions(f);
+        }
+    }
+
+    // names of the called functions together with the span of the name at the call
+    pub fn calls(&self) -> Vec<(&str, Span)> {
+        let mut calls = Vec::new();
+        self.walk_expressions(&mut |expression| {
+            if let Expression::Call { expression, .. } = expression {
+                if let Some(call) = expression.identifier() {
+                    calls.push(call);
+                }
+            }
+        });
+        calls
+    }
 }
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
@@ -177,3 +428,298 @@ impl ParameterWithOptionalType {
         })
     }
 }
+
+// the grammar has no rule for `context(A, B)` in front of a function, it ends up as a call
+// at the top level and as an error node in class bodies, which only are receivers when
+// the function follows right away. The types keep their source text because the grammar
+// doesn't parse them as types there
+pub fn context_receivers(node: &Node, content: &[u8]) -> Option<Vec<Type>> {
+    if !matches!(node.kind(), "call_expression" | "ERROR")
+        || node.next_sibling()?.kind() != "function_declaration"
+    {
+        return None;
+    }
+    let receivers = node
+        .utf8_text(content)
+        .ok()?
+        .strip_prefix("context")?
+        .trim_start()
+        .strip_prefix('(')?
+        .strip_suffix(')')?;
+
+    let mut types = Vec::new();
+    let (mut depth, mut start, mut previous) = (0, 0, ' ');
+    for (i, c) in receivers.char_indices().chain([(receivers.len(), ',')]) {
+        match c {
+            '<' | '(' => depth += 1,
+            // the arrow of a function type doesn't close anything
+            '>' if previous == '-' => {}
+            '>' | ')' => depth -= 1,
+            ',' if depth == 0 => {
+                let receiver = receivers[start..i].trim();
+                match receiver.strip_suffix('?') {
+                    _ if receiver.is_empty() => {}
+                    Some(receiver) => types.push(Type::Nullable(Vec::new(), receiver.to_string())),
+                    None => types.push(Type::NonNullable(Vec::new(), receiver.to_string())),
+                }
+                start = i + 1;
+            }
+            _ => {}
+        }
+        previous = c;
+    }
+
+    Some(types)
+}
+
+pub fn get_top_level_functions(tree: &Tree, content: &[u8]) -> Result<Vec<Function>> {
+    let mut functions = Vec::new();
+    let root = tree.root_node();
+    let mut cursor = root.walk();
+    for child in root.children(&mut cursor) {
+        if child.kind() == "function_declaration" {
+            functions.push(Function::new(&child, content)?);
+        }
+    }
+
+    Ok(functions)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kotlin::{
+        declaration::Declaration,
+        test_utils::{find_node, parse},
+        types::Type,
+        KotlinFile,
+    };
+
+    use super::{Function, FunctionBody, FunctionModifier, Statement};
+
+    #[test]
+    fn platform_modifier() {
+        let content = "actual suspend fun foo() {\n}";
+        let tree = parse(content);
+        let node = find_node(&tree, "function_declaration");
+
+        let function = Function::new(&node, content.as_bytes()).unwrap();
+        assert_eq!(
+            function.modifiers,
+            vec![
+                FunctionModifier::Platform("actual".to_string()),
+                FunctionModifier::Suspend,
+            ]
+        );
+        assert!(function.is_actual());
+        assert!(!function.is_expect());
+    }
+
+    #[test]
+    fn block_comments() {
+        let content = "fun f() {\n    /* leading */\n    g()\n    /* inner */\n    h()\n}";
+        let tree = parse(content);
+        let node = find_node(&tree, "function_declaration");
+
+        let function = Function::new(&node, content.as_bytes()).unwrap();
+        let Some(FunctionBody::Block(statements)) = function.body else {
+            panic!("expected a block body");
+        };
+        assert_eq!(statements.len(), 4);
+        assert_eq!(
+            statements[0],
+            Statement::Comment("/* leading */".to_string())
+        );
+        assert_eq!(statements[2], Statement::Comment("/* inner */".to_string()));
+    }
+
+    #[test]
+    fn inline_parameter_modifiers() {
+        let content =
+            "inline fun f(crossinline a: () -> Unit, noinline b: () -> Unit, vararg c: Int) {}";
+        let tree = parse(content);
+        let node = find_node(&tree, "function_declaration");
+
+        let function = Function::new(&node, content.as_bytes()).unwrap();
+        let modifiers: Vec<&[FunctionModifier]> = function
+            .parameters
+            .iter()
+            .map(|parameter| parameter.modifiers.as_slice())
+            .collect();
+        assert_eq!(
+            modifiers,
+            vec![
+                &[FunctionModifier::Crossinline][..],
+                &[FunctionModifier::Noinline][..],
+                &[][..],
+            ]
+        );
+        assert!(function.parameters[2].vararg);
+    }
+
+    #[test]
+    fn vararg_parameters() {
+        let content = "fun f(vararg xs: String, y: Int) {}";
+        let tree = parse(content);
+        let node = find_node(&tree, "function_declaration");
+
+        let function = Function::new(&node, content.as_bytes()).unwrap();
+        let parameters: Vec<(&str, bool, &Type)> = function
+            .parameters
+            .iter()
+            .map(|parameter| {
+                (
+                    parameter.name.as_str(),
+                    parameter.vararg,
+                    &parameter.type_identifier,
+                )
+            })
+            .collect();
+        // the type stays the element type
+        assert_eq!(
+            parameters,
+            vec![
+                (
+                    "xs",
+                    true,
+                    &Type::NonNullable(Vec::new(), "String".to_string())
+                ),
+                (
+                    "y",
+                    false,
+                    &Type::NonNullable(Vec::new(), "Int".to_string())
+                ),
+            ]
+        );
+        assert_eq!(
+            Declaration::Function(&function).signature(),
+            "fun f(vararg xs: String, y: Int)"
+        );
+    }
+
+    #[test]
+    fn extension_receiver() {
+        let content = "fun <T> List<T>.second(): T = this[1]\nfun String?.orEmpty() = this ?: \"\"\nfun plain(): String = \"\"";
+        let file: KotlinFile = content.parse().unwrap();
+
+        let receivers: Vec<(Option<&Type>, Option<&str>)> = file
+            .functions
+            .iter()
+            .map(|function| {
+                (
+                    function.receiver_type.as_ref(),
+                    function.return_type.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            receivers,
+            vec![
+                (
+                    Some(&Type::NonNullable(Vec::new(), "List<T>".to_string())),
+                    Some("T")
+                ),
+                (
+                    Some(&Type::Nullable(Vec::new(), "String?".to_string())),
+                    None
+                ),
+                (None, Some("String")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_functions() {
+        let content = "class A {\n    @Test fun a() {}\n    @org.junit.jupiter.params.ParameterizedTest(name = \"x\") fun b() {}\n    @Tested fun c() {}\n}";
+        let tree = parse(content);
+        let body = find_node(&tree, "class_body");
+
+        let mut cursor = body.walk();
+        let tests: Vec<bool> = body
+            .children(&mut cursor)
+            .filter(|child| child.kind() == "function_declaration")
+            .map(|child| Function::new(&child, content.as_bytes()).unwrap().is_test())
+            .collect();
+        assert_eq!(tests, vec![true, true, false]);
+    }
+
+    #[test]
+    fn override_function() {
+        let content = "class A : B {\n    override suspend fun f() {}\n    fun g() {}\n}";
+        let tree = parse(content);
+        let body = find_node(&tree, "class_body");
+
+        let mut cursor = body.walk();
+        let functions: Vec<Function> = body
+            .children(&mut cursor)
+            .filter(|child| child.kind() == "function_declaration")
+            .map(|child| Function::new(&child, content.as_bytes()).unwrap())
+            .collect();
+        assert!(functions[0].is_override());
+        assert!(!functions[1].is_override());
+        assert_eq!(
+            Declaration::Function(&functions[0]).signature(),
+            "override suspend fun f()"
+        );
+    }
+
+    #[test]
+    fn context_receivers() {
+        let content = "context(Logger, Scope<Int, String>)\nfun log() {}\nclass A {\n    context(Logger)\n    fun log() {}\n    fun plain() {}\n}";
+        let file: KotlinFile = content.parse().unwrap();
+
+        let function = &file.functions[0];
+        assert_eq!(
+            function.context_receivers,
+            vec![
+                Type::NonNullable(Vec::new(), "Logger".to_string()),
+                Type::NonNullable(Vec::new(), "Scope<Int, String>".to_string()),
+            ]
+        );
+        assert_eq!(
+            Declaration::Function(function).signature(),
+            "context(Logger, Scope<Int, String>) fun log()"
+        );
+
+        let functions = file.classes[0].body.as_ref().unwrap().functions();
+        assert_eq!(functions[0].context_receivers.len(), 1);
+        assert!(functions[1].context_receivers.is_empty());
+    }
+
+    #[test]
+    fn context_receivers_with_function_types() {
+        let content = "context(Logger, (Int) -> Unit, Map<String, () -> Int>)\nfun log() {}\n";
+        let file: KotlinFile = content.parse().unwrap();
+
+        assert_eq!(
+            file.functions[0].context_receivers,
+            vec![
+                Type::NonNullable(Vec::new(), "Logger".to_string()),
+                Type::NonNullable(Vec::new(), "(Int) -> Unit".to_string()),
+                Type::NonNullable(Vec::new(), "Map<String, () -> Int>".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn context_call_without_function() {
+        let content = "context(logger)\nval x = 1\nfun log() {}\n";
+        let tree = parse(content);
+        let call = find_node(&tree, "call_expression");
+
+        assert_eq!(super::context_receivers(&call, content.as_bytes()), None);
+        let file: KotlinFile = content.parse().unwrap();
+        assert!(file.functions[0].context_receivers.is_empty());
+    }
+
+    #[test]
+    fn extension_function_signature() {
+        let file: KotlinFile = "fun <T> List<T>?.second(fallback: T): T = this?.get(1) ?: fallback"
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            Declaration::Function(&file.functions[0]).signature(),
+            "fun <T> List<T>?.second(fallback: T): T"
+        );
+    }
+}
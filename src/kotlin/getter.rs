@@ -1,7 +1,7 @@
 use anyhow::{bail, Result};
 use tree_sitter::Node;
 
-use crate::kotlin::function::FunctionBody;
+use crate::kotlin::{expression::Expression, function::FunctionBody};
 
 use super::{function::ParameterWithOptionalType, modifier::Modifier};
 
@@ -13,7 +13,9 @@ pub struct Getter {
 
 impl Getter {
     pub fn new(node: &Node, content: &[u8]) -> Result<Getter> {
-        let modifiers = if let Some(modifiers_node) = node.child(0) {
+        let modifiers = if let Some(modifiers_node) =
+            node.child(0).filter(|child| child.kind() == "modifiers")
+        {
             let mut modifiers = Vec::new();
             let mut cursor = node.walk();
             for child in modifiers_node.children(&mut cursor) {
@@ -47,6 +49,12 @@ impl Getter {
             function_body,
         })
     }
+
+    pub fn walk_expressions<'a>(&'a self, f: &mut impl FnMut(&'a Expression)) {
+        if let Some(function_body) = &self.function_body {
+            function_body.walk_expressions(f);
+        }
+    }
 }
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
@@ -58,7 +66,9 @@ pub struct Setter {
 
 impl Setter {
     pub fn new(node: &Node, content: &[u8]) -> Result<Setter> {
-        let modifiers = if let Some(modifiers_node) = node.child(0) {
+        let modifiers = if let Some(modifiers_node) =
+            node.child(0).filter(|child| child.kind() == "modifiers")
+        {
             let mut modifiers = Vec::new();
             let mut cursor = node.walk();
             for child in modifiers_node.children(&mut cursor) {
@@ -97,4 +107,48 @@ impl Setter {
             function_body,
         })
     }
+
+    pub fn walk_expressions<'a>(&'a self, f: &mut impl FnMut(&'a Expression)) {
+        if let Some(function_body) = &self.function_body {
+            function_body.walk_expressions(f);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kotlin::test_utils::{find_node, parse};
+
+    use super::{Getter, Setter};
+
+    #[test]
+    fn abstract_getter() {
+        let content = "interface A {\n    val foo: String\n        get\n}";
+        let tree = parse(content);
+        let node = find_node(&tree, "getter");
+
+        assert_eq!(
+            Getter::new(&node, content.as_bytes()).unwrap(),
+            Getter {
+                modifiers: None,
+                function_body: None,
+            }
+        );
+    }
+
+    #[test]
+    fn abstract_setter() {
+        let content = "interface A {\n    var foo: String\n        set\n}";
+        let tree = parse(content);
+        let node = find_node(&tree, "setter");
+
+        assert_eq!(
+            Setter::new(&node, content.as_bytes()).unwrap(),
+            Setter {
+                modifiers: None,
+                parameter: None,
+                function_body: None,
+            }
+        );
+    }
 }
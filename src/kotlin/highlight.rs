@@ -0,0 +1,175 @@
+use tree_sitter::{Node, Tree};
+
+use super::span::Span;
+
+// the order is the order of the semantic token legend
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub enum TokenType {
+    Class,
+    Interface,
+    Enum,
+    Function,
+    Variable,
+    Parameter,
+    Type,
+    Keyword,
+    String,
+    Number,
+    Comment,
+    Operator,
+}
+
+const PUNCTUATION: [&str; 13] = [
+    "(", ")", "{", "}", "[", "]", "<", ">", ",", ".", ";", ":", "@",
+];
+
+// tokens in document order, nodes that get a token as a whole are not descended into
+pub fn highlights(tree: &Tree) -> Vec<(Span, TokenType)> {
+    let mut tokens = Vec::new();
+    let mut cursor = tree.walk();
+    loop {
+        let node = cursor.node();
+        match token_type(&node) {
+            Some(token_type) => tokens.push((Span::new(&node), token_type)),
+            None if !is_leaf_token(&node) && cursor.goto_first_child() => continue,
+            None => {}
+        }
+
+        while !cursor.goto_next_sibling() {
+            if !cursor.goto_parent() {
+                return tokens;
+            }
+        }
+    }
+}
+
+fn is_leaf_token(node: &Node) -> bool {
+    matches!(node.kind(), "string_literal" | "character_literal")
+}
+
+fn token_type(node: &Node) -> Option<TokenType> {
+    let parent = node.parent();
+    let parent_kind = parent.map(|parent| parent.kind()).unwrap_or_default();
+    match node.kind() {
+        "line_comment" | "multiline_comment" => Some(TokenType::Comment),
+        "string_literal" | "character_literal" => Some(TokenType::String),
+        "integer_literal" | "long_literal" | "real_literal" | "hex_literal" | "bin_literal"
+        | "unsigned_literal" => Some(TokenType::Number),
+        "boolean_literal" => Some(TokenType::Keyword),
+        "type_identifier" => Some(match parent_kind {
+            "class_declaration" => class_token_type(&parent?),
+            "object_declaration" => TokenType::Class,
+            _ => TokenType::Type,
+        }),
+        "simple_identifier" => Some(match parent_kind {
+            "function_declaration" => TokenType::Function,
+            "parameter" | "class_parameter" | "parameter_with_optional_type" => {
+                TokenType::Parameter
+            }
+            "call_expression" => TokenType::Function,
+            _ => TokenType::Variable,
+        }),
+        kind if !node.is_named() && node.child_count() == 0 => {
+            if kind.chars().all(|c| c.is_ascii_alphabetic()) {
+                Some(TokenType::Keyword)
+            } else if PUNCTUATION.contains(&kind) {
+                None
+            } else {
+                Some(TokenType::Operator)
+            }
+        }
+        _ => None,
+    }
+}
+
+fn class_token_type(node: &Node) -> TokenType {
+    let mut cursor = node.walk();
+    let mut token_type = TokenType::Class;
+    for child in node.children(&mut cursor) {
+        match child.kind() {
+            "interface" => token_type = TokenType::Interface,
+            "enum" => token_type = TokenType::Enum,
+            _ => {}
+        }
+    }
+
+    token_type
+}
+
+#[cfg(test)]
+mod tests {
+    use tree_sitter::Point;
+
+    use crate::kotlin::{span::Span, test_utils::parse};
+
+    use super::{highlights, TokenType};
+
+    fn tokens(content: &str) -> Vec<(String, TokenType)> {
+        let lines: Vec<&str> = content.lines().collect();
+        highlights(&parse(content))
+            .into_iter()
+            .map(|(span, token_type)| {
+                assert_eq!(span.start.row, span.end.row);
+                let line = lines[span.start.row];
+                (
+                    line[span.start.column..span.end.column].to_string(),
+                    token_type,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn declarations() {
+        assert_eq!(
+            tokens("interface A\nenum class B\nfun f(x: Int) = g(x + 1)"),
+            vec![
+                ("interface".to_string(), TokenType::Keyword),
+                ("A".to_string(), TokenType::Interface),
+                ("enum".to_string(), TokenType::Keyword),
+                ("class".to_string(), TokenType::Keyword),
+                ("B".to_string(), TokenType::Enum),
+                ("fun".to_string(), TokenType::Keyword),
+                ("f".to_string(), TokenType::Function),
+                ("x".to_string(), TokenType::Parameter),
+                ("Int".to_string(), TokenType::Type),
+                ("=".to_string(), TokenType::Operator),
+                ("g".to_string(), TokenType::Function),
+                ("x".to_string(), TokenType::Variable),
+                ("+".to_string(), TokenType::Operator),
+                ("1".to_string(), TokenType::Number),
+            ]
+        );
+    }
+
+    #[test]
+    fn literals_and_comments() {
+        assert_eq!(
+            tokens("// note\nval s = \"a $b\" /* c */"),
+            vec![
+                ("// note".to_string(), TokenType::Comment),
+                ("val".to_string(), TokenType::Keyword),
+                ("s".to_string(), TokenType::Variable),
+                ("=".to_string(), TokenType::Operator),
+                ("\"a $b\"".to_string(), TokenType::String),
+                ("/* c */".to_string(), TokenType::Comment),
+            ]
+        );
+    }
+
+    #[test]
+    fn multiline_string_is_one_token() {
+        let content = "val s = \"\"\"\n    a\n\"\"\"";
+
+        assert_eq!(
+            highlights(&parse(content)).last(),
+            Some(&(
+                Span {
+                    start: Point::new(0, 8),
+                    end: Point::new(2, 3),
+                },
+                TokenType::String
+            ))
+        );
+    }
+}
@@ -1,8 +1,147 @@
+use std::collections::HashSet;
+
 use anyhow::{Context, Result};
-use tree_sitter::Tree;
+use tree_sitter::{Node, Point, Tree};
+
+use super::span::Span;
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone)]
+pub struct Import {
+    pub path: String,
+    pub alias: Option<String>,
+    // the import header without the comments the grammar attaches to its end
+    pub span: Span,
+}
+
+impl Import {
+    // the last segment of the path, which is what the file body refers to without an alias
+    pub fn imported_name(&self) -> &str {
+        self.path.rsplit('.').next().unwrap_or(&self.path)
+    }
+
+    pub fn is_wildcard(&self) -> bool {
+        self.path.ends_with(".*")
+    }
+}
+
+// imported functions that are called through operators or conventions, so their name
+// never shows up in the file body
+const OPERATORS: [&str; 15] = [
+    "getValue",
+    "setValue",
+    "provideDelegate",
+    "invoke",
+    "get",
+    "set",
+    "contains",
+    "iterator",
+    "plus",
+    "minus",
+    "times",
+    "div",
+    "compareTo",
+    "rangeTo",
+    "plusAssign",
+];
+
+// replacement for the whole import list: duplicates and unused imports are dropped and
+// the rest is sorted, with `java` and `javax` imports after everything else. Comments
+// between imports would get lost, so those lists are left alone
+pub fn organize_imports(tree: &Tree, content: &[u8], imports: &[Import]) -> Option<(Span, String)> {
+    let (first, last) = (imports.first()?, imports.last()?);
+    if has_comment(&tree.root_node(), first.span.start, last.span.end) {
+        return None;
+    }
+
+    let mut names = HashSet::new();
+    collect_names(&tree.root_node(), content, &mut names);
+
+    let mut organized: Vec<&Import> = Vec::new();
+    for import in imports {
+        let name = import.alias.as_deref().unwrap_or(import.imported_name());
+        let used = import.is_wildcard()
+            || names.contains(name)
+            || (import.alias.is_none() && OPERATORS.contains(&name));
+        if used {
+            organized.push(import);
+        }
+    }
+    organized.sort_by_key(|import| {
+        let java = import.path.starts_with("java.") || import.path.starts_with("javax.");
+        (java, import.path.as_str(), import.alias.as_deref())
+    });
+    organized.dedup_by(|a, b| a.path == b.path && a.alias == b.alias);
+
+    let lines: Vec<String> = organized
+        .iter()
+        .map(|import| match &import.alias {
+            Some(alias) => format!("import {} as {alias}", import.path),
+            None => format!("import {}", import.path),
+        })
+        .collect();
 
-#[derive(Debug, Hash, PartialEq, Eq)]
-pub struct Import(String);
+    // without any imports left the line of the last one goes away as well
+    let end = if lines.is_empty() {
+        Point::new(last.span.end.row + 1, 0)
+    } else {
+        last.span.end
+    };
+    let span = Span {
+        start: first.span.start,
+        end,
+    };
+
+    Some((span, lines.join("\n")))
+}
+
+fn has_comment(node: &Node, start: Point, end: Point) -> bool {
+    if node.end_position() <= start || node.start_position() >= end {
+        return false;
+    }
+    if matches!(node.kind(), "line_comment" | "multiline_comment") {
+        return true;
+    }
+
+    let mut cursor = node.walk();
+    let has_comment = node
+        .children(&mut cursor)
+        .any(|child| has_comment(&child, start, end));
+    has_comment
+}
+
+// identifiers and type names outside of the package header and the imports
+fn collect_names<'a>(node: &Node, content: &'a [u8], names: &mut HashSet<&'a str>) {
+    match node.kind() {
+        "package_header" | "import_list" => return,
+        "simple_identifier" | "type_identifier" => {
+            if let Ok(name) = node.utf8_text(content) {
+                names.insert(name);
+            }
+        }
+        _ => {}
+    }
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_names(&child, content, names);
+    }
+}
+
+fn get_alias(node: &Node, content: &[u8]) -> Result<Option<String>> {
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        if child.kind() == "import_alias" {
+            let alias = child
+                .named_child(0)
+                .context(format!("[Import] no alias at {}", child.start_position()))?
+                .utf8_text(content)?
+                .to_string();
+            return Ok(Some(alias));
+        }
+    }
+
+    Ok(None)
+}
 
 pub fn get_imports(tree: &Tree, content: &[u8]) -> Result<Vec<Import>> {
     let mut imports = Vec::new();
@@ -10,14 +149,33 @@ pub fn get_imports(tree: &Tree, content: &[u8]) -> Result<Vec<Import>> {
     loop {
         let node = cursor.node();
         if node.kind() == "import" {
-            let import = node
-                .next_sibling()
-                .context("malformed import")?
+            let identifier = node.next_sibling().context("malformed import")?;
+            let mut path = identifier
                 .utf8_text(content)
                 .context("malformed import")?
                 .to_string();
-
-            imports.push(Import(import));
+            // `.*` of a star import is a sibling of the identifier
+            if identifier
+                .next_sibling()
+                .is_some_and(|next| next.kind() == ".*")
+            {
+                path.push_str(".*");
+            }
+            let header = node.parent().context("malformed import")?;
+            let mut cursor = header.walk();
+            let end = header
+                .children(&mut cursor)
+                .filter(|child| !matches!(child.kind(), "line_comment" | "multiline_comment"))
+                .last()
+                .map_or(header.end_position(), |child| child.end_position());
+            imports.push(Import {
+                path,
+                alias: get_alias(&header, content)?,
+                span: Span {
+                    start: header.start_position(),
+                    end,
+                },
+            });
         }
 
         if cursor.goto_first_child() {
@@ -35,3 +193,124 @@ pub fn get_imports(tree: &Tree, content: &[u8]) -> Result<Vec<Import>> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use tree_sitter::Point;
+
+    use crate::kotlin::{span::Span, test_utils::parse};
+
+    use super::{get_imports, organize_imports, Import};
+
+    #[test]
+    fn imports() {
+        let content = "import foo.Bar as Baz\nimport foo.Qux\nimport foo.bar.*\n";
+        let tree = parse(content);
+
+        assert_eq!(
+            get_imports(&tree, content.as_bytes()).unwrap(),
+            vec![
+                Import {
+                    path: "foo.Bar".to_string(),
+                    alias: Some("Baz".to_string()),
+                    span: Span {
+                        start: Point::new(0, 0),
+                        end: Point::new(0, 21),
+                    },
+                },
+                Import {
+                    path: "foo.Qux".to_string(),
+                    alias: None,
+                    span: Span {
+                        start: Point::new(1, 0),
+                        end: Point::new(1, 14),
+                    },
+                },
+                Import {
+                    path: "foo.bar.*".to_string(),
+                    alias: None,
+                    span: Span {
+                        start: Point::new(2, 0),
+                        end: Point::new(2, 16),
+                    },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn wildcard_import() {
+        let content = "import kotlinx.coroutines.*\n";
+        let tree = parse(content);
+
+        let imports = get_imports(&tree, content.as_bytes()).unwrap();
+        assert!(imports[0].is_wildcard());
+    }
+
+    #[test]
+    fn organized_imports() {
+        let content = "package a\n\nimport java.util.UUID\nimport foo.Unused\nimport kotlin.math.max\nimport foo.Bar as Baz\nimport kotlin.math.max\nimport foo.bar.*\nimport foo.getValue\n\nval x: UUID = Baz(max(1, 2))\n";
+        let tree = parse(content);
+        let imports = get_imports(&tree, content.as_bytes()).unwrap();
+
+        assert_eq!(
+            organize_imports(&tree, content.as_bytes(), &imports),
+            Some((
+                Span {
+                    start: Point::new(2, 0),
+                    end: Point::new(8, 19),
+                },
+                "import foo.Bar as Baz\nimport foo.bar.*\nimport foo.getValue\nimport kotlin.math.max\nimport java.util.UUID".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn no_imports_left() {
+        let content = "import foo.Unused\nclass A\n";
+        let tree = parse(content);
+        let imports = get_imports(&tree, content.as_bytes()).unwrap();
+
+        assert_eq!(
+            organize_imports(&tree, content.as_bytes(), &imports),
+            Some((
+                Span {
+                    start: Point::new(0, 0),
+                    end: Point::new(1, 0),
+                },
+                String::new()
+            ))
+        );
+    }
+
+    #[test]
+    fn comments_between_imports() {
+        for content in [
+            "import foo.Unused\n// keep me\nimport foo.Bar\n\nval x = Bar()\n",
+            "import foo.Bar /* why */\nimport foo.Unused\n\nval x = Bar()\n",
+        ] {
+            let tree = parse(content);
+            let imports = get_imports(&tree, content.as_bytes()).unwrap();
+
+            assert_eq!(organize_imports(&tree, content.as_bytes(), &imports), None);
+        }
+    }
+
+    #[test]
+    fn comments_around_imports() {
+        let content = "// header\nimport foo.Unused\nimport foo.Bar\n\n// body\nval x = Bar()\n";
+        let tree = parse(content);
+        let imports = get_imports(&tree, content.as_bytes()).unwrap();
+
+        assert_eq!(
+            organize_imports(&tree, content.as_bytes(), &imports),
+            Some((
+                Span {
+                    start: Point::new(1, 0),
+                    end: Point::new(2, 14),
+                },
+                "import foo.Bar".to_string()
+            ))
+        );
+    }
+}
@@ -0,0 +1,177 @@
+use anyhow::Result;
+use tree_sitter::Node;
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone)]
+pub enum KDocTag {
+    Param {
+        name: String,
+        description: String,
+    },
+    Return(String),
+    Throws {
+        exception: String,
+        description: String,
+    },
+    See(String),
+    Sample(String),
+}
+
+impl KDocTag {
+    fn new(tag: &str, rest: &str) -> Option<KDocTag> {
+        let (first, description) = rest
+            .split_once(char::is_whitespace)
+            .map(|(first, description)| (first.to_string(), description.trim().to_string()))
+            .unwrap_or((rest.to_string(), String::new()));
+
+        match tag {
+            "@param" => Some(KDocTag::Param {
+                name: first,
+                description,
+            }),
+            "@return" => Some(KDocTag::Return(rest.to_string())),
+            "@throws" | "@exception" => Some(KDocTag::Throws {
+                exception: first,
+                description,
+            }),
+            "@see" => Some(KDocTag::See(rest.to_string())),
+            "@sample" => Some(KDocTag::Sample(rest.to_string())),
+            _ => None,
+        }
+    }
+
+    fn append(&mut self, line: &str) {
+        let description = match self {
+            KDocTag::Param { description, .. }
+            | KDocTag::Return(description)
+            | KDocTag::Throws { description, .. }
+            | KDocTag::See(description)
+            | KDocTag::Sample(description) => description,
+        };
+        if !description.is_empty() {
+            description.push(' ');
+        }
+        description.push_str(line);
+    }
+
+    fn to_markdown(&self) -> String {
+        match self {
+            KDocTag::Param { name, description } => format!("*@param* `{name}` {description}"),
+            KDocTag::Return(description) => format!("*@return* {description}"),
+            KDocTag::Throws {
+                exception,
+                description,
+            } => format!("*@throws* `{exception}` {description}"),
+            KDocTag::See(reference) => format!("*@see* `{reference}`"),
+            KDocTag::Sample(reference) => format!("*@sample* `{reference}`"),
+        }
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone)]
+pub struct KDoc {
+    pub text: String,
+    pub description: String,
+    pub tags: Vec<KDocTag>,
+}
+
+impl KDoc {
+    pub fn new(text: &str) -> KDoc {
+        let body = text
+            .trim_start_matches("/**")
+            .trim_end_matches("*/")
+            .lines()
+            .map(|line| {
+                let line = line.trim();
+                line.strip_prefix('*').unwrap_or(line).trim()
+            });
+
+        let mut description = Vec::new();
+        let mut tags: Vec<KDocTag> = Vec::new();
+        // unknown tags swallow their lines instead of extending the previous tag
+        let mut in_tag = false;
+        for line in body {
+            if line.starts_with('@') {
+                let (tag, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+                match KDocTag::new(tag, rest.trim()) {
+                    Some(tag) => {
+                        tags.push(tag);
+                        in_tag = true;
+                    }
+                    None => in_tag = false,
+                }
+            } else if !tags.is_empty() {
+                if in_tag && !line.is_empty() {
+                    if let Some(tag) = tags.last_mut() {
+                        tag.append(line);
+                    }
+                }
+            } else {
+                description.push(line);
+            }
+        }
+
+        KDoc {
+            text: text.to_string(),
+            description: description.join("\n").trim().to_string(),
+            tags,
+        }
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut sections = Vec::new();
+        if !self.description.is_empty() {
+            sections.push(self.description.clone());
+        }
+        if !self.tags.is_empty() {
+            sections.push(
+                self.tags
+                    .iter()
+                    .map(KDocTag::to_markdown)
+                    .collect::<Vec<String>>()
+                    .join("\n\n"),
+            );
+        }
+
+        sections.join("\n\n")
+    }
+}
+
+// a kdoc comment is the sibling right before the documented declaration
+pub fn get_kdoc(node: &Node, content: &[u8]) -> Result<Option<KDoc>> {
+    let Some(prev) = node
+        .prev_named_sibling()
+        .filter(|prev| prev.kind() == "multiline_comment")
+    else {
+        return Ok(None);
+    };
+
+    let text = prev.utf8_text(content)?;
+    if !text.starts_with("/**") {
+        return Ok(None);
+    }
+
+    Ok(Some(KDoc::new(text)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{KDoc, KDocTag};
+
+    #[test]
+    fn kdoc_tags() {
+        let text = "/**\n * Does a thing.\n *\n * @param x the x,\n *   continued\n * @author someone\n * @return the result\n */";
+
+        let kdoc = KDoc::new(text);
+        assert_eq!(kdoc.description, "Does a thing.");
+        assert_eq!(
+            kdoc.tags,
+            vec![
+                KDocTag::Param {
+                    name: "x".to_string(),
+                    description: "the x, continued".to_string(),
+                },
+                KDocTag::Return("the result".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,82 @@
+// where the cursor is, decides which keywords make sense
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum KeywordContext {
+    TopLevel,
+    ClassBody,
+    FunctionBody,
+}
+
+const DECLARATION_KEYWORDS: [&str; 19] = [
+    "class",
+    "interface",
+    "object",
+    "fun",
+    "val",
+    "var",
+    "typealias",
+    "sealed",
+    "data",
+    "enum",
+    "annotation",
+    "abstract",
+    "open",
+    "private",
+    "internal",
+    "public",
+    "inline",
+    "const",
+    "suspend",
+];
+
+const CLASS_BODY_KEYWORDS: [&str; 7] = [
+    "override",
+    "protected",
+    "lateinit",
+    "inner",
+    "companion object",
+    "init",
+    "constructor",
+];
+
+const STATEMENT_KEYWORDS: [&str; 13] = [
+    "if", "when", "for", "while", "do", "try", "return", "throw", "break", "continue", "val",
+    "var", "fun",
+];
+
+// keywords with their snippet, if they expand to more than themselves
+pub fn keywords(context: KeywordContext) -> Vec<(&'static str, Option<&'static str>)> {
+    let keywords: Vec<&str> = match context {
+        KeywordContext::TopLevel => ["package", "import"]
+            .into_iter()
+            .chain(DECLARATION_KEYWORDS)
+            .collect(),
+        KeywordContext::ClassBody => DECLARATION_KEYWORDS
+            .into_iter()
+            .chain(CLASS_BODY_KEYWORDS)
+            .collect(),
+        KeywordContext::FunctionBody => STATEMENT_KEYWORDS.to_vec(),
+    };
+
+    keywords
+        .into_iter()
+        .map(|keyword| (keyword, snippet(keyword)))
+        .collect()
+}
+
+fn snippet(keyword: &str) -> Option<&'static str> {
+    match keyword {
+        "class" => Some("class ${1:Name} {\n\t$0\n}"),
+        "interface" => Some("interface ${1:Name} {\n\t$0\n}"),
+        "object" => Some("object ${1:Name} {\n\t$0\n}"),
+        "fun" => Some("fun ${1:name}($2) {\n\t$0\n}"),
+        "companion object" => Some("companion object {\n\t$0\n}"),
+        "init" => Some("init {\n\t$0\n}"),
+        "if" => Some("if ($1) {\n\t$0\n}"),
+        "when" => Some("when ($1) {\n\t$0\n}"),
+        "for" => Some("for ($1 in $2) {\n\t$0\n}"),
+        "while" => Some("while ($1) {\n\t$0\n}"),
+        "do" => Some("do {\n\t$0\n} while ($1)"),
+        "try" => Some("try {\n\t$0\n} catch (${1:e}: ${2:Exception}) {\n}"),
+        _ => None,
+    }
+}
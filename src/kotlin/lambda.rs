@@ -1,7 +1,7 @@
 use anyhow::{bail, Context, Result};
 use tree_sitter::Node;
 
-use super::literal::Literal;
+use super::{expression::Expression, literal::Literal};
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub struct AnnotatedLambda {
@@ -30,4 +30,8 @@ impl AnnotatedLambda {
             lambda_literal: lambda_literal.context("no lambda_literal found")?,
         })
     }
+
+    pub fn walk_expressions<'a>(&'a self, f: &mut impl FnMut(&'a Expression)) {
+        self.lambda_literal.walk_expressions(f);
+    }
 }
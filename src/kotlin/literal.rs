@@ -1,20 +1,30 @@
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use tree_sitter::Node;
 
 use super::{
     class::ClassBody,
     delegation::Delegation,
+    expression::Expression,
+    span::Span,
     statement::{self, Statement},
     variable_declaration::{MultiVariableDeclaration, VariableDeclaration},
 };
 
+#[derive(Debug, Hash, PartialEq, Eq, Clone)]
+pub enum StringPart {
+    Literal(String),
+    Interpolated(Box<Expression>),
+}
+
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub enum Literal {
     Boolean(String),
     String(String),
+    StringTemplate(Vec<StringPart>),
     Integer(String),
     Long(String),
-    Object(ClassBody, Vec<Delegation>),
+    // `object : Foo` may leave out the body and `object {}` the supertypes
+    Object(Option<ClassBody>, Vec<Delegation>),
     Character(String),
     Lambda(Option<Vec<Statement>>, Option<Vec<LambdaParameter>>),
     Real(String),
@@ -26,6 +36,7 @@ impl Literal {
     pub fn new(node: &Node, content: &[u8]) -> Result<Literal> {
         match node.kind() {
             "boolean_literal" => Ok(Literal::Boolean(node.utf8_text(content)?.to_string())),
+            "string_literal" if node.named_child_count() > 0 => string_template(node, content),
             "string_literal" => Ok(Literal::String(node.utf8_text(content)?.to_string())),
             "integer_literal" => Ok(Literal::Integer(node.utf8_text(content)?.to_string())),
             "character_literal" => Ok(Literal::Character(node.utf8_text(content)?.to_string())),
@@ -33,20 +44,16 @@ impl Literal {
             "real_literal" => Ok(Literal::Real(node.utf8_text(content)?.to_string())),
             "hex_literal" => Ok(Literal::Hex(node.utf8_text(content)?.to_string())),
             "object_literal" => {
+                let mut body = None;
                 let mut delegations = Vec::new();
                 let mut cursor = node.walk();
                 for child in node.children(&mut cursor) {
                     match child.kind() {
-                        "object" | ":" => {}
+                        "object" | ":" | "," | "line_comment" | "multiline_comment" => {}
                         "delegation_specifier" => {
                             delegations.push(Delegation::new(&child, content)?)
                         }
-                        "class_body" => {
-                            return Ok(Literal::Object(
-                                ClassBody::new_class_body(&child, content)?,
-                                delegations,
-                            ))
-                        }
+                        "class_body" => body = Some(ClassBody::new_class_body(&child, content)?),
                         _ => {
                             bail!(
                                 "[Literal] unhandled node {} '{}' at {}",
@@ -58,7 +65,7 @@ impl Literal {
                     }
                 }
 
-                bail!("[Literal] no class_body at {}", node.start_position());
+                Ok(Literal::Object(body, delegations))
             }
             "lambda_literal" => {
                 let mut statements = None;
@@ -87,6 +94,83 @@ impl Literal {
             }
         }
     }
+
+    pub fn walk_expressions<'a>(&'a self, f: &mut impl FnMut(&'a Expression)) {
+        match self {
+            Literal::Object(body, delegations) => {
+                for delegation in delegations {
+                    delegation.walk_expressions(f);
+                }
+                if let Some(body) = body {
+                    body.walk_expressions(f);
+                }
+            }
+            Literal::Lambda(statements, _) => {
+                for statement in statements.iter().flatten() {
+                    statement.walk_expressions(f);
+                }
+            }
+            Literal::StringTemplate(parts) => {
+                for part in parts {
+                    if let StringPart::Interpolated(expression) = part {
+                        expression.walk_expressions(f);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// the text between interpolations is not a node of its own, so it is cut out by byte offsets
+fn string_template(node: &Node, content: &[u8]) -> Result<Literal> {
+    let text = node.utf8_text(content)?;
+    let quote_len = if text.starts_with("\"\"\"") { 3 } else { 1 };
+    let end = node.end_byte() - quote_len;
+    let mut parts = Vec::new();
+    let mut start = node.start_byte() + quote_len;
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        match child.kind() {
+            "$" | "${" => push_text(&mut parts, &content[start..child.start_byte()])?,
+            "}" => {}
+            "interpolated_identifier" => {
+                parts.push(StringPart::Interpolated(Box::new(Expression::Identifier {
+                    identifier: child.utf8_text(content)?.to_string(),
+                    span: Span::new(&child),
+                })))
+            }
+            "interpolated_expression" => {
+                parts.push(StringPart::Interpolated(Box::new(Expression::new(
+                    &child.child(0).context(format!(
+                        "[Literal::StringTemplate] no expression at {}",
+                        child.start_position()
+                    ))?,
+                    content,
+                )?)))
+            }
+            _ => {
+                bail!(
+                    "[Literal::StringTemplate] unhandled child {} '{}' at {}",
+                    child.kind(),
+                    child.utf8_text(content)?,
+                    child.start_position(),
+                )
+            }
+        }
+        start = child.end_byte();
+    }
+    push_text(&mut parts, &content[start..end])?;
+
+    Ok(Literal::StringTemplate(parts))
+}
+
+fn push_text(parts: &mut Vec<StringPart>, text: &[u8]) -> Result<()> {
+    if !text.is_empty() {
+        parts.push(StringPart::Literal(std::str::from_utf8(text)?.to_string()));
+    }
+
+    Ok(())
 }
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
@@ -122,3 +206,90 @@ fn get_parameters(node: &Node, content: &[u8]) -> Result<Vec<LambdaParameter>> {
 
     Ok(parameters)
 }
+
+#[cfg(test)]
+mod tests {
+    use tree_sitter::Point;
+
+    use crate::kotlin::{
+        expression::Expression,
+        span::Span,
+        test_utils::{find_node, parse},
+    };
+
+    use super::{Literal, StringPart};
+
+    #[test]
+    fn string_template() {
+        let content = "val s = \"a $b c ${1} d\"";
+        let tree = parse(content);
+        let node = find_node(&tree, "string_literal");
+
+        assert_eq!(
+            Literal::new(&node, content.as_bytes()).unwrap(),
+            Literal::StringTemplate(vec![
+                StringPart::Literal("a ".to_string()),
+                StringPart::Interpolated(Box::new(Expression::Identifier {
+                    identifier: "b".to_string(),
+                    span: Span {
+                        start: Point::new(0, 12),
+                        end: Point::new(0, 13),
+                    },
+                })),
+                StringPart::Literal(" c ".to_string()),
+                StringPart::Interpolated(Box::new(Expression::Literal(Literal::Integer(
+                    "1".to_string(),
+                )))),
+                StringPart::Literal(" d".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn object_without_supertypes() {
+        let content = "val o = object {}";
+        let tree = parse(content);
+        let node = find_node(&tree, "object_literal");
+
+        let Literal::Object(body, delegations) = Literal::new(&node, content.as_bytes()).unwrap()
+        else {
+            panic!("expected object literal");
+        };
+        assert!(body.is_some());
+        assert!(delegations.is_empty());
+    }
+
+    #[test]
+    fn object_with_empty_body() {
+        let content = "val o = object : Runnable {}";
+        let tree = parse(content);
+        let node = find_node(&tree, "object_literal");
+
+        let Literal::Object(body, delegations) = Literal::new(&node, content.as_bytes()).unwrap()
+        else {
+            panic!("expected object literal");
+        };
+        assert!(body.is_some_and(|body| body.functions().is_empty()));
+        assert_eq!(delegations[0].type_name(), Some("Runnable"));
+    }
+
+    #[test]
+    fn object_with_several_supertypes() {
+        let content = "val o = object : A, B(1) {\n    fun x() {}\n}";
+        let tree = parse(content);
+        let node = find_node(&tree, "object_literal");
+
+        let Literal::Object(body, delegations) = Literal::new(&node, content.as_bytes()).unwrap()
+        else {
+            panic!("expected object literal");
+        };
+        assert!(body.is_some_and(|body| body.functions().len() == 1));
+        assert_eq!(
+            delegations
+                .iter()
+                .map(|delegation| delegation.type_name())
+                .collect::<Vec<Option<&str>>>(),
+            vec![Some("A"), Some("B")]
+        );
+    }
+}
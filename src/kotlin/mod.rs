@@ -1,75 +1,1170 @@
-use std::{collections::HashMap, hash::Hash, path::PathBuf};
+//! Analysis of Kotlin source files.
+//!
+//! A file is parsed with tree-sitter first, then [`KotlinFile::new`] walks the syntax tree
+//! into the types of this module. Each type is built from one kind of node by its own
+//! `new`, which hands the children it understands to the types responsible for them, so
+//! the model follows the grammar closely: a [`Class`] owns its body, a [`Function`] its
+//! statements, and statements their expressions. Only top level declarations are stored
+//! on [`KotlinFile`]; everything else is reached through `walk_declarations` and
+//! `walk_expressions`.
+//!
+//! Spans keep tree-sitter's positions, rows and byte columns. Converting them to the
+//! UTF-16 columns of the language server protocol is up to the caller.
+//!
+//! Analysis is all or nothing. A node that a type doesn't know how to handle, including
+//! most `ERROR` nodes from syntax errors, fails the whole file with a message naming the
+//! type, the node kind, its text and its position, e.g.
+//! `[ClassBody::Class] unhandled child ERROR '@' at (3, 4)`. A few known grammar quirks
+//! are recovered instead. Syntax errors themselves are reported separately by
+//! [`syntax_errors`], which only looks at the tree and works even when analysis fails.
+
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
 use anyhow::{Context, Result};
-use tree_sitter::{Parser, Tree};
+use tree_sitter::{Node, Parser, Point, Tree};
 use walkdir::WalkDir;
 
-use self::{class::Class, import::Import, package::Package};
+use self::{
+    expression::{Expression, NavigationOperator, PostfixUnaryOperator},
+    function::Parameter,
+    import::Import,
+    literal::Literal,
+    object::Object,
+    package::Package,
+    property::Property,
+    type_alias::TypeAlias,
+    types::Type,
+};
 
 mod argument;
 mod assignment;
 mod class;
 mod constructor_invocation;
+mod declaration;
 mod delegation;
 mod expression;
+mod folding;
+mod format;
 mod function;
 mod getter;
+mod highlight;
 mod import;
+mod kdoc;
+mod keyword;
 mod label;
 mod lambda;
 mod literal;
 mod modifier;
+mod module;
 mod object;
 mod package;
 mod property;
+mod resolve;
+mod scope;
+mod span;
 mod statement;
+#[cfg(test)]
+pub(crate) mod test_utils;
+mod type_alias;
 mod types;
 mod variable_declaration;
 
-#[derive(Debug, Hash, PartialEq, Eq)]
+pub use self::{
+    class::{Class, ClassType, SynthesizedFunction},
+    declaration::Declaration,
+    folding::{folding_ranges, FoldingKind},
+    format::format_edits,
+    function::Function,
+    highlight::{highlights, TokenType},
+    import::{get_imports, organize_imports},
+    keyword::{keywords, KeywordContext},
+    module::ModuleGraph,
+    resolve::{occurrences, Symbol},
+    scope::{scope_at, Scope, ScopeEntry},
+    span::Span,
+};
+
+/// The analyzed contents of a single `.kt` file.
+///
+/// Only top level declarations are stored here, nested ones hang off their
+/// parent [`Class`] or object.
+///
+/// ```
+/// use kotlin_ls::kotlin::KotlinFile;
+///
+/// let file: KotlinFile = "class Foo {\n    fun bar() {}\n}\n".parse().unwrap();
+/// assert_eq!(file.classes[0].name, "Foo");
+/// ```
+#[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub struct KotlinFile {
     pub package: Package,
     pub imports: Vec<Import>,
     pub classes: Vec<Class>,
+    pub objects: Vec<Object>,
+    pub type_aliases: Vec<TypeAlias>,
+    pub functions: Vec<Function>,
+    pub properties: Vec<Property>,
 }
 
 impl KotlinFile {
     pub fn new(tree: &Tree, content: &[u8]) -> Result<KotlinFile> {
         let package = package::get_package(tree, content)?;
         let imports = import::get_imports(tree, content)?;
-        let classes = class::get_classes(tree, content)?;
+        let mut classes = class::get_classes(tree, content)?;
+        let objects = object::get_top_level_objects(tree, content)?;
+        class::set_sealed_subclasses(&mut classes, &objects);
+        let type_aliases = type_alias::get_type_aliases(tree, content)?;
+        let functions = function::get_top_level_functions(tree, content)?;
+        let properties = property::get_top_level_properties(tree, content)?;
 
         Ok(KotlinFile {
             package,
             imports,
             classes,
+            objects,
+            type_aliases,
+            functions,
+            properties,
+        })
+    }
+
+    /// Parses and analyzes content that doesn't have to come from a file.
+    pub fn from_content(content: &[u8]) -> Result<KotlinFile> {
+        let tree = new_parser()?
+            .parse(content, None)
+            .context("failed to parse")?;
+
+        KotlinFile::new(&tree, content)
+    }
+
+    /// Calls `f` for every expression in the file, outer expressions first.
+    pub fn walk_expressions<'a>(&'a self, f: &mut impl FnMut(&'a Expression)) {
+        for class in &self.classes {
+            class.walk_expressions(f);
+        }
+        for object in &self.objects {
+            object.walk_expressions(f);
+        }
+        for function in &self.functions {
+            function.walk_expressions(f);
+        }
+        for property in &self.properties {
+            property.walk_expressions(f);
+        }
+    }
+
+    // usages of a name in this file, matched by identifier only
+    pub fn find_references(&self, name: &str) -> Vec<Span> {
+        let mut references = Vec::new();
+        self.walk_expressions(&mut |expression| {
+            if let Some((identifier, span)) = expression.identifier() {
+                if identifier == name {
+                    references.push(span);
+                }
+            }
+        });
+        references
+    }
+
+    // calls of a function with the given name, the span is the one of the name at the call
+    pub fn find_calls(&self, name: &str) -> Vec<Span> {
+        let mut calls = Vec::new();
+        self.walk_expressions(&mut |expression| {
+            if let Expression::Call { expression, .. } = expression {
+                if let Some((identifier, span)) = expression.identifier() {
+                    if identifier == name {
+                        calls.push(span);
+                    }
+                }
+            }
+        });
+        calls
+    }
+
+    // usages of a name that are the target of an assignment, e.g. `x` in `x = 1` or `this.x += 1`
+    pub fn find_assignments(&self, name: &str) -> Vec<Span> {
+        let mut assignments = Vec::new();
+        self.walk_expressions(&mut |expression| {
+            if let Expression::DirectlyAssignable(target) = expression {
+                if let Some((identifier, span)) = target.identifier() {
+                    if identifier == name {
+                        assignments.push(span);
+                    }
+                }
+            }
+        });
+        assignments
+    }
+
+    // usages of a name under the aliases it is imported as, e.g. `Baz` for `import foo.Bar as Baz`
+    pub fn find_alias_references(&self, name: &str) -> Vec<Span> {
+        self.imports
+            .iter()
+            .filter(|import| import.imported_name() == name)
+            .filter_map(|import| import.alias.as_deref())
+            .flat_map(|alias| self.find_references(alias))
+            .collect()
+    }
+
+    // class name of a variable visible at the given point, taken from its declared type,
+    // parameters and locals of the enclosing declarations shadow properties elsewhere
+    pub fn type_name_at(&self, name: &str, point: Point) -> Option<&str> {
+        let mut scoped = None;
+        let mut global = None;
+        self.walk_declarations(&mut |declaration| match declaration {
+            Declaration::Class(class) if class.span.contains(point) => {
+                if name == "this" {
+                    scoped = Some(class.name.as_str());
+                }
+                for parameter in class.constructor.iter().flat_map(|c| &c.parameters) {
+                    if parameter.name == name {
+                        scoped = parameter.data_type.class_name();
+                    }
+                }
+            }
+            Declaration::Function(function) if function.span.contains(point) => {
+                for parameter in &function.parameters {
+                    if parameter.name == name {
+                        scoped = parameter.type_identifier.class_name();
+                    }
+                }
+                for property in function.local_properties() {
+                    for declaration in property.variable_declarations() {
+                        if declaration.identifier == name
+                            && declaration.identifier_span.end <= point
+                        {
+                            scoped = declaration.data_type.as_ref().and_then(Type::class_name);
+                        }
+                    }
+                }
+            }
+            Declaration::Property(_, declaration)
+                if global.is_none() && declaration.identifier == name =>
+            {
+                global = declaration.data_type.as_ref().and_then(Type::class_name);
+            }
+            _ => {}
+        });
+
+        scoped.or(global)
+    }
+
+    // innermost body around the point, local declarations are not visited
+    pub fn keyword_context(&self, point: Point) -> KeywordContext {
+        let mut context = KeywordContext::TopLevel;
+        self.walk_declarations(&mut |declaration| {
+            let contains = match declaration {
+                Declaration::Class(class) => class.span.contains(point),
+                Declaration::Object(object) => object.span.contains(point),
+                Declaration::Function(function) => function.span.contains(point),
+                Declaration::Property(property, _) => property.span.contains(point),
+                // nothing can be typed inside an alias that keywords would be offered for
+                Declaration::TypeAlias(_) => false,
+            };
+            if contains {
+                context = match declaration {
+                    Declaration::Class(_) | Declaration::Object(_) => KeywordContext::ClassBody,
+                    Declaration::Function(_)
+                    | Declaration::Property(..)
+                    | Declaration::TypeAlias(_) => KeywordContext::FunctionBody,
+                };
+            }
+        });
+        context
+    }
+
+    // the import that brings a name into this file, either directly or as its alias
+    pub fn import_of(&self, name: &str) -> Option<&Import> {
+        self.imports.iter().find(|import| match &import.alias {
+            Some(alias) => alias == name,
+            None => import.imported_name() == name,
         })
     }
+
+    // whether a top level declaration can be imported from this file under the name
+    pub fn declares_top_level(&self, name: &str) -> bool {
+        self.classes.iter().any(|class| class.name == name)
+            || self.objects.iter().any(|object| object.name == name)
+            || self.functions.iter().any(|function| function.name == name)
+            || self.type_aliases.iter().any(|alias| alias.name == name)
+            || self
+                .properties
+                .iter()
+                .any(|property| property.names().contains(&name))
+    }
+
+    // point and text of an edit that adds `import <path>`, imports are kept sorted
+    pub fn import_edit(&self, path: &str) -> (Point, String) {
+        let line = format!("import {path}\n");
+        if let Some(next) = self
+            .imports
+            .iter()
+            .find(|import| import.path.as_str() > path)
+        {
+            return (Point::new(next.span.start.row, 0), line);
+        }
+        if let Some(last) = self.imports.last() {
+            return (Point::new(last.span.end.row + 1, 0), line);
+        }
+
+        match self.package.span() {
+            Some(span) => (Point::new(span.end.row + 1, 0), format!("\n{line}")),
+            None => (Point::new(0, 0), format!("{line}\n")),
+        }
+    }
+
+    // parameter names for positional call arguments, `resolve` looks up the parameters
+    // of a called function by name
+    pub fn parameter_hints<'b>(
+        &self,
+        resolve: impl Fn(&str) -> Option<&'b [Parameter]>,
+    ) -> Vec<(Point, String)> {
+        let mut hints = Vec::new();
+        self.walk_expressions(&mut |expression| {
+            if let Some((name, call_suffix)) = expression.call() {
+                if let Some(parameters) = resolve(name) {
+                    hints.extend(call_suffix.parameter_hints(parameters));
+                }
+            }
+        });
+        hints
+    }
+
+    // `: Type` after variables without a type annotation whose type can be inferred
+    pub fn type_hints<'b>(
+        &self,
+        return_type: impl Fn(&str) -> Option<&'b str>,
+    ) -> Vec<(Point, String)> {
+        let mut properties = Vec::new();
+        self.walk_declarations(&mut |declaration| match declaration {
+            Declaration::Property(property, _) => properties.push(property),
+            Declaration::Function(function) => properties.extend(function.local_properties()),
+            Declaration::Class(_) | Declaration::Object(_) | Declaration::TypeAlias(_) => {}
+        });
+
+        let mut hints = Vec::new();
+        for property in properties {
+            if let (Some(inferred), [declaration]) = (
+                property.inferred_type(&return_type),
+                property.variable_declarations().as_slice(),
+            ) {
+                hints.push((declaration.identifier_span.end, format!(": {inferred}")));
+            }
+        }
+        hints
+    }
+
+    // `.` on a receiver declared with a nullable type, locals and parameters only. Names
+    // that are compared against `null`, checked with `is`, asserted with `!!`, guarded by
+    // `?: return` or assigned a non-null value anywhere in the function are skipped since
+    // they are likely smart cast
+    pub fn null_safety_warnings(&self) -> Vec<(Span, String)> {
+        let mut functions = Vec::new();
+        self.walk_declarations(&mut |declaration| {
+            if let Declaration::Function(function) = declaration {
+                functions.push(function);
+            }
+        });
+
+        let mut warnings = Vec::new();
+        for function in functions {
+            let mut checked = Vec::new();
+            // the value of an assignment is walked right after its target
+            let mut assigned: Option<(&Expression, &str)> = None;
+            function.walk_expressions(&mut |expression| {
+                if let Some((target, name)) = assigned {
+                    if std::ptr::eq(expression, target) {
+                        return;
+                    }
+                    if !matches!(expression, Expression::Literal(Literal::Null)) {
+                        checked.push(name);
+                    }
+                    assigned = None;
+                }
+
+                match expression {
+                    Expression::DirectlyAssignable(target) => {
+                        assigned = target_name(target).map(|name| (target.as_ref(), name));
+                    }
+                    Expression::Equality { left, right, .. } => {
+                        for (side, other) in [(left, right), (right, left)] {
+                            if matches!(other.as_ref(), Expression::Literal(Literal::Null)) {
+                                checked.extend(target_name(side));
+                            }
+                        }
+                    }
+                    Expression::CheckIs { left, .. } => checked.extend(target_name(left)),
+                    Expression::Postfix {
+                        operator: PostfixUnaryOperator::NullAssertion,
+                        expression,
+                    } => checked.extend(target_name(expression)),
+                    Expression::Elvis { left, right }
+                        if matches!(
+                            right.as_ref(),
+                            Expression::JumpReturn(..) | Expression::JumpThrow(_)
+                        ) =>
+                    {
+                        checked.extend(target_name(left));
+                    }
+                    _ => {}
+                }
+            });
+
+            let scopes = Scope::from_function(function);
+            function.walk_expressions(&mut |expression| {
+                let Expression::Navigation {
+                    expression,
+                    navigation_suffix,
+                } = expression
+                else {
+                    return;
+                };
+                let Expression::Identifier { identifier, span } = expression.as_ref() else {
+                    return;
+                };
+                if navigation_suffix.operator() != NavigationOperator::Dot
+                    || checked.contains(&identifier.as_str())
+                {
+                    return;
+                }
+
+                let nullable = scope_at(&scopes, span.start)
+                    .and_then(|scope| scope.get(identifier))
+                    .is_some_and(|entry| matches!(entry.data_type, Some(Type::Nullable(..))));
+                if nullable {
+                    warnings.push((
+                        *span,
+                        "Unsafe call on nullable receiver; use `?.` instead".to_string(),
+                    ));
+                }
+            });
+        }
+        warnings
+    }
+
+    // local declarations inside function bodies are not visited
+    pub fn walk_declarations<'a>(&'a self, f: &mut impl FnMut(Declaration<'a>)) {
+        for class in &self.classes {
+            class.walk_declarations(f);
+        }
+        for object in &self.objects {
+            object.walk_declarations(f);
+        }
+        for function in &self.functions {
+            f(Declaration::Function(function));
+        }
+        for property in &self.properties {
+            property.walk_declarations(f);
+        }
+        for alias in &self.type_aliases {
+            f(Declaration::TypeAlias(alias));
+        }
+    }
+
+    pub fn find_declarations(&self, name: &str) -> Vec<Span> {
+        let mut declarations = Vec::new();
+        self.walk_declarations(&mut |declaration| {
+            if declaration.name() == name {
+                declarations.push(declaration.name_span());
+            }
+        });
+        declarations
+    }
+
+    // classes with the name, nested classes and classes inside objects included
+    pub fn find_class_by_name(&self, name: &str) -> Vec<&Class> {
+        let mut classes = Vec::new();
+        self.walk_declarations(&mut |declaration| {
+            if let Declaration::Class(class) = declaration {
+                if class.name == name {
+                    classes.push(class);
+                }
+            }
+        });
+        classes
+    }
+
+    // top level and member functions with the name, overloads included
+    pub fn find_function_by_name(&self, name: &str) -> Vec<&Function> {
+        let mut functions = Vec::new();
+        self.walk_declarations(&mut |declaration| {
+            if let Declaration::Function(function) = declaration {
+                if function.name == name {
+                    functions.push(function);
+                }
+            }
+        });
+        functions
+    }
+
+    // top level and member properties declaring the name, a destructuring property is
+    // returned once for each matching variable
+    pub fn find_property_by_name(&self, name: &str) -> Vec<&Property> {
+        let mut properties = Vec::new();
+        self.walk_declarations(&mut |declaration| {
+            if let Declaration::Property(property, variable) = declaration {
+                if variable.identifier == name {
+                    properties.push(property);
+                }
+            }
+        });
+        properties
+    }
+
+    // innermost top level or member function around the given point, local functions are
+    // part of the function they are declared in
+    pub fn function_at(&self, point: Point) -> Option<&Function> {
+        let mut found: Option<&Function> = None;
+        self.walk_declarations(&mut |declaration| {
+            if let Declaration::Function(function) = declaration {
+                if function.span.contains(point)
+                    && found.is_none_or(|found| found.span.start < function.span.start)
+                {
+                    found = Some(function);
+                }
+            }
+        });
+        found
+    }
+
+    // innermost class or object around the given point
+    pub fn type_at(&self, point: Point) -> Option<Declaration<'_>> {
+        let mut found: Option<Declaration> = None;
+        self.walk_declarations(&mut |declaration| {
+            if matches!(declaration, Declaration::Class(_) | Declaration::Object(_))
+                && declaration.span().contains(point)
+                && found.is_none_or(|found| found.span().start < declaration.span().start)
+            {
+                found = Some(declaration);
+            }
+        });
+        found
+    }
+
+    // classes and objects that directly extend or implement the given type
+    pub fn subtypes_of(&self, name: &str) -> Vec<Declaration<'_>> {
+        let mut subtypes = Vec::new();
+        self.walk_declarations(&mut |declaration| {
+            if declaration.supertypes().contains(&name) {
+                subtypes.push(declaration);
+            }
+        });
+        subtypes
+    }
+
+    // identifier of a usage or a declaration name at the given point
+    pub fn identifier_at(&self, point: Point) -> Option<(&str, Span)> {
+        let mut found = None;
+        self.walk_expressions(&mut |expression| {
+            if let Some((identifier, span)) = expression.identifier() {
+                if span.contains(point) {
+                    found = Some((identifier, span));
+                }
+            }
+        });
+        self.walk_declarations(&mut |declaration| {
+            if declaration.name_span().contains(point) {
+                found = Some((declaration.name(), declaration.name_span()));
+            }
+        });
+        found
+    }
+}
+
+impl FromStr for KotlinFile {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<KotlinFile> {
+        KotlinFile::from_content(s.as_bytes())
+    }
+}
+
+const HARD_KEYWORDS: [&str; 28] = [
+    "as",
+    "break",
+    "class",
+    "continue",
+    "do",
+    "else",
+    "false",
+    "for",
+    "fun",
+    "if",
+    "in",
+    "interface",
+    "is",
+    "null",
+    "object",
+    "package",
+    "return",
+    "super",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typealias",
+    "typeof",
+    "val",
+    "var",
+    "when",
+    "while",
+];
+
+pub fn is_valid_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    let Some(first) = chars.next() else {
+        return false;
+    };
+
+    (first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !HARD_KEYWORDS.contains(&name)
+}
+
+// name of a local or parameter an expression refers to, e.g. `x` in `(x)`
+fn target_name(expression: &Expression) -> Option<&str> {
+    match expression {
+        Expression::Identifier { identifier, .. } => Some(identifier),
+        Expression::Parenthesized(expression) => target_name(expression),
+        _ => None,
+    }
+}
+
+// ERROR and MISSING nodes that tree-sitter inserted while recovering
+pub fn syntax_errors(tree: &Tree) -> Vec<(Span, String)> {
+    let mut errors = Vec::new();
+    let mut cursor = tree.walk();
+    loop {
+        let node = cursor.node();
+        if node.is_missing() {
+            errors.push((Span::new(&node), format!("missing {}", node.kind())));
+        } else if node.is_error() {
+            errors.push((Span::new(&node), "syntax error".to_string()));
+        } else if node.has_error() && cursor.goto_first_child() {
+            continue;
+        } else if node.has_error() {
+            // a missing token the grammar aliased to a named node, like an identifier
+            errors.push((Span::new(&node), format!("missing {}", node.kind())));
+        }
+
+        while !cursor.goto_next_sibling() {
+            if !cursor.goto_parent() {
+                return errors;
+            }
+        }
+    }
 }
 
+// name of the function called at the given point and the index of the argument the
+// point is in, the point has to be inside the parentheses
+pub fn call_at<'a>(tree: &Tree, content: &'a [u8], point: Point) -> Option<(&'a str, usize)> {
+    let mut node = tree.root_node().descendant_for_point_range(point, point);
+    while let Some(current) = node {
+        if current.kind() == "value_arguments" {
+            break;
+        }
+        node = current.parent();
+    }
+    let arguments = node?;
+
+    let mut cursor = arguments.walk();
+    let children: Vec<Node> = arguments.children(&mut cursor).collect();
+    let open = children.iter().find(|child| child.kind() == "(")?;
+    let inside = open.end_position() <= point
+        && children
+            .iter()
+            .find(|child| child.kind() == ")" && !child.is_missing())
+            .is_none_or(|close| point <= close.start_position());
+    if !inside {
+        return None;
+    }
+    let index = children
+        .iter()
+        .filter(|child| child.kind() == "," && child.end_position() <= point)
+        .count();
+
+    let call = arguments.parent()?.parent()?;
+    if call.kind() != "call_expression" {
+        return None;
+    }
+    let callee = call.child(0)?;
+    let identifier = match callee.kind() {
+        "simple_identifier" => callee,
+        "navigation_expression" => {
+            let suffix = callee.child(callee.child_count().checked_sub(1)?)?;
+            suffix.named_child(0)?
+        }
+        _ => return None,
+    };
+
+    Some((identifier.utf8_text(content).ok()?, index))
+}
+
+// spans of the node at the point and all its ancestors up to the source file, innermost
+// first, ancestors covering the same text as their child are left out
+pub fn selection_spans(tree: &Tree, point: Point) -> Vec<Span> {
+    let mut spans: Vec<Span> = Vec::new();
+    let mut node = tree.root_node().descendant_for_point_range(point, point);
+    while let Some(current) = node {
+        let span = Span::new(&current);
+        if spans.last() != Some(&span) {
+            spans.push(span);
+        }
+        node = current.parent();
+    }
+    spans
+}
+
+pub fn kotlin_paths(p: &str) -> impl Iterator<Item = PathBuf> {
+    WalkDir::new(p)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "kt"))
+        .map(|e| e.into_path())
+}
+
+// files are parsed in parallel, see parallel_map
 pub fn from_path(p: &str) -> Result<HashMap<PathBuf, Result<KotlinFile>>> {
+    let paths: Vec<PathBuf> = kotlin_paths(p).collect();
+
+    Ok(parallel_map(&paths, |parser, path| {
+        (path.clone(), from_path_with_parser(parser, path))
+    })?
+    .into_iter()
+    .collect())
+}
+
+// maps the items on one scoped thread per available core. Threads take the next item
+// that nobody has taken yet, so a few large files don't hold up a whole chunk, and each
+// thread creates one parser that it reuses for all of its items. Results keep the order
+// of the items
+pub fn parallel_map<T: Sync, R: Send>(
+    items: &[T],
+    f: impl Fn(&mut Parser, &T) -> R + Sync,
+) -> Result<Vec<R>> {
+    let threads = std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(items.len())
+        .max(1);
+    let next = AtomicUsize::new(0);
+
+    let mapped = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| -> Result<Vec<(usize, R)>> {
+                    let mut parser = new_parser()?;
+                    let mut mapped = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(i) else {
+                            return Ok(mapped);
+                        };
+                        mapped.push((i, f(&mut parser, item)));
+                    }
+                })
+            })
+            .collect();
+
+        let mut mapped = Vec::with_capacity(items.len());
+        for handle in handles {
+            let thread_mapped = handle
+                .join()
+                .map_err(|_| anyhow::anyhow!("parser thread panicked"))??;
+            mapped.extend(thread_mapped);
+        }
+
+        Ok::<_, anyhow::Error>(mapped)
+    })?;
+
+    let mut results: Vec<Option<R>> = (0..items.len()).map(|_| None).collect();
+    for (i, result) in mapped {
+        results[i] = Some(result);
+    }
+    Ok(results.into_iter().flatten().collect())
+}
+
+pub fn new_parser() -> Result<Parser> {
     let mut parser = Parser::new();
     parser
         .set_language(tree_sitter_kotlin::language())
         .context("failed to create kotlin parser")?;
 
-    let mut files = HashMap::new();
-    for path in WalkDir::new(p)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_file())
-        .filter(|e| e.path().extension().map_or(false, |ext| ext == "kt"))
-        .map(|e| e.into_path())
-    {
-        let content = std::fs::read(&path)?;
-        let tree = parser
-            .parse(&content, None)
-            .context(format!("failed to parse {path:?}"))?;
-        files.insert(
-            path.clone(),
-            KotlinFile::new(&tree, &content).context(format!("failed to analyze {path:?}")),
+    Ok(parser)
+}
+
+pub fn from_path_single(path: &Path) -> Result<KotlinFile> {
+    from_path_with_parser(&mut new_parser()?, path)
+}
+
+pub fn from_path_with_parser(parser: &mut Parser, path: &Path) -> Result<KotlinFile> {
+    let content = std::fs::read(path).context(format!("failed to read {path:?}"))?;
+    let tree = parser
+        .parse(&content, None)
+        .context(format!("failed to parse {path:?}"))?;
+
+    KotlinFile::new(&tree, &content).context(format!("failed to analyze {path:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use tree_sitter::Point;
+
+    use super::{test_utils::parse, KeywordContext, KotlinFile, Span};
+
+    #[test]
+    fn syntax_errors() {
+        let span = |row, start, end| Span {
+            start: Point::new(row, start),
+            end: Point::new(row, end),
+        };
+
+        assert_eq!(
+            super::syntax_errors(&parse("fun f( {\n    val = 1\n}\n")),
+            vec![
+                (span(0, 6, 6), "missing )".to_string()),
+                (span(1, 7, 7), "missing simple_identifier".to_string()),
+            ]
+        );
+        assert_eq!(
+            super::syntax_errors(&parse("val x = listOf(1\n")),
+            vec![(span(0, 0, 16), "syntax error".to_string())]
+        );
+        assert!(super::syntax_errors(&parse("val x = listOf(1)\n")).is_empty());
+    }
+
+    // spans are part of the comparison, so only whitespace that moves no node is ignored
+    #[test]
+    fn trailing_whitespace_is_equal() {
+        let a: KotlinFile = "class Foo {\n    fun bar(x: Int) = x + 1\n}\n"
+            .parse()
+            .unwrap();
+        let b: KotlinFile = "class Foo {  \n    fun bar(x: Int) = x + 1\t\n}\n\n\n"
+            .parse()
+            .unwrap();
+        let c: KotlinFile = "class Foo {\n\n    fun bar(x: Int) = x + 1\n}\n"
+            .parse()
+            .unwrap();
+
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn alias_references() {
+        let file: KotlinFile = "import foo.Bar as Baz\n\nfun f() {\n    Baz()\n}\n"
+            .parse()
+            .unwrap();
+
+        assert!(file.find_references("Bar").is_empty());
+        assert_eq!(
+            file.find_alias_references("Bar"),
+            file.find_references("Baz")
+        );
+        assert_eq!(file.find_alias_references("Bar").len(), 1);
+    }
+
+    #[test]
+    fn assignments() {
+        let file: KotlinFile =
+            "class A {\n    var x = 0\n\n    fun f() {\n        x = 1\n        this.x += x\n    }\n}\n"
+                .parse()
+                .unwrap();
+
+        assert_eq!(
+            file.find_assignments("x"),
+            vec![
+                Span {
+                    start: Point::new(4, 8),
+                    end: Point::new(4, 9),
+                },
+                Span {
+                    start: Point::new(5, 13),
+                    end: Point::new(5, 14),
+                },
+            ]
+        );
+        assert_eq!(file.find_references("x").len(), 3);
+    }
+
+    #[test]
+    fn import_edit() {
+        let file: KotlinFile = "package a\n\nimport b.B\nimport d.D\n\nval x = C()\n"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            file.import_edit("c.C"),
+            (Point::new(3, 0), "import c.C\n".to_string())
+        );
+        assert_eq!(
+            file.import_edit("e.E"),
+            (Point::new(4, 0), "import e.E\n".to_string())
+        );
+
+        let file: KotlinFile = "package a\n\nval x = C()\n".parse().unwrap();
+        assert_eq!(
+            file.import_edit("c.C"),
+            (Point::new(1, 0), "\nimport c.C\n".to_string())
         );
     }
 
-    Ok(files)
+    #[test]
+    fn parameter_hints() {
+        let file: KotlinFile =
+            "fun foo(x: Int, label: String) {\n}\n\nfun bar() {\n    foo(42, label = \"a\")\n}\n"
+                .parse()
+                .unwrap();
+        let parameters = file.functions[0].parameters.clone();
+
+        assert_eq!(
+            file.parameter_hints(|name| (name == "foo").then_some(parameters.as_slice())),
+            vec![(Point::new(4, 8), "x:".to_string())]
+        );
+    }
+
+    #[test]
+    fn type_hints() {
+        let file: KotlinFile = "fun foo(): Long = 1L\n\nfun bar() {\n    val a = 1\n    val b: Int = 2\n    var c = foo()\n    val d = a\n}\n"
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            file.type_hints(|name| (name == "foo").then_some("Long")),
+            vec![
+                (Point::new(3, 9), ": Int".to_string()),
+                (Point::new(5, 9), ": Long".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn call_at() {
+        let content = "fun f() {\n    foo.bar(1, 2)\n}";
+        let tree = parse(content);
+
+        assert_eq!(
+            super::call_at(&tree, content.as_bytes(), Point::new(1, 12)),
+            Some(("bar", 0))
+        );
+        assert_eq!(
+            super::call_at(&tree, content.as_bytes(), Point::new(1, 15)),
+            Some(("bar", 1))
+        );
+        assert_eq!(
+            super::call_at(&tree, content.as_bytes(), Point::new(1, 6)),
+            None
+        );
+    }
+
+    #[test]
+    fn selection_spans() {
+        let content = "fun f() {\n    foo.bar(1)\n}\n";
+        let tree = parse(content);
+
+        let spans: Vec<(Point, Point)> = super::selection_spans(&tree, Point::new(1, 5))
+            .into_iter()
+            .map(|span| (span.start, span.end))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                (Point::new(1, 4), Point::new(1, 7)),
+                (Point::new(1, 4), Point::new(1, 11)),
+                (Point::new(1, 4), Point::new(1, 14)),
+                (Point::new(0, 8), Point::new(2, 1)),
+                (Point::new(0, 0), Point::new(2, 1)),
+                (Point::new(0, 0), Point::new(3, 0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn type_name_at() {
+        let file: KotlinFile = "class A(val repo: Repo) {\n    fun f(user: User?) {\n        val list: List<Int> = listOf()\n        g()\n    }\n}\n"
+            .parse()
+            .unwrap();
+
+        let point = Point::new(3, 8);
+        assert_eq!(file.type_name_at("repo", point), Some("Repo"));
+        assert_eq!(file.type_name_at("user", point), Some("User"));
+        assert_eq!(file.type_name_at("list", point), Some("List"));
+        assert_eq!(file.type_name_at("this", point), Some("A"));
+        assert_eq!(file.type_name_at("user", Point::new(0, 10)), None);
+    }
+
+    #[test]
+    fn keyword_context() {
+        let file: KotlinFile = "class A {\n\n    fun f() {\n\n    }\n}\n".parse().unwrap();
+
+        assert_eq!(
+            file.keyword_context(Point::new(1, 0)),
+            KeywordContext::ClassBody
+        );
+        assert_eq!(
+            file.keyword_context(Point::new(3, 0)),
+            KeywordContext::FunctionBody
+        );
+        assert_eq!(
+            file.keyword_context(Point::new(6, 0)),
+            KeywordContext::TopLevel
+        );
+    }
+
+    #[test]
+    fn calls() {
+        let file: KotlinFile = "class A {\n    fun f() {\n        g()\n        this.g()\n    }\n    fun g() = h(1)\n}\nfun h(x: Int) = x\n"
+            .parse()
+            .unwrap();
+
+        let caller = file.function_at(Point::new(3, 8)).unwrap();
+        assert_eq!(caller.name, "f");
+        assert_eq!(
+            caller
+                .calls()
+                .into_iter()
+                .map(|(name, span)| (name, span.start))
+                .collect::<Vec<_>>(),
+            vec![("g", Point::new(2, 8)), ("g", Point::new(3, 13))]
+        );
+
+        let callers: Vec<&str> = file
+            .find_calls("h")
+            .into_iter()
+            .filter_map(|span| file.function_at(span.start))
+            .map(|function| function.name.as_str())
+            .collect();
+        assert_eq!(callers, vec!["g"]);
+        assert!(file.function_at(Point::new(0, 0)).is_none());
+    }
+
+    #[test]
+    fn type_hierarchy() {
+        let file: KotlinFile = "sealed interface Shape\nclass Circle : Shape, Comparable<Circle> {\n    class Inner\n}\nobject Empty : Shape\n"
+            .parse()
+            .unwrap();
+
+        let circle = file.type_at(Point::new(1, 6)).unwrap();
+        assert_eq!(circle.supertypes(), vec!["Shape", "Comparable"]);
+        assert_eq!(file.type_at(Point::new(2, 10)).unwrap().name(), "Inner");
+
+        let subtypes: Vec<&str> = file
+            .subtypes_of("Shape")
+            .iter()
+            .map(|declaration| declaration.name())
+            .collect();
+        assert_eq!(subtypes, vec!["Circle", "Empty"]);
+    }
+
+    #[test]
+    fn null_safety_warnings() {
+        let file: KotlinFile = "fun f(a: String?, b: String) {\n    val c: String? = null\n    a.length\n    b.length\n    c?.length\n    c.length\n}\nfun g(d: String?) {\n    if (d != null) {\n        d.length\n    }\n}\n"
+            .parse()
+            .unwrap();
+
+        let spans: Vec<Span> = file
+            .null_safety_warnings()
+            .into_iter()
+            .map(|(span, _)| span)
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                Span {
+                    start: Point::new(2, 4),
+                    end: Point::new(2, 5)
+                },
+                Span {
+                    start: Point::new(5, 4),
+                    end: Point::new(5, 5)
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn null_safety_smart_casts() {
+        let file: KotlinFile = "fun f(a: String?, b: String?, c: String?) {\n    if (a is String) {\n        a.length\n    }\n    b!!\n    b.length\n    c ?: return\n    c.length\n    var d: String? = null\n    d = \"d\"\n    d.length\n    var e: String? = null\n    e = null\n    e.length\n}\n"
+            .parse()
+            .unwrap();
+
+        let lines: Vec<usize> = file
+            .null_safety_warnings()
+            .into_iter()
+            .map(|(span, _)| span.start.row)
+            .collect();
+        // only `e` is never smart cast
+        assert_eq!(lines, vec![13]);
+    }
+
+    #[test]
+    fn find_by_name() {
+        let file: KotlinFile = "class A {\n    class B {\n        fun f() {}\n    }\n    companion object {\n        val p = 1\n        fun f(x: Int) {}\n    }\n}\nobject O {\n    class C\n}\nfun f() {}\nval p = 2\n"
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            file.find_class_by_name("B")[0].name_span.start,
+            Point::new(1, 10)
+        );
+        assert_eq!(file.find_class_by_name("C")[0].name, "C");
+        assert!(file.find_class_by_name("O").is_empty());
+
+        let functions: Vec<usize> = file
+            .find_function_by_name("f")
+            .iter()
+            .map(|function| function.span.start.row)
+            .collect();
+        assert_eq!(functions, vec![2, 6, 12]);
+        assert!(file.find_function_by_name("g").is_empty());
+
+        assert_eq!(file.find_property_by_name("p").len(), 2);
+    }
+
+    #[test]
+    fn find_same_named_classes() {
+        let file: KotlinFile = "class A {\n    class Builder {\n        fun a() {}\n    }\n}\nclass B {\n    class Builder {\n        fun b() {}\n    }\n}\n"
+            .parse()
+            .unwrap();
+
+        let functions: Vec<&str> = file
+            .find_class_by_name("Builder")
+            .iter()
+            .flat_map(|class| class.body.as_ref().unwrap().functions())
+            .map(|function| function.name.as_str())
+            .collect();
+        assert_eq!(functions, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn from_content() {
+        let file = KotlinFile::from_content(b"package a.b\n\nfun f() {}\n").unwrap();
+
+        assert_eq!(file.package.name(), "a.b");
+        assert_eq!(file.functions[0].name, "f");
+    }
+
+    #[test]
+    fn parallel_map_keeps_order() {
+        let contents: Vec<String> = (0..100).map(|i| format!("fun f{i}() {{}}\n")).collect();
+
+        let names = super::parallel_map(&contents, |parser, content| {
+            let tree = parser.parse(content, None).unwrap();
+            KotlinFile::new(&tree, content.as_bytes())
+                .unwrap()
+                .functions[0]
+                .name
+                .clone()
+        })
+        .unwrap();
+        assert_eq!(
+            names,
+            (0..100).map(|i| format!("f{i}")).collect::<Vec<String>>()
+        );
+    }
 }
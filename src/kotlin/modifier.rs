@@ -1,4 +1,4 @@
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use tree_sitter::Node;
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
@@ -6,9 +6,48 @@ pub enum Modifier {
     Class(String),
     Visibility(String),
     Annotation(String),
+    // `@get:JvmName("foo")`, the annotation is stored without the `@` and its target
+    AnnotationWithTarget {
+        target: AnnotationTarget,
+        annotation: String,
+    },
     Inheritance(String),
     Member(String),
     Property(String),
+    // e.g. `inline` on a property with accessors
+    Function(String),
+    // `expect` or `actual` in multiplatform code
+    Platform(String),
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub enum AnnotationTarget {
+    Get,
+    Set,
+    Field,
+    Param,
+    Receiver,
+    File,
+    Delegate,
+    Property,
+    SetParam,
+}
+
+impl AnnotationTarget {
+    fn new(target: &str) -> Option<AnnotationTarget> {
+        match target {
+            "get" => Some(AnnotationTarget::Get),
+            "set" => Some(AnnotationTarget::Set),
+            "field" => Some(AnnotationTarget::Field),
+            "param" => Some(AnnotationTarget::Param),
+            "receiver" => Some(AnnotationTarget::Receiver),
+            "file" => Some(AnnotationTarget::File),
+            "delegate" => Some(AnnotationTarget::Delegate),
+            "property" => Some(AnnotationTarget::Property),
+            "setparam" => Some(AnnotationTarget::SetParam),
+            _ => None,
+        }
+    }
 }
 
 impl Modifier {
@@ -16,12 +55,18 @@ impl Modifier {
         match node.kind() {
             "visibility_modifier" => Ok(Modifier::Visibility(node.utf8_text(content)?.to_string())),
             "class_modifier" => Ok(Modifier::Class(node.utf8_text(content)?.to_string())),
-            "annotation" => Ok(Modifier::Annotation(node.utf8_text(content)?.to_string())),
+            "annotation" => annotation(node, content),
+            "file_annotation" => Ok(Modifier::AnnotationWithTarget {
+                target: AnnotationTarget::File,
+                annotation: annotated(node, content)?,
+            }),
             "inheritance_modifier" => {
                 Ok(Modifier::Inheritance(node.utf8_text(content)?.to_string()))
             }
             "member_modifier" => Ok(Modifier::Member(node.utf8_text(content)?.to_string())),
             "property_modifier" => Ok(Modifier::Property(node.utf8_text(content)?.to_string())),
+            "function_modifier" => Ok(Modifier::Function(node.utf8_text(content)?.to_string())),
+            "platform_modifier" => Ok(Modifier::Platform(node.utf8_text(content)?.to_string())),
             _ => bail!(
                 "[Modifier] unknown modifier {} at {}",
                 node.kind(),
@@ -30,3 +75,92 @@ impl Modifier {
         }
     }
 }
+
+fn annotation(node: &Node, content: &[u8]) -> Result<Modifier> {
+    let Some(target) = node
+        .child(1)
+        .filter(|child| child.kind() == "use_site_target")
+    else {
+        return Ok(Modifier::Annotation(node.utf8_text(content)?.to_string()));
+    };
+
+    let name = target
+        .child(0)
+        .context(format!(
+            "[Modifier] no target at {}",
+            target.start_position()
+        ))?
+        .utf8_text(content)?;
+    let target = AnnotationTarget::new(name).context(format!(
+        "[Modifier] unknown annotation target '{name}' at {}",
+        node.start_position()
+    ))?;
+
+    Ok(Modifier::AnnotationWithTarget {
+        target,
+        annotation: annotated(node, content)?,
+    })
+}
+
+// the text after the target's `:`
+fn annotated(node: &Node, content: &[u8]) -> Result<String> {
+    let annotated = node
+        .child(node.child_count() - 1)
+        .filter(|child| child.kind() != ":" && child.kind() != "use_site_target")
+        .context(format!(
+            "[Modifier] no annotation at {}",
+            node.start_position()
+        ))?;
+
+    Ok(annotated.utf8_text(content)?.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kotlin::test_utils::{find_node, parse};
+
+    use super::{AnnotationTarget, Modifier};
+
+    #[test]
+    fn annotation_targets() {
+        let content =
+            "class A {\n    @get:JvmName(\"foo\") @field:Transient @Deprecated(\"x\") val y = 1\n}";
+        let tree = parse(content);
+        let node = find_node(&tree, "modifiers");
+
+        let mut cursor = node.walk();
+        let modifiers: Vec<Modifier> = node
+            .children(&mut cursor)
+            .map(|child| Modifier::new(&child, content.as_bytes()).unwrap())
+            .collect();
+        assert_eq!(
+            modifiers,
+            vec![
+                Modifier::AnnotationWithTarget {
+                    target: AnnotationTarget::Get,
+                    annotation: "JvmName(\"foo\")".to_string(),
+                },
+                Modifier::AnnotationWithTarget {
+                    target: AnnotationTarget::Field,
+                    annotation: "Transient".to_string(),
+                },
+                Modifier::Annotation("@Deprecated(\"x\")".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn file_annotation() {
+        let content = "@file:JvmMultifileClass\npackage a";
+        let tree = parse(content);
+        let node = find_node(&tree, "file_annotation");
+
+        assert_eq!(
+            Modifier::new(&node, content.as_bytes()).unwrap(),
+            Modifier::AnnotationWithTarget {
+                target: AnnotationTarget::File,
+                annotation: "JvmMultifileClass".to_string(),
+            }
+        );
+    }
+}
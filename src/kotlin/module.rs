@@ -0,0 +1,153 @@
+use std::{
+    collections::{BTreeSet, HashMap, VecDeque},
+    path::{Path, PathBuf},
+};
+
+use super::KotlinFile;
+
+// packages of the workspace, the files declaring them and the packages their imports point to
+#[derive(Debug, Default)]
+pub struct ModuleGraph {
+    files: HashMap<String, Vec<PathBuf>>,
+    dependencies: HashMap<String, BTreeSet<String>>,
+}
+
+impl ModuleGraph {
+    pub fn new<'a>(files: impl IntoIterator<Item = (&'a Path, &'a KotlinFile)>) -> ModuleGraph {
+        let files: Vec<(&Path, &KotlinFile)> = files.into_iter().collect();
+
+        let mut graph = ModuleGraph::default();
+        for (path, file) in &files {
+            graph
+                .files
+                .entry(file.package.name().to_string())
+                .or_default()
+                .push(path.to_path_buf());
+        }
+
+        for (_, file) in &files {
+            let package = file.package.name();
+            let dependencies: BTreeSet<String> = file
+                .imports
+                .iter()
+                .map(|import| graph.imported_package(&import.path))
+                .filter(|dependency| dependency != package)
+                .collect();
+            graph
+                .dependencies
+                .entry(package.to_string())
+                .or_default()
+                .extend(dependencies);
+        }
+
+        graph
+    }
+
+    // the package of a star import, otherwise the longest known package the path starts
+    // with, for unknown packages the path without its last segment
+    fn imported_package(&self, path: &str) -> String {
+        if let Some(package) = path.strip_suffix(".*") {
+            return package.to_string();
+        }
+
+        let mut prefix = path;
+        while let Some((package, _)) = prefix.rsplit_once('.') {
+            if self.files.contains_key(package) {
+                return package.to_string();
+            }
+            prefix = package;
+        }
+
+        path.rsplit_once('.')
+            .map_or(path, |(package, _)| package)
+            .to_string()
+    }
+
+    pub fn files(&self, package: &str) -> &[PathBuf] {
+        self.files.get(package).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn direct_dependencies(&self, package: &str) -> Vec<&str> {
+        self.dependencies
+            .get(package)
+            .map(|dependencies| dependencies.iter().map(String::as_str).collect())
+            .unwrap_or_default()
+    }
+
+    // shortest chain of packages from the imported one back to the importing one, e.g.
+    // `["b", "a"]` when `a` imports `b` and `b` imports `a`
+    pub fn import_cycle(&self, package: &str, import_path: &str) -> Option<Vec<&str>> {
+        let (start, _) = self
+            .dependencies
+            .get_key_value(&self.imported_package(import_path))?;
+        if start == package {
+            return None;
+        }
+
+        let mut previous: HashMap<&str, &str> = HashMap::new();
+        let mut pending = VecDeque::from([start.as_str()]);
+        while let Some(current) = pending.pop_front() {
+            if current == package {
+                let mut cycle = vec![current];
+                while let Some(before) = previous.get(cycle[cycle.len() - 1]) {
+                    cycle.push(before);
+                }
+                cycle.reverse();
+                return Some(cycle);
+            }
+
+            for dependency in self.dependencies.get(current).into_iter().flatten() {
+                if dependency != start && !previous.contains_key(dependency.as_str()) {
+                    previous.insert(dependency, current);
+                    pending.push_back(dependency);
+                }
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::kotlin::KotlinFile;
+
+    use super::ModuleGraph;
+
+    #[test]
+    fn direct_dependencies() {
+        let a: KotlinFile = "package foo.a\n\nimport foo.b.Bar.Inner\nimport foo.a.Other\nimport kotlinx.coroutines.*\n"
+            .parse()
+            .unwrap();
+        let b: KotlinFile = "package foo.b\n\nclass Bar {\n}\n".parse().unwrap();
+
+        let graph = ModuleGraph::new([(Path::new("a.kt"), &a), (Path::new("b.kt"), &b)]);
+        assert_eq!(
+            graph.direct_dependencies("foo.a"),
+            vec!["foo.b", "kotlinx.coroutines"]
+        );
+        assert!(graph.direct_dependencies("foo.b").is_empty());
+        assert_eq!(graph.files("foo.b"), [Path::new("b.kt").to_path_buf()]);
+    }
+
+    #[test]
+    fn import_cycles() {
+        let a: KotlinFile = "package a\n\nimport b.B\nimport d.*\n".parse().unwrap();
+        let b: KotlinFile = "package b\n\nimport c.C\n".parse().unwrap();
+        let c: KotlinFile = "package c\n\nimport a.A\n".parse().unwrap();
+        let d: KotlinFile = "package d\n\nclass D\n".parse().unwrap();
+
+        let graph = ModuleGraph::new([
+            (Path::new("a.kt"), &a),
+            (Path::new("b.kt"), &b),
+            (Path::new("c.kt"), &c),
+            (Path::new("d.kt"), &d),
+        ]);
+        assert_eq!(graph.import_cycle("a", "b.B"), Some(vec!["b", "c", "a"]));
+        assert_eq!(graph.import_cycle("c", "a.A"), Some(vec!["a", "b", "c"]));
+        assert_eq!(graph.import_cycle("a", "d.*"), None);
+        assert_eq!(graph.import_cycle("a", "kotlin.math.max"), None);
+    }
+}
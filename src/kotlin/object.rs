@@ -1,14 +1,25 @@
 use anyhow::{bail, Context, Result};
-use tree_sitter::Node;
+use tree_sitter::{Node, Tree};
 
-use super::{class::ClassBody, delegation::Delegation, modifier::Modifier};
+use super::{
+    class::ClassBody,
+    declaration::Declaration,
+    delegation::Delegation,
+    expression::Expression,
+    kdoc::{self, KDoc},
+    modifier::Modifier,
+    span::Span,
+};
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub struct Object {
     pub modifiers: Vec<Modifier>,
     pub name: String,
+    pub name_span: Span,
     pub delegations: Vec<Delegation>,
     pub class_body: Option<ClassBody>,
+    pub span: Span,
+    pub kdoc: Option<KDoc>,
 }
 
 impl Object {
@@ -20,13 +31,15 @@ impl Object {
         let mut cursor = node.walk();
         for child in node.children(&mut cursor.clone()) {
             match child.kind() {
-                "object" | ":" => {}
+                "object" | ":" | "line_comment" | "multiline_comment" => {}
                 "modifiers" => {
                     for child in child.children(&mut cursor) {
                         modifiers.push(Modifier::new(&child, content)?);
                     }
                 }
-                "type_identifier" => name = Some(child.utf8_text(content)?.to_string()),
+                "type_identifier" => {
+                    name = Some((child.utf8_text(content)?.to_string(), Span::new(&child)))
+                }
                 "delegation_specifier" => delegations.push(Delegation::new(&child, content)?),
                 "class_body" => class_body = Some(ClassBody::new_class_body(&child, content)?),
                 _ => {
@@ -40,11 +53,44 @@ impl Object {
             }
         }
 
+        let (name, name_span) = name.context("no name found")?;
         Ok(Object {
             modifiers,
-            name: name.context("no name found")?,
+            name,
+            name_span,
             delegations,
             class_body,
+            span: Span::new(node),
+            kdoc: kdoc::get_kdoc(node, content)?,
         })
     }
+
+    pub fn walk_declarations<'a>(&'a self, f: &mut impl FnMut(Declaration<'a>)) {
+        f(Declaration::Object(self));
+        if let Some(class_body) = &self.class_body {
+            class_body.walk_declarations(f);
+        }
+    }
+
+    pub fn walk_expressions<'a>(&'a self, f: &mut impl FnMut(&'a Expression)) {
+        for delegation in &self.delegations {
+            delegation.walk_expressions(f);
+        }
+        if let Some(class_body) = &self.class_body {
+            class_body.walk_expressions(f);
+        }
+    }
+}
+
+pub fn get_top_level_objects(tree: &Tree, content: &[u8]) -> Result<Vec<Object>> {
+    let mut objects = Vec::new();
+    let root = tree.root_node();
+    let mut cursor = root.walk();
+    for child in root.children(&mut cursor) {
+        if child.kind() == "object_declaration" {
+            objects.push(Object::new(&child, content)?);
+        }
+    }
+
+    Ok(objects)
 }
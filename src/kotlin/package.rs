@@ -1,8 +1,22 @@
 use anyhow::Result;
 use tree_sitter::Tree;
 
-#[derive(Debug, Hash, PartialEq, Eq)]
-pub struct Package(String);
+use super::span::Span;
+
+// name and span of the package header, if there is one
+#[derive(Debug, Hash, PartialEq, Eq, Clone)]
+pub struct Package(String, Option<Span>);
+
+impl Package {
+    // empty for files without a package header
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+
+    pub fn span(&self) -> Option<Span> {
+        self.1
+    }
+}
 
 pub fn get_package(tree: &Tree, content: &[u8]) -> Result<Package> {
     let mut cursor = tree.walk();
@@ -15,7 +29,8 @@ pub fn get_package(tree: &Tree, content: &[u8]) -> Result<Package> {
                 "".to_string()
             };
 
-            return Ok(Package(package));
+            let span = node.parent().map(|header| Span::new(&header));
+            return Ok(Package(package, span));
         }
 
         if cursor.goto_first_child() {
@@ -28,7 +43,7 @@ pub fn get_package(tree: &Tree, content: &[u8]) -> Result<Package> {
             }
 
             if !cursor.goto_parent() {
-                return Ok(Package("".to_string()));
+                return Ok(Package("".to_string(), None));
             }
         }
     }
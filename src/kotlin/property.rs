@@ -1,14 +1,18 @@
 use anyhow::{bail, Context, Result};
-use tree_sitter::Node;
+use tree_sitter::{Node, Tree};
 
 use crate::kotlin::{
     expression::{Expression, EXPRESSIONS},
     getter::{Getter, Setter},
+    literal::Literal,
     types::Type,
 };
 
 use super::{
+    declaration::Declaration,
+    kdoc::{self, KDoc},
     modifier::Modifier,
+    span::Span,
     variable_declaration::{MultiVariableDeclaration, VariableDeclaration},
 };
 
@@ -18,6 +22,33 @@ pub enum PropertyMutability {
     Val,
 }
 
+// property specific keywords, they are still stored as plain modifiers
+#[derive(Debug, Hash, PartialEq, Eq, Clone)]
+pub enum PropertyModifier {
+    Override,
+    Lateinit,
+    Const,
+}
+
+impl PropertyModifier {
+    fn new(modifier: &Modifier) -> Option<PropertyModifier> {
+        match modifier {
+            Modifier::Member(text) if text == "override" => Some(PropertyModifier::Override),
+            Modifier::Member(text) if text == "lateinit" => Some(PropertyModifier::Lateinit),
+            Modifier::Property(text) if text == "const" => Some(PropertyModifier::Const),
+            _ => None,
+        }
+    }
+
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            PropertyModifier::Override => "override",
+            PropertyModifier::Lateinit => "lateinit",
+            PropertyModifier::Const => "const",
+        }
+    }
+}
+
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub struct PropertyDelegate {
     expression: Expression,
@@ -42,6 +73,7 @@ pub enum PropertyVariableDeclaration {
     Multi(MultiVariableDeclaration),
 }
 
+/// A `val` or `var` declaration, possibly destructuring several variables.
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub struct Property {
     pub modifiers: Vec<Modifier>,
@@ -52,6 +84,8 @@ pub struct Property {
     pub delegate: Option<PropertyDelegate>,
     pub getter: Option<Getter>,
     pub setter: Option<Setter>,
+    pub span: Span,
+    pub kdoc: Option<KDoc>,
 }
 
 impl Property {
@@ -105,12 +139,15 @@ impl Property {
         }
 
         // getter and setter can be both inside of property_declaration and outside!
-        if let Some(next) = node.next_sibling() {
-            match next.kind() {
-                "getter" => getter = Some(Getter::new(&next, content)?),
-                "setter" => setter = Some(Setter::new(&next, content)?),
-                _ => {}
+        // outside they follow the declaration, in either order
+        let mut next = node.next_sibling();
+        while let Some(sibling) = next {
+            match sibling.kind() {
+                "getter" => getter = Some(Getter::new(&sibling, content)?),
+                "setter" => setter = Some(Setter::new(&sibling, content)?),
+                _ => break,
             }
+            next = sibling.next_sibling();
         }
 
         Ok(Property {
@@ -123,6 +160,177 @@ impl Property {
             getter,
             setter,
             delegate,
+            span: Span::new(node),
+            kdoc: kdoc::get_kdoc(node, content)?,
         })
     }
+
+    pub fn property_modifiers(&self) -> Vec<PropertyModifier> {
+        self.modifiers
+            .iter()
+            .filter_map(PropertyModifier::new)
+            .collect()
+    }
+
+    pub fn is_override(&self) -> bool {
+        self.property_modifiers()
+            .contains(&PropertyModifier::Override)
+    }
+
+    pub fn is_lateinit(&self) -> bool {
+        self.property_modifiers()
+            .contains(&PropertyModifier::Lateinit)
+    }
+
+    pub fn is_const(&self) -> bool {
+        self.property_modifiers().contains(&PropertyModifier::Const)
+    }
+
+    // type of a single variable without type annotation, only for initializers whose type
+    // is obvious, `return_type` looks up the return type of a called function by name
+    pub fn inferred_type<'b>(
+        &self,
+        return_type: impl Fn(&str) -> Option<&'b str>,
+    ) -> Option<String> {
+        let PropertyVariableDeclaration::Single(declaration) = &self.variable_declaration else {
+            return None;
+        };
+        if declaration.data_type.is_some() || self.delegate.is_some() {
+            return None;
+        }
+
+        let inferred = match self.expression.as_ref()? {
+            Expression::Literal(literal) => match literal {
+                Literal::Integer(_) | Literal::Hex(_) => "Int",
+                Literal::Long(_) => "Long",
+                Literal::Boolean(_) => "Boolean",
+                Literal::String(_) | Literal::StringTemplate(_) => "String",
+                Literal::Character(_) => "Char",
+                Literal::Null => "Nothing?",
+                _ => return None,
+            },
+            expression => return_type(expression.call()?.0)?,
+        };
+
+        Some(inferred.to_string())
+    }
+
+    // a destructuring declaration introduces several names
+    pub fn variable_declarations(&self) -> Vec<&VariableDeclaration> {
+        match &self.variable_declaration {
+            PropertyVariableDeclaration::Single(declaration) => vec![declaration],
+            PropertyVariableDeclaration::Multi(declaration) => {
+                declaration.variable_declarations.iter().collect()
+            }
+        }
+    }
+
+    pub fn walk_declarations<'a>(&'a self, f: &mut impl FnMut(Declaration<'a>)) {
+        for declaration in self.variable_declarations() {
+            f(Declaration::Property(self, declaration));
+        }
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.variable_declarations()
+            .into_iter()
+            .map(|d| d.identifier.as_str())
+            .collect()
+    }
+
+    pub fn walk_expressions<'a>(&'a self, f: &mut impl FnMut(&'a Expression)) {
+        if let Some(expression) = &self.expression {
+            expression.walk_expressions(f);
+        }
+        if let Some(delegate) = &self.delegate {
+            delegate.expression.walk_expressions(f);
+        }
+        if let Some(getter) = &self.getter {
+            getter.walk_expressions(f);
+        }
+        if let Some(setter) = &self.setter {
+            setter.walk_expressions(f);
+        }
+    }
+}
+
+pub fn get_top_level_properties(tree: &Tree, content: &[u8]) -> Result<Vec<Property>> {
+    let mut properties = Vec::new();
+    let root = tree.root_node();
+    let mut cursor = root.walk();
+    for child in root.children(&mut cursor) {
+        if child.kind() == "property_declaration" {
+            properties.push(Property::new(&child, content)?);
+        }
+    }
+
+    Ok(properties)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kotlin::test_utils::{find_node, parse};
+
+    use super::{Property, PropertyModifier};
+
+    #[test]
+    fn lateinit_property() {
+        let content = "class A {\n    private lateinit var foo: String\n}";
+        let tree = parse(content);
+        let node = find_node(&tree, "property_declaration");
+
+        let property = Property::new(&node, content.as_bytes()).unwrap();
+        assert_eq!(
+            property.property_modifiers(),
+            vec![PropertyModifier::Lateinit]
+        );
+        assert!(property.is_lateinit());
+    }
+
+    #[test]
+    fn override_property() {
+        let content = "class A : B {\n    override lateinit var foo: String\n}";
+        let tree = parse(content);
+        let node = find_node(&tree, "property_declaration");
+
+        let property = Property::new(&node, content.as_bytes()).unwrap();
+        assert!(property.is_override());
+        assert_eq!(
+            property.property_modifiers(),
+            vec![PropertyModifier::Override, PropertyModifier::Lateinit]
+        );
+    }
+
+    #[test]
+    fn const_property() {
+        let content = "object A {\n    const val FOO = 42\n}";
+        let tree = parse(content);
+        let node = find_node(&tree, "property_declaration");
+
+        let property = Property::new(&node, content.as_bytes()).unwrap();
+        assert!(property.is_const());
+        assert!(!property.is_lateinit());
+    }
+
+    #[test]
+    fn accessors_inside_declaration() {
+        let content = "class A {\n    val y: Int get() = 2\n}";
+        let tree = parse(content);
+        let node = find_node(&tree, "property_declaration");
+
+        let property = Property::new(&node, content.as_bytes()).unwrap();
+        assert!(property.getter.is_some());
+        assert!(property.setter.is_none());
+    }
+
+    #[test]
+    fn accessors_after_declaration() {
+        let content = "class A {\n    var x: Int = 1\n        get() = field\n        private set\n    val y = 2\n}";
+        let tree = parse(content);
+        let node = find_node(&tree, "property_declaration");
+
+        let property = Property::new(&node, content.as_bytes()).unwrap();
+        assert!(property.getter.is_some());
+        assert!(property.setter.is_some());
+    }
 }
@@ -0,0 +1,308 @@
+use std::ptr;
+
+use tree_sitter::{Node, Point, Tree};
+
+use super::{
+    declaration::Declaration,
+    expression::Expression,
+    scope::{scope_at, Scope},
+    span::Span,
+    KotlinFile,
+};
+
+/// What an occurrence of a name refers to.
+///
+/// Occurrences that resolve to the same symbol are the same thing, which is what rename
+/// goes by.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Symbol {
+    // a parameter or local by the span of its declaration, only valid within its file
+    Local(Span),
+    // a top level declaration by qualified name, just the name without a package
+    TopLevel(String),
+    // usage under an import alias, with the qualified name of the import
+    Alias(String),
+    // members and anything that isn't declared in the workspace go by name
+    Member(String),
+}
+
+// every occurrence of the name in the file and what it resolves to, in document order.
+// `declared` tells whether a qualified name is declared at the top level of the workspace
+pub fn occurrences(
+    file: &KotlinFile,
+    tree: &Tree,
+    content: &[u8],
+    name: &str,
+    declared: &dyn Fn(&str) -> bool,
+) -> Vec<(Span, Symbol)> {
+    let resolver = Resolver {
+        file,
+        name,
+        declared,
+    };
+    let mut found = Vec::new();
+
+    file.walk_declarations(&mut |declaration| {
+        if declaration.name() == name {
+            found.push((declaration.name_span(), resolver.declared(declaration)));
+        }
+        if let Declaration::Function(function) = declaration {
+            for (_, scope) in Scope::from_function(function) {
+                if let Some(entry) = scope.get(name) {
+                    found.push((entry.span, Symbol::Local(entry.span)));
+                }
+            }
+        }
+    });
+
+    // the receiver type after a `.` isn't known, so that is taken to be a member
+    file.walk_expressions(&mut |expression| match expression {
+        Expression::Identifier { identifier, span } if identifier == name => {
+            found.push((*span, resolver.resolve(span.start, true)));
+        }
+        Expression::Navigation {
+            navigation_suffix, ..
+        } if navigation_suffix.identifier == name => {
+            found.push((navigation_suffix.span, Symbol::Member(name.to_string())));
+        }
+        _ => {}
+    });
+
+    // type references and imports only have spans in the syntax tree
+    resolver.type_occurrences(&tree.root_node(), content, &mut found);
+
+    found.sort_by_key(|(span, _)| (span.start, span.end));
+    found.dedup_by(|(a, _), (b, _)| a == b);
+    found
+}
+
+struct Resolver<'a> {
+    file: &'a KotlinFile,
+    name: &'a str,
+    declared: &'a dyn Fn(&str) -> bool,
+}
+
+impl Resolver<'_> {
+    fn declared(&self, declaration: Declaration) -> Symbol {
+        let file = self.file;
+        let top_level = match declaration {
+            Declaration::Class(class) => file.classes.iter().any(|c| ptr::eq(c, class)),
+            Declaration::Object(object) => file.objects.iter().any(|o| ptr::eq(o, object)),
+            Declaration::Function(function) => file.functions.iter().any(|f| ptr::eq(f, function)),
+            Declaration::Property(property, _) => {
+                file.properties.iter().any(|p| ptr::eq(p, property))
+            }
+            Declaration::TypeAlias(alias) => file.type_aliases.iter().any(|a| ptr::eq(a, alias)),
+        };
+
+        if top_level {
+            Symbol::TopLevel(self.qualified(file.package.name()))
+        } else {
+            Symbol::Member(self.name.to_string())
+        }
+    }
+
+    // locals, then members of the classes around the point, then explicit imports, the
+    // package of the file and star imports, the same order kotlin resolves names in
+    fn resolve(&self, point: Point, locals: bool) -> Symbol {
+        let (file, name) = (self.file, self.name);
+        if locals {
+            if let Some(span) = self.local(point) {
+                return Symbol::Local(span);
+            }
+        }
+        if self.is_member(point) {
+            return Symbol::Member(name.to_string());
+        }
+
+        if let Some(import) = file.import_of(name) {
+            return match import.alias {
+                Some(_) => Symbol::Alias(import.path.clone()),
+                None => Symbol::TopLevel(import.path.clone()),
+            };
+        }
+        let same_package = self.qualified(file.package.name());
+        if (self.declared)(&same_package) {
+            return Symbol::TopLevel(same_package);
+        }
+        for import in file.imports.iter().filter(|import| import.is_wildcard()) {
+            let qualified = self.qualified(import.path.trim_end_matches(".*"));
+            if (self.declared)(&qualified) {
+                return Symbol::TopLevel(qualified);
+            }
+        }
+
+        Symbol::Member(name.to_string())
+    }
+
+    fn qualified(&self, package: &str) -> String {
+        match package {
+            "" => self.name.to_string(),
+            package => format!("{package}.{}", self.name),
+        }
+    }
+
+    fn local(&self, point: Point) -> Option<Span> {
+        let scopes = Scope::from_function(self.file.function_at(point)?);
+        scope_at(&scopes, point)?
+            .get(self.name)
+            .map(|entry| entry.span)
+    }
+
+    // declared anywhere inside a class or object around the point
+    fn is_member(&self, point: Point) -> bool {
+        let mut member = false;
+        self.file.walk_declarations(&mut |declaration| {
+            let mut check = |nested: Declaration| {
+                member |= nested != declaration && nested.name() == self.name;
+            };
+            match declaration {
+                Declaration::Class(class) if class.span.contains(point) => {
+                    class.walk_declarations(&mut check)
+                }
+                Declaration::Object(object) if object.span.contains(point) => {
+                    object.walk_declarations(&mut check)
+                }
+                _ => {}
+            }
+        });
+        member
+    }
+
+    fn type_occurrences(&self, node: &Node, content: &[u8], found: &mut Vec<(Span, Symbol)>) {
+        let is_name = |node: &Node| node.utf8_text(content).is_ok_and(|text| text == self.name);
+        match node.kind() {
+            "type_identifier" if is_name(node) => {
+                if matches!(
+                    node.parent().as_ref().map(Node::kind),
+                    Some("user_type" | "callable_reference")
+                ) {
+                    found.push((Span::new(node), self.resolve(node.start_position(), false)));
+                }
+            }
+            // the last segment of `import a.b.Name`, aliases keep pointing at the declaration
+            "import_header" => {
+                let import = self
+                    .file
+                    .imports
+                    .iter()
+                    .find(|import| import.span.start == node.start_position());
+                let last = node.named_child(0).and_then(|identifier| {
+                    identifier.named_child(identifier.named_child_count() - 1)
+                });
+                if let (Some(import), Some(last)) = (import, last) {
+                    if is_name(&last) && import.imported_name() == self.name {
+                        found.push((Span::new(&last), Symbol::TopLevel(import.path.clone())));
+                    }
+                }
+            }
+            _ => {
+                let mut cursor = node.walk();
+                for child in node.children(&mut cursor) {
+                    self.type_occurrences(&child, content, found);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kotlin::{span::Span, test_utils::parse, KotlinFile};
+
+    use super::{occurrences, Symbol};
+
+    fn resolved(content: &str, name: &str, declared: &[&str]) -> Vec<(usize, usize, Symbol)> {
+        let file: KotlinFile = content.parse().unwrap();
+        let tree = parse(content);
+
+        occurrences(&file, &tree, content.as_bytes(), name, &|qualified| {
+            declared.contains(&qualified)
+        })
+        .into_iter()
+        .map(|(span, symbol)| (span.start.row, span.start.column, symbol))
+        .collect()
+    }
+
+    #[test]
+    fn locals_shadow_top_level() {
+        let content = "package a\n\nval x = 1\nfun f(x: Int) = x\nfun g() {\n    val x = 2\n    print(x)\n}\nfun h() = x\n";
+        let local = |row, column| {
+            Symbol::Local(Span {
+                start: tree_sitter::Point::new(row, column),
+                end: tree_sitter::Point::new(row, column + 1),
+            })
+        };
+
+        assert_eq!(
+            resolved(content, "x", &["a.x"]),
+            vec![
+                (2, 4, Symbol::TopLevel("a.x".to_string())),
+                (3, 6, local(3, 6)),
+                (3, 16, local(3, 6)),
+                (5, 8, local(5, 8)),
+                (6, 10, local(5, 8)),
+                (8, 10, Symbol::TopLevel("a.x".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn type_references() {
+        let content = "package a\n\nimport b.Foo\n\n@Foo\nclass A : Foo, List<Foo> {\n    fun f(): Foo = Foo()\n    val k = Foo::class\n}\n";
+        let symbols: Vec<(usize, usize)> = resolved(content, "Foo", &["b.Foo"])
+            .into_iter()
+            .map(|(row, column, symbol)| {
+                assert_eq!(symbol, Symbol::TopLevel("b.Foo".to_string()));
+                (row, column)
+            })
+            .collect();
+
+        assert_eq!(
+            symbols,
+            vec![(2, 9), (4, 1), (5, 10), (5, 20), (6, 13), (6, 19), (7, 12)]
+        );
+    }
+
+    #[test]
+    fn imports_before_package_before_star_imports() {
+        let content = "package a\n\nimport c.*\nimport d.Bar as Baz\n\nval x: Foo = Qux()\nval y: Baz = Bar()\n";
+
+        assert_eq!(
+            resolved(content, "Foo", &["a.Foo", "c.Foo"]),
+            vec![(5, 7, Symbol::TopLevel("a.Foo".to_string()))]
+        );
+        assert_eq!(
+            resolved(content, "Qux", &["c.Qux"]),
+            vec![(5, 13, Symbol::TopLevel("c.Qux".to_string()))]
+        );
+        assert_eq!(
+            resolved(content, "Baz", &[]),
+            vec![(6, 7, Symbol::Alias("d.Bar".to_string()))]
+        );
+        // not the import, which is visible as `Baz`, and not declared anywhere
+        assert_eq!(
+            resolved(content, "Bar", &[]),
+            vec![
+                (3, 9, Symbol::TopLevel("d.Bar".to_string())),
+                (6, 13, Symbol::Member("Bar".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn members_shadow_top_level() {
+        let content = "package a\n\nfun size() = 0\nclass A {\n    fun size() = 1\n    fun f() = size()\n}\nfun g() = size()\nfun h(a: A) = a.size()\n";
+
+        assert_eq!(
+            resolved(content, "size", &["a.size"]),
+            vec![
+                (2, 4, Symbol::TopLevel("a.size".to_string())),
+                (4, 8, Symbol::Member("size".to_string())),
+                (5, 14, Symbol::Member("size".to_string())),
+                (7, 10, Symbol::TopLevel("a.size".to_string())),
+                (8, 16, Symbol::Member("size".to_string())),
+            ]
+        );
+    }
+}
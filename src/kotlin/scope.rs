@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+
+use tree_sitter::Point;
+
+use super::{
+    expression::{ControlStructureBody, Expression},
+    function::{Function, FunctionBody},
+    span::Span,
+    statement::{ForParameter, Statement},
+    types::Type,
+};
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ScopeEntry {
+    pub data_type: Option<Type>,
+    // span of the declared name
+    pub span: Span,
+}
+
+/// Names bound at some point of a function body, parameters and locals declared so far.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct Scope {
+    pub entries: HashMap<String, ScopeEntry>,
+}
+
+impl Scope {
+    // every scope of a function together with the region it applies to, a local is bound
+    // from the end of its declaration to the end of its block and shadows outer names
+    pub fn from_function(function: &Function) -> Vec<(Span, Scope)> {
+        let mut scope = Scope::default();
+        for parameter in &function.parameters {
+            scope.insert(
+                &parameter.name,
+                Some(parameter.type_identifier.clone()),
+                parameter.span,
+            );
+        }
+
+        let mut scopes = vec![(function.span, scope.clone())];
+        if let Some(FunctionBody::Block(statements)) = &function.body {
+            block_scopes(statements, function.span.end, scope, &mut scopes);
+        }
+
+        scopes
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ScopeEntry> {
+        self.entries.get(name)
+    }
+
+    fn insert(&mut self, name: &str, data_type: Option<Type>, span: Span) {
+        self.entries
+            .insert(name.to_string(), ScopeEntry { data_type, span });
+    }
+}
+
+// the innermost scope at a point, nested regions always start after the ones around them
+pub fn scope_at(scopes: &[(Span, Scope)], point: Point) -> Option<&Scope> {
+    scopes
+        .iter()
+        .filter(|(span, _)| span.contains(point))
+        .max_by_key(|(span, _)| span.start)
+        .map(|(_, scope)| scope)
+}
+
+fn block_scopes(
+    statements: &[Statement],
+    end: Point,
+    mut scope: Scope,
+    scopes: &mut Vec<(Span, Scope)>,
+) {
+    for statement in statements {
+        match statement {
+            Statement::PropertyDeclaration(property) => {
+                for declaration in property.variable_declarations() {
+                    scope.insert(
+                        &declaration.identifier,
+                        declaration.data_type.clone(),
+                        declaration.identifier_span,
+                    );
+                }
+                let region = Span {
+                    start: property.span.end,
+                    end,
+                };
+                scopes.push((region, scope.clone()));
+            }
+            Statement::Expression(Expression::If { body, .. })
+            | Statement::While(_, Some(body))
+            | Statement::DoWhile(Some(body), _) => child_scopes(body, scope.clone(), scopes),
+            Statement::For(_, parameter, Some(body)) => {
+                let mut child = scope.clone();
+                let declarations = match parameter {
+                    ForParameter::VariableDeclaration(declaration) => vec![declaration],
+                    ForParameter::MultiVariableDeclaration(declaration) => {
+                        declaration.variable_declarations.iter().collect()
+                    }
+                };
+                for declaration in declarations {
+                    child.insert(
+                        &declaration.identifier,
+                        declaration.data_type.clone(),
+                        declaration.identifier_span,
+                    );
+                }
+                child_scopes(body, child, scopes);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn child_scopes(body: &ControlStructureBody, scope: Scope, scopes: &mut Vec<(Span, Scope)>) {
+    scopes.push((body.span, scope.clone()));
+    block_scopes(&body.statements, body.span.end, scope, scopes);
+}
+
+#[cfg(test)]
+mod tests {
+    use tree_sitter::Point;
+
+    use crate::kotlin::{
+        function::Function,
+        test_utils::{find_node, parse},
+    };
+
+    use super::{scope_at, Scope};
+
+    #[test]
+    fn nested_scopes() {
+        let content = "fun f(a: Int) {\n    val b = 1\n    if (a > b) {\n        val c: String = \"\"\n    }\n    for (i in 0..a) {\n    }\n}";
+        let tree = parse(content);
+        let node = find_node(&tree, "function_declaration");
+        let function = Function::new(&node, content.as_bytes()).unwrap();
+        let scopes = Scope::from_function(&function);
+
+        let names = |row, column| {
+            let mut names: Vec<String> = scope_at(&scopes, Point::new(row, column))
+                .unwrap()
+                .entries
+                .keys()
+                .cloned()
+                .collect();
+            names.sort();
+            names
+        };
+
+        assert_eq!(names(1, 4), vec!["a"]);
+        assert_eq!(names(3, 30), vec!["a", "b", "c"]);
+        assert_eq!(names(5, 21), vec!["a", "b", "i"]);
+        assert_eq!(names(7, 0), vec!["a", "b"]);
+    }
+}
@@ -0,0 +1,20 @@
+use tree_sitter::{Node, Point};
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub struct Span {
+    pub start: Point,
+    pub end: Point,
+}
+
+impl Span {
+    pub fn new(node: &Node) -> Span {
+        Span {
+            start: node.start_position(),
+            end: node.end_position(),
+        }
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        self.start <= point && point < self.end
+    }
+}
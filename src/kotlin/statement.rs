@@ -4,8 +4,11 @@ use tree_sitter::Node;
 
 use super::{
     assignment::Assignment,
+    class::Class,
     expression::{ControlStructureBody, Expression, EXPRESSIONS},
     function::Function,
+    object::Object,
+    type_alias::TypeAlias,
     variable_declaration::{MultiVariableDeclaration, VariableDeclaration},
 };
 
@@ -15,14 +18,48 @@ pub enum ForParameter {
     MultiVariableDeclaration(MultiVariableDeclaration),
 }
 
+/// A statement inside a block or function body.
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub enum Statement {
-    PropertyDeclaration(Property),
+    PropertyDeclaration(Box<Property>),
     Expression(Expression),
     Assignment(Assignment),
     Function(Function),
+    Class(Box<Class>),
+    Object(Box<Object>),
+    TypeAlias(TypeAlias),
     While(Expression, Option<ControlStructureBody>),
     For(Expression, ForParameter, Option<ControlStructureBody>),
+    // the condition is evaluated after the body, `do while (x)` has no body at all
+    DoWhile(Option<ControlStructureBody>, Expression),
+    // kept so comments can be shown together with the statement that follows them
+    Comment(String),
+}
+
+impl Statement {
+    pub fn walk_expressions<'a>(&'a self, f: &mut impl FnMut(&'a Expression)) {
+        match self {
+            Statement::PropertyDeclaration(property) => property.walk_expressions(f),
+            Statement::Expression(expression) => expression.walk_expressions(f),
+            Statement::Assignment(assignment) => assignment.walk_expressions(f),
+            Statement::Function(function) => function.walk_expressions(f),
+            Statement::Class(class) => class.walk_expressions(f),
+            Statement::Object(object) => object.walk_expressions(f),
+            Statement::While(expression, body) | Statement::For(expression, _, body) => {
+                expression.walk_expressions(f);
+                if let Some(body) = body {
+                    body.walk_expressions(f);
+                }
+            }
+            Statement::DoWhile(body, condition) => {
+                if let Some(body) = body {
+                    body.walk_expressions(f);
+                }
+                condition.walk_expressions(f);
+            }
+            Statement::TypeAlias(_) | Statement::Comment(_) => {}
+        }
+    }
 }
 
 pub fn get_statements(node: &Node, content: &[u8]) -> Result<Vec<Statement>> {
@@ -30,18 +67,30 @@ pub fn get_statements(node: &Node, content: &[u8]) -> Result<Vec<Statement>> {
     let mut cursor = node.walk();
     for child in node.children(&mut cursor) {
         match child.kind() {
-            "line_comment" => {}
-            "property_declaration" => statements.push(Statement::PropertyDeclaration(
+            "line_comment" | "multiline_comment" => {
+                statements.push(Statement::Comment(child.utf8_text(content)?.to_string()))
+            }
+            "property_declaration" => statements.push(Statement::PropertyDeclaration(Box::new(
                 Property::new(&child, content)?,
-            )),
+            ))),
             "function_declaration" => {
                 statements.push(Statement::Function(Function::new(&child, content)?))
             }
+            "class_declaration" => {
+                statements.push(Statement::Class(Box::new(Class::new(&child, content)?)))
+            }
+            "object_declaration" => {
+                statements.push(Statement::Object(Box::new(Object::new(&child, content)?)))
+            }
+            "type_alias" => statements.push(Statement::TypeAlias(TypeAlias::new(&child, content)?)),
             "assignment" => {
                 statements.push(Statement::Assignment(Assignment::new(&child, content)?))
             }
             "while_statement" => statements.push(while_statement(&child, content)?),
             "for_statement" => statements.push(for_statement(&child, content)?),
+            "do_while_statement" => statements.push(do_while_statement(&child, content)?),
+            // `loop@` before a loop, a sibling of the loop it labels
+            "label" => {}
             kind => {
                 if EXPRESSIONS.contains(&kind) {
                     statements.push(Statement::Expression(Expression::new(&child, content)?))
@@ -161,3 +210,139 @@ fn for_statement(node: &Node, content: &[u8]) -> Result<Statement> {
         bail!("[Statement::For] no child at {}", node.start_position());
     }
 }
+
+fn do_while_statement(node: &Node, content: &[u8]) -> Result<Statement> {
+    let mut body = None;
+    let mut condition = None;
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        match child.kind() {
+            "do" | "while" | "(" | ")" | "line_comment" | "multiline_comment" => {}
+            "control_structure_body" => body = Some(ControlStructureBody::new(&child, content)?),
+            kind if EXPRESSIONS.contains(&kind) => {
+                condition = Some(Expression::new(&child, content)?)
+            }
+            _ => {
+                bail!(
+                    "[Statement::DoWhile] unhandled child {} '{}' at {}",
+                    child.kind(),
+                    child.utf8_text(content)?,
+                    child.start_position(),
+                )
+            }
+        }
+    }
+
+    Ok(Statement::DoWhile(
+        body,
+        condition.context(format!(
+            "[Statement::DoWhile] no condition at {}",
+            node.start_position()
+        ))?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kotlin::test_utils::{find_node, parse};
+
+    use crate::kotlin::expression::Expression;
+    use crate::kotlin::types::Type;
+
+    use super::{get_statements, ForParameter, Statement};
+
+    #[test]
+    fn line_comment() {
+        let content = "fun f() {\n    g()\n    // does nothing\n    h()\n}";
+        let tree = parse(content);
+        let node = find_node(&tree, "statements");
+
+        let statements = get_statements(&node, content.as_bytes()).unwrap();
+        assert_eq!(
+            statements[1],
+            Statement::Comment("// does nothing".to_string())
+        );
+        assert!(matches!(statements[2], Statement::Expression(_)));
+    }
+
+    #[test]
+    fn do_while() {
+        let content = "fun f() {\n    do {\n        g()\n    } while (x)\n    do while (y)\n    do g() while (z)\n}";
+        let tree = parse(content);
+        let node = find_node(&tree, "statements");
+
+        let statements = get_statements(&node, content.as_bytes()).unwrap();
+        let bodies: Vec<Option<usize>> = statements
+            .iter()
+            .map(|statement| match statement {
+                Statement::DoWhile(body, Expression::Identifier { .. }) => {
+                    body.as_ref().map(|body| body.statements.len())
+                }
+                _ => panic!("expected do while with a condition"),
+            })
+            .collect();
+        assert_eq!(bodies, vec![Some(1), None, Some(1)]);
+    }
+
+    #[test]
+    fn for_destructuring() {
+        let content =
+            "fun f() {\n    for ((k, v) in mapOf(\"a\" to 1)) {\n        g(k, v)\n    }\n}";
+        let tree = parse(content);
+        let node = find_node(&tree, "statements");
+
+        let statements = get_statements(&node, content.as_bytes()).unwrap();
+        let Statement::For(_, ForParameter::MultiVariableDeclaration(declaration), Some(body)) =
+            &statements[0]
+        else {
+            panic!("expected for loop over a destructuring declaration");
+        };
+        let names: Vec<&str> = declaration
+            .variable_declarations
+            .iter()
+            .map(|declaration| declaration.identifier.as_str())
+            .collect();
+        assert_eq!(names, vec!["k", "v"]);
+        assert_eq!(body.statements.len(), 1);
+    }
+
+    #[test]
+    fn for_destructuring_with_types() {
+        let content = "fun f() {\n    for ((k: String, v: Int) in map) g(k)\n}";
+        let tree = parse(content);
+        let node = find_node(&tree, "statements");
+
+        let statements = get_statements(&node, content.as_bytes()).unwrap();
+        let Statement::For(_, ForParameter::MultiVariableDeclaration(declaration), _) =
+            &statements[0]
+        else {
+            panic!("expected for loop over a destructuring declaration");
+        };
+        let types: Vec<Option<Type>> = declaration
+            .variable_declarations
+            .iter()
+            .map(|declaration| declaration.data_type.clone())
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                Some(Type::NonNullable(Vec::new(), "String".to_string())),
+                Some(Type::NonNullable(Vec::new(), "Int".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn local_declarations() {
+        let content = "fun f() {\n    class Local(val x: Int)\n    object Cache\n    typealias Id = Int\n    loop@ for (i in 0..1) {\n        break@loop\n    }\n}";
+        let tree = parse(content);
+        let node = find_node(&tree, "statements");
+
+        let statements = get_statements(&node, content.as_bytes()).unwrap();
+        assert!(matches!(&statements[0], Statement::Class(class) if class.name == "Local"));
+        assert!(matches!(&statements[1], Statement::Object(object) if object.name == "Cache"));
+        assert!(matches!(&statements[2], Statement::TypeAlias(alias) if alias.name == "Id"));
+        assert!(matches!(statements[3], Statement::For(..)));
+        assert_eq!(statements.len(), 4);
+    }
+}
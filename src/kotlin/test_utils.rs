@@ -0,0 +1,34 @@
+use tree_sitter::{Node, Parser, Tree};
+
+pub fn parse(content: &str) -> Tree {
+    let mut parser = Parser::new();
+    parser
+        .set_language(tree_sitter_kotlin::language())
+        .expect("failed to create kotlin parser");
+    parser.parse(content, None).expect("failed to parse")
+}
+
+// first node of the given kind in depth-first order
+pub fn find_node<'a>(tree: &'a Tree, kind: &str) -> Node<'a> {
+    let mut cursor = tree.walk();
+    loop {
+        let node = cursor.node();
+        if node.kind() == kind {
+            return node;
+        }
+
+        if cursor.goto_first_child() {
+            continue;
+        }
+
+        loop {
+            if cursor.goto_next_sibling() {
+                break;
+            }
+
+            if !cursor.goto_parent() {
+                panic!("no {kind} node found");
+            }
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use anyhow::{bail, Context, Result};
+use tree_sitter::{Node, Tree};
+
+use super::{
+    kdoc::{self, KDoc},
+    modifier::Modifier,
+    span::Span,
+    types::{Type, TypeParameter, TYPES},
+};
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone)]
+pub struct TypeAlias {
+    pub modifiers: Vec<Modifier>,
+    pub name: String,
+    pub name_span: Span,
+    pub type_parameters: Vec<TypeParameter>,
+    pub aliased_type: Type,
+    pub span: Span,
+    pub kdoc: Option<KDoc>,
+}
+
+impl TypeAlias {
+    pub fn new(node: &Node, content: &[u8]) -> Result<TypeAlias> {
+        let mut modifiers = Vec::new();
+        let mut name = None;
+        let mut type_parameters = Vec::new();
+        let mut aliased_type = None;
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor.clone()) {
+            match child.kind() {
+                "typealias" | "=" | "type_modifiers" | "line_comment" | "multiline_comment" => {}
+                "modifiers" => {
+                    for child in child.children(&mut cursor) {
+                        modifiers.push(Modifier::new(&child, content)?);
+                    }
+                }
+                "type_identifier" => {
+                    name = Some((child.utf8_text(content)?.to_string(), Span::new(&child)))
+                }
+                "type_parameters" => {
+                    for child in child.children(&mut cursor) {
+                        if child.kind() == "type_parameter" {
+                            type_parameters.push(TypeParameter::new(&child, content)?)
+                        }
+                    }
+                }
+                kind => {
+                    if TYPES.contains(&kind) {
+                        aliased_type = Some(Type::new(&child, content)?);
+                    } else {
+                        bail!(
+                            "[TypeAlias] unhandled child {} '{}' at {}",
+                            child.kind(),
+                            child.utf8_text(content)?,
+                            child.start_position(),
+                        )
+                    }
+                }
+            }
+        }
+
+        let (name, name_span) = name.context(format!(
+            "[TypeAlias] no name found at {}",
+            node.start_position()
+        ))?;
+        Ok(TypeAlias {
+            modifiers,
+            name,
+            name_span,
+            type_parameters,
+            aliased_type: aliased_type.context(format!(
+                "[TypeAlias] no aliased type found at {}",
+                node.start_position()
+            ))?,
+            span: Span::new(node),
+            kdoc: kdoc::get_kdoc(node, content)?,
+        })
+    }
+}
+
+pub fn get_type_aliases(tree: &Tree, content: &[u8]) -> Result<Vec<TypeAlias>> {
+    let mut type_aliases = Vec::new();
+    let root = tree.root_node();
+    let mut cursor = root.walk();
+    for child in root.children(&mut cursor) {
+        if child.kind() == "type_alias" {
+            type_aliases.push(TypeAlias::new(&child, content)?);
+        }
+    }
+
+    Ok(type_aliases)
+}
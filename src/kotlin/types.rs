@@ -1,14 +1,13 @@
+use std::fmt;
+
 use anyhow::{bail, Context, Result};
 use tree_sitter::Node;
 
-use super::{
-    argument::{self, Argument},
-    function::Parameter,
-};
+use super::{function::Parameter, span::Span};
 
 pub const TYPES: [&str; 6] = [
     "parenthesized_type",
-    "nullable_Type",
+    "nullable_type",
     "user_type",
     "dynamic",
     "function_type",
@@ -17,7 +16,7 @@ pub const TYPES: [&str; 6] = [
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub enum FunctionTypeParameter {
-    Parameter(Parameter),
+    Parameter(Box<Parameter>),
     Type(Type),
 }
 
@@ -29,7 +28,9 @@ impl FunctionTypeParameter {
         for child in node.children(&mut cursor) {
             match child.kind() {
                 "(" | ")" | ":" => {}
-                "simple_identifier" => identifier = Some(child.utf8_text(content)?.to_string()),
+                "simple_identifier" => {
+                    identifier = Some((child.utf8_text(content)?.to_string(), Span::new(&child)))
+                }
                 "user_type" | "nullable_type" => param_type = Some(Type::new(&child, content)?),
                 _ => {
                     bail!(
@@ -42,16 +43,21 @@ impl FunctionTypeParameter {
             }
         }
 
-        Ok(FunctionTypeParameter::Parameter(Parameter {
-            name: identifier.context(format!(
-                "[FunctionTypeParameter] no identifier found at {}",
-                node.start_position()
-            ))?,
+        let (name, span) = identifier.context(format!(
+            "[FunctionTypeParameter] no identifier found at {}",
+            node.start_position()
+        ))?;
+        Ok(FunctionTypeParameter::Parameter(Box::new(Parameter {
+            name,
+            span,
             type_identifier: param_type.context(format!(
                 "[FunctionTypeParameter] no param type found at {}",
                 node.start_position()
             ))?,
-        }))
+            vararg: false,
+            modifiers: Vec::new(),
+            default_value: None,
+        })))
     }
 
     pub fn new_type(node: &Node, content: &[u8]) -> Result<FunctionTypeParameter> {
@@ -65,14 +71,14 @@ pub enum TypeModifier {
     Suspend,
 }
 
+/// A type reference as written in the source, it is not resolved.
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub enum Type {
     Nullable(Vec<TypeModifier>, String),
     NonNullable(Vec<TypeModifier>, String),
     Function {
         modifiers: Vec<TypeModifier>,
-        type_identifier: Option<String>,
-        type_argument: Option<Box<Argument>>,
+        receiver_type: Option<Box<Type>>,
         parameters: Vec<FunctionTypeParameter>,
         return_type: Box<Type>,
     },
@@ -80,7 +86,10 @@ pub enum Type {
 
 impl Type {
     pub fn new(node: &Node, content: &[u8]) -> Result<Type> {
-        let modifiers = if let Some(prev) = node.prev_sibling() {
+        let modifiers = if let Some(prev) = node
+            .prev_sibling()
+            .filter(|prev| prev.kind() == "type_modifiers")
+        {
             let mut mods = Vec::new();
             let mut cursor = prev.walk();
             for child in prev.children(&mut cursor) {
@@ -107,7 +116,8 @@ impl Type {
 
         match node.kind() {
             "function_type" => get_function_type(modifiers, node, content),
-            "user_type" => Ok(Type::NonNullable(
+            // older grammar versions emit a bare type_identifier instead of a user_type
+            "user_type" | "type_identifier" => Ok(Type::NonNullable(
                 modifiers,
                 node.utf8_text(content)?.to_string(),
             )),
@@ -127,65 +137,110 @@ impl Type {
     }
 }
 
+impl Type {
+    // name of the class without type arguments and nullability, e.g. `List` for `List<Int>?`
+    pub fn class_name(&self) -> Option<&str> {
+        match self {
+            Type::NonNullable(_, name) | Type::Nullable(_, name) => name
+                .split('<')
+                .next()
+                .map(|name| name.trim_end_matches('?').trim()),
+            Type::Function { .. } => None,
+        }
+    }
+}
+
+// kotlin syntax, user types keep their source text
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let modifiers = match self {
+            Type::Nullable(modifiers, _)
+            | Type::NonNullable(modifiers, _)
+            | Type::Function { modifiers, .. } => modifiers,
+        };
+        for modifier in modifiers {
+            match modifier {
+                TypeModifier::Annotation(annotation) => write!(f, "{annotation} ")?,
+                TypeModifier::Suspend => write!(f, "suspend ")?,
+            }
+        }
+
+        match self {
+            Type::Nullable(_, text) | Type::NonNullable(_, text) => write!(f, "{text}"),
+            Type::Function {
+                receiver_type,
+                parameters,
+                return_type,
+                ..
+            } => {
+                if let Some(receiver_type) = receiver_type {
+                    write!(f, "{receiver_type}.")?;
+                }
+
+                let parameters: Vec<String> = parameters
+                    .iter()
+                    .map(|parameter| match parameter {
+                        FunctionTypeParameter::Parameter(parameter) => {
+                            format!("{}: {}", parameter.name, parameter.type_identifier)
+                        }
+                        FunctionTypeParameter::Type(data_type) => data_type.to_string(),
+                    })
+                    .collect();
+                write!(f, "({}) -> {return_type}", parameters.join(", "))
+            }
+        }
+    }
+}
+
+// everything in front of the parameters is the receiver, e.g. `List<Int>` in `List<Int>.() -> Unit`
 fn get_function_type(modifiers: Vec<TypeModifier>, node: &Node, content: &[u8]) -> Result<Type> {
-    let first_child = node.child(0).context(format!(
-        "[Type::Function] no function parameters found at {}",
-        node.start_position(),
-    ))?;
-    let (type_identifier, type_argument, parameters, return_type) = match first_child.kind() {
-        "function_type_parameters" => (
-            None,
-            None,
-            get_function_type_params(&first_child, content)?,
-            Box::new(Type::new(
-                &node.child(2).context(format!(
-                    "[Type::Function] no return type found at {}",
-                    node.start_position(),
-                ))?,
-                content,
-            )?),
-        ),
-        "type_identifier" => (
-            Some(first_child.utf8_text(content)?.to_string()),
-            Some(Box::new(argument::get_type_argument(
-                &node.child(1).context(format!(
-                    "[Type::Function] no function parameters found at {}",
-                    node.start_position(),
-                ))?,
-                content,
-            )?)),
-            get_function_type_params(
-                &node.child(2).context(format!(
-                    "[Type::Function] no function parameters found at {}",
-                    node.start_position(),
-                ))?,
-                content,
-            )?,
-            Box::new(Type::new(
-                &node
-                    .child(4)
-                    .filter(|c| c.kind() != "->")
-                    .or_else(|| node.child(5))
-                    .context(format!(
-                        "[Type::Function] no return type found at {}",
-                        node.start_position(),
-                    ))?,
-                content,
-            )?),
-        ),
-        unknown_child => {
-            bail!(
-                "[Type::Function] unhandled child {unknown_child} at {}",
-                node.start_position()
-            )
+    let mut receiver = Vec::new();
+    let mut parameters = None;
+    let mut return_type = None;
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        match child.kind() {
+            "." | "->" => {}
+            "function_type_parameters" => {
+                parameters = Some(get_function_type_params(&child, content)?)
+            }
+            kind if parameters.is_none() => match kind {
+                "type_identifier" | "type_arguments" => receiver.push(child),
+                kind if TYPES.contains(&kind) => receiver.push(child),
+                _ => bail!(
+                    "[Type::Function] unhandled receiver {} '{}' at {}",
+                    child.kind(),
+                    child.utf8_text(content)?,
+                    child.start_position(),
+                ),
+            },
+            _ => return_type = Some(Box::new(Type::new(&child, content)?)),
+        }
+    }
+
+    let receiver_type = match receiver.as_slice() {
+        [] => None,
+        [receiver] if TYPES.contains(&receiver.kind()) => {
+            Some(Box::new(Type::new(receiver, content)?))
+        }
+        // the grammar splits a user type receiver into its name and type arguments
+        [first, .., last] | [first @ last] => {
+            let text = std::str::from_utf8(&content[first.start_byte()..last.end_byte()])?;
+            Some(Box::new(Type::NonNullable(Vec::new(), text.to_string())))
         }
     };
+
     Ok(Type::Function {
         modifiers,
-        type_identifier,
-        type_argument,
-        parameters,
-        return_type,
+        receiver_type,
+        parameters: parameters.context(format!(
+            "[Type::Function] no function parameters found at {}",
+            node.start_position(),
+        ))?,
+        return_type: return_type.context(format!(
+            "[Type::Function] no return type found at {}",
+            node.start_position(),
+        ))?,
     })
 }
 
@@ -213,20 +268,49 @@ fn get_function_type_params(node: &Node, content: &[u8]) -> Result<Vec<FunctionT
     Ok(params)
 }
 
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub enum Variance {
+    In,
+    Out,
+}
+
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub struct TypeParameter {
     identifier: String,
+    variance: Option<Variance>,
+    // only allowed on type parameters of inline functions
+    reified: bool,
+    // upper bound, e.g. `Any` in `T : Any`
     data_type: Option<Type>,
 }
 
 impl TypeParameter {
     pub fn new(node: &Node, content: &[u8]) -> Result<TypeParameter> {
         let mut identifier = None;
+        let mut variance = None;
+        let mut reified = false;
         let mut data_type = None;
         let mut cursor = node.walk();
         for child in node.children(&mut cursor) {
             match child.kind() {
+                ":" => {}
                 "type_identifier" => identifier = Some(child.utf8_text(content)?.to_string()),
+                "type_parameter_modifiers" => {
+                    let mut cursor = child.walk();
+                    for modifier in child.children(&mut cursor) {
+                        match modifier.kind() {
+                            "variance_modifier" => {
+                                variance = match modifier.utf8_text(content)? {
+                                    "in" => Some(Variance::In),
+                                    "out" => Some(Variance::Out),
+                                    _ => None,
+                                }
+                            }
+                            "reification_modifier" => reified = true,
+                            _ => {}
+                        }
+                    }
+                }
                 kind => {
                     if TYPES.contains(&kind) {
                         data_type = Some(Type::new(&child, content)?);
@@ -247,7 +331,87 @@ impl TypeParameter {
                 "[TypeParameter] no identifier found at {}",
                 node.start_position()
             ))?,
+            variance,
+            reified,
             data_type,
         })
     }
+
+    pub fn name(&self) -> &str {
+        &self.identifier
+    }
+
+    pub fn is_reified(&self) -> bool {
+        self.reified
+    }
+}
+
+impl fmt::Display for TypeParameter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.reified {
+            write!(f, "reified ")?;
+        }
+        match self.variance {
+            Some(Variance::In) => write!(f, "in ")?,
+            Some(Variance::Out) => write!(f, "out ")?,
+            None => {}
+        }
+        write!(f, "{}", self.identifier)?;
+        if let Some(data_type) = &self.data_type {
+            write!(f, " : {data_type}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kotlin::{
+        test_utils::{find_node, parse},
+        Declaration, KotlinFile,
+    };
+
+    use super::Type;
+
+    #[test]
+    fn function_type_receiver() {
+        for content in [
+            "val f: String.(Int) -> Boolean = g",
+            "val f: List<Int>.() -> Unit = g",
+            "val f: (Int, String) -> Unit = g",
+        ] {
+            let tree = parse(content);
+            let node = find_node(&tree, "function_type");
+
+            let data_type = Type::new(&node, content.as_bytes()).unwrap();
+            assert_eq!(
+                data_type.to_string(),
+                node.utf8_text(content.as_bytes()).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn variance() {
+        let file: KotlinFile = "class Foo<out T, in R : Any, X>\n".parse().unwrap();
+
+        assert_eq!(
+            Declaration::Class(&file.classes[0]).signature(),
+            "class Foo<out T, in R : Any, X>"
+        );
+    }
+
+    #[test]
+    fn reified() {
+        let file: KotlinFile = "inline fun <reified T, R> foo() {}\n".parse().unwrap();
+
+        let function = &file.functions[0];
+        assert!(function.type_parameters[0].is_reified());
+        assert!(!function.type_parameters[1].is_reified());
+        assert_eq!(
+            Declaration::Function(function).call_signature().unwrap().0,
+            "inline fun <reified T, R> foo()"
+        );
+    }
 }
@@ -1,12 +1,13 @@
 use anyhow::{bail, Context, Result};
 use tree_sitter::Node;
 
-use super::types::Type;
+use super::{span::Span, types::Type};
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub struct VariableDeclaration {
-    identifier: String,
-    data_type: Option<Type>,
+    pub identifier: String,
+    pub identifier_span: Span,
+    pub data_type: Option<Type>,
 }
 
 impl VariableDeclaration {
@@ -17,8 +18,12 @@ impl VariableDeclaration {
         for child in node.children(&mut cursor) {
             match child.kind() {
                 ":" => {}
-                "simple_identifier" => identifier = Some(child.utf8_text(content)?.to_string()),
-                "user_type" | "nullable_type" => data_type = Some(Type::new(&child, content)?),
+                "simple_identifier" => {
+                    identifier = Some((child.utf8_text(content)?.to_string(), Span::new(&child)))
+                }
+                "user_type" | "nullable_type" | "type_identifier" | "function_type" => {
+                    data_type = Some(Type::new(&child, content)?)
+                }
                 _ => {
                     bail!(
                         "[VariableDeclaration] unhandled child {} '{}' at {}",
@@ -30,8 +35,10 @@ impl VariableDeclaration {
             }
         }
 
+        let (identifier, identifier_span) = identifier.context("no identifier found")?;
         Ok(VariableDeclaration {
-            identifier: identifier.context("no identifier found")?,
+            identifier,
+            identifier_span,
             data_type,
         })
     }
@@ -39,7 +46,7 @@ impl VariableDeclaration {
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub struct MultiVariableDeclaration {
-    variable_declarations: Vec<VariableDeclaration>,
+    pub variable_declarations: Vec<VariableDeclaration>,
 }
 
 impl MultiVariableDeclaration {
@@ -66,3 +73,27 @@ impl MultiVariableDeclaration {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::kotlin::{
+        test_utils::{find_node, parse},
+        types::Type,
+    };
+
+    use super::VariableDeclaration;
+
+    #[test]
+    fn function_type() {
+        let content = "class A {\n    val callback: (Int) -> String\n}";
+        let tree = parse(content);
+        let node = find_node(&tree, "variable_declaration");
+
+        let declaration = VariableDeclaration::new(&node, content.as_bytes()).unwrap();
+        assert!(matches!(
+            declaration.data_type,
+            Some(Type::Function { ref return_type, .. })
+                if **return_type == Type::NonNullable(Vec::new(), "String".to_string())
+        ));
+    }
+}
@@ -0,0 +1,258 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Value};
+use tower_lsp::lsp_types::{Position, Url};
+use tracing::warn;
+use tree_sitter::Point;
+
+use crate::kotlin::{self, KotlinFile, Span};
+use crate::{hover_markdown, utf16_column};
+
+const LSIF_VERSION: &str = "0.4.3";
+
+// writes one vertex or edge per line, ids are handed out in order
+struct Emitter<W> {
+    out: W,
+    next_id: u64,
+}
+
+impl<W: Write> Emitter<W> {
+    fn emit(&mut self, element: &str, label: &str, mut data: Value) -> Result<u64> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        data["id"] = json!(id);
+        data["type"] = json!(element);
+        data["label"] = json!(label);
+        writeln!(self.out, "{data}")?;
+
+        Ok(id)
+    }
+
+    fn vertex(&mut self, label: &str, data: Value) -> Result<u64> {
+        self.emit("vertex", label, data)
+    }
+
+    fn edge(&mut self, label: &str, data: Value) -> Result<u64> {
+        self.emit("edge", label, data)
+    }
+
+    // columns are converted to UTF-16, the encoding declared in the metadata
+    fn range(&mut self, lines: &[&[u8]], span: Span) -> Result<u64> {
+        let position = |point: Point| {
+            let line = lines.get(point.row).copied().unwrap_or_default();
+            Position::new(point.row as u32, utf16_column(line, point.column) as u32)
+        };
+        self.vertex(
+            "range",
+            json!({ "start": position(span.start), "end": position(span.end) }),
+        )
+    }
+
+    // one item edge per document, the ranges are grouped by the document they are in
+    fn items(&mut self, result: u64, ranges: &[(u64, u64)], property: Option<&str>) -> Result<()> {
+        for group in ranges.chunk_by(|(a, _), (b, _)| a == b) {
+            let mut data = json!({
+                "outV": result,
+                "inVs": group.iter().map(|(_, range)| *range).collect::<Vec<u64>>(),
+                "document": group[0].0,
+            });
+            if let Some(property) = property {
+                data["property"] = json!(property);
+            }
+            self.edge("item", data)?;
+        }
+
+        Ok(())
+    }
+}
+
+// declarations and usages are matched by name, the same way the server resolves them
+pub fn dump(root: &str, out: impl Write) -> Result<()> {
+    let root = Path::new(root)
+        .canonicalize()
+        .context(format!("failed to resolve {root}"))?;
+    let root_uri = Url::from_directory_path(&root).map_err(|_| anyhow!("invalid root {root:?}"))?;
+
+    let mut files: Vec<(PathBuf, Vec<u8>, KotlinFile)> = Vec::new();
+    for (path, file) in kotlin::from_path(&root.to_string_lossy())? {
+        match file {
+            Ok(file) => {
+                let content = std::fs::read(&path).context(format!("failed to read {path:?}"))?;
+                files.push((path, content, file));
+            }
+            Err(err) => warn!("Skipping {:?}: {:?}", path, err),
+        }
+    }
+    files.sort_by(|(a, ..), (b, ..)| a.cmp(b));
+
+    emit(&root_uri, &files, out)
+}
+
+fn emit(root_uri: &Url, files: &[(PathBuf, Vec<u8>, KotlinFile)], out: impl Write) -> Result<()> {
+    let mut hovers: BTreeMap<&str, String> = BTreeMap::new();
+    for (_, _, file) in files {
+        file.walk_declarations(&mut |declaration| {
+            hovers
+                .entry(declaration.name())
+                .or_insert_with(|| hover_markdown(declaration));
+        });
+    }
+
+    let mut lsif = Emitter { out, next_id: 1 };
+    lsif.vertex(
+        "metaData",
+        json!({
+            "version": LSIF_VERSION,
+            "projectRoot": root_uri,
+            "positionEncoding": "utf-16",
+            "toolInfo": {
+                "name": env!("CARGO_PKG_NAME"),
+                "version": env!("CARGO_PKG_VERSION"),
+            },
+        }),
+    )?;
+    let project = lsif.vertex("project", json!({ "kind": "kotlin" }))?;
+
+    // (document, range) pairs per name
+    let mut definitions: BTreeMap<&str, Vec<(u64, u64)>> = BTreeMap::new();
+    let mut references: BTreeMap<&str, Vec<(u64, u64)>> = BTreeMap::new();
+    let mut documents = Vec::new();
+    for (path, content, file) in files {
+        let lines: Vec<&[u8]> = content.split(|&b| b == b'\n').collect();
+        let uri = Url::from_file_path(path).map_err(|_| anyhow!("invalid path {path:?}"))?;
+        let document = lsif.vertex("document", json!({ "uri": uri, "languageId": "kotlin" }))?;
+        documents.push(document);
+
+        let mut declarations = Vec::new();
+        file.walk_declarations(&mut |declaration| {
+            declarations.push((declaration.name(), declaration.name_span()));
+        });
+        let mut usages = Vec::new();
+        file.walk_expressions(&mut |expression| {
+            if let Some((identifier, span)) = expression.identifier() {
+                if hovers.contains_key(identifier) {
+                    usages.push((identifier, span));
+                }
+            }
+        });
+
+        let mut ranges = Vec::new();
+        for (name, span) in declarations {
+            let range = lsif.range(&lines, span)?;
+            definitions.entry(name).or_default().push((document, range));
+            ranges.push(range);
+        }
+        for (name, span) in usages {
+            let range = lsif.range(&lines, span)?;
+            references.entry(name).or_default().push((document, range));
+            ranges.push(range);
+        }
+
+        if !ranges.is_empty() {
+            lsif.edge("contains", json!({ "outV": document, "inVs": ranges }))?;
+        }
+    }
+    if !documents.is_empty() {
+        lsif.edge("contains", json!({ "outV": project, "inVs": documents }))?;
+    }
+
+    for (name, markdown) in hovers {
+        let declared = &definitions[name];
+        let used = references.get(name).map_or(&[][..], Vec::as_slice);
+
+        let result_set = lsif.vertex("resultSet", json!({}))?;
+        for (_, range) in declared.iter().chain(used) {
+            lsif.edge("next", json!({ "outV": range, "inV": result_set }))?;
+        }
+
+        let hover = lsif.vertex(
+            "hoverResult",
+            json!({ "result": { "contents": { "kind": "markdown", "value": markdown } } }),
+        )?;
+        lsif.edge(
+            "textDocument/hover",
+            json!({ "outV": result_set, "inV": hover }),
+        )?;
+
+        let definition = lsif.vertex("definitionResult", json!({}))?;
+        lsif.edge(
+            "textDocument/definition",
+            json!({ "outV": result_set, "inV": definition }),
+        )?;
+        lsif.items(definition, declared, None)?;
+
+        let reference = lsif.vertex("referenceResult", json!({}))?;
+        lsif.edge(
+            "textDocument/references",
+            json!({ "outV": result_set, "inV": reference }),
+        )?;
+        lsif.items(reference, declared, Some("definitions"))?;
+        lsif.items(reference, used, Some("references"))?;
+    }
+
+    lsif.out.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use tower_lsp::lsp_types::Url;
+
+    use super::emit;
+
+    #[test]
+    fn small_file() {
+        let content = "fun f() = \"\u{e9}\" + g()\nfun g() = f()\n";
+        let files = vec![(
+            PathBuf::from("/project/A.kt"),
+            content.as_bytes().to_vec(),
+            content.parse().unwrap(),
+        )];
+
+        let mut out = Vec::new();
+        emit(&Url::parse("file:///project/").unwrap(), &files, &mut out).unwrap();
+        // the reference to `g` is after a two byte character, one UTF-16 code unit
+        let expected = r#"{"id":1,"label":"metaData","positionEncoding":"utf-16","projectRoot":"file:///project/","toolInfo":{"name":"kotlin-ls","version":"{version}"},"type":"vertex","version":"0.4.3"}
+{"id":2,"kind":"kotlin","label":"project","type":"vertex"}
+{"id":3,"label":"document","languageId":"kotlin","type":"vertex","uri":"file:///project/A.kt"}
+{"end":{"character":5,"line":0},"id":4,"label":"range","start":{"character":4,"line":0},"type":"vertex"}
+{"end":{"character":5,"line":1},"id":5,"label":"range","start":{"character":4,"line":1},"type":"vertex"}
+{"end":{"character":17,"line":0},"id":6,"label":"range","start":{"character":16,"line":0},"type":"vertex"}
+{"end":{"character":11,"line":1},"id":7,"label":"range","start":{"character":10,"line":1},"type":"vertex"}
+{"id":8,"inVs":[4,5,6,7],"label":"contains","outV":3,"type":"edge"}
+{"id":9,"inVs":[3],"label":"contains","outV":2,"type":"edge"}
+{"id":10,"label":"resultSet","type":"vertex"}
+{"id":11,"inV":10,"label":"next","outV":4,"type":"edge"}
+{"id":12,"inV":10,"label":"next","outV":7,"type":"edge"}
+{"id":13,"label":"hoverResult","result":{"contents":{"kind":"markdown","value":"```kotlin\nfun f()\n```"}},"type":"vertex"}
+{"id":14,"inV":13,"label":"textDocument/hover","outV":10,"type":"edge"}
+{"id":15,"label":"definitionResult","type":"vertex"}
+{"id":16,"inV":15,"label":"textDocument/definition","outV":10,"type":"edge"}
+{"document":3,"id":17,"inVs":[4],"label":"item","outV":15,"type":"edge"}
+{"id":18,"label":"referenceResult","type":"vertex"}
+{"id":19,"inV":18,"label":"textDocument/references","outV":10,"type":"edge"}
+{"document":3,"id":20,"inVs":[4],"label":"item","outV":18,"property":"definitions","type":"edge"}
+{"document":3,"id":21,"inVs":[7],"label":"item","outV":18,"property":"references","type":"edge"}
+{"id":22,"label":"resultSet","type":"vertex"}
+{"id":23,"inV":22,"label":"next","outV":5,"type":"edge"}
+{"id":24,"inV":22,"label":"next","outV":6,"type":"edge"}
+{"id":25,"label":"hoverResult","result":{"contents":{"kind":"markdown","value":"```kotlin\nfun g()\n```"}},"type":"vertex"}
+{"id":26,"inV":25,"label":"textDocument/hover","outV":22,"type":"edge"}
+{"id":27,"label":"definitionResult","type":"vertex"}
+{"id":28,"inV":27,"label":"textDocument/definition","outV":22,"type":"edge"}
+{"document":3,"id":29,"inVs":[5],"label":"item","outV":27,"type":"edge"}
+{"id":30,"label":"referenceResult","type":"vertex"}
+{"id":31,"inV":30,"label":"textDocument/references","outV":22,"type":"edge"}
+{"document":3,"id":32,"inVs":[5],"label":"item","outV":30,"property":"definitions","type":"edge"}
+{"document":3,"id":33,"inVs":[6],"label":"item","outV":30,"property":"references","type":"edge"}
+"#
+            .replace("{version}", env!("CARGO_PKG_VERSION"));
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+    }
+}
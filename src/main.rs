@@ -1,88 +1,2055 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs::OpenOptions;
-use std::panic::PanicInfo;
-use std::path::PathBuf;
+use std::io::BufWriter;
+use std::iter::Peekable;
+use std::panic::PanicHookInfo;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::RwLock;
+use std::time::Instant;
 
+use anyhow::Context;
 use dashmap::DashMap;
-use kotlin::KotlinFile;
-use tower_lsp::jsonrpc::Result;
+use index::{PackageIndex, SymbolIndex, SymbolLocation};
+use kotlin::{
+    Class, ClassType, Declaration, FoldingKind, Function, KeywordContext, KotlinFile, ModuleGraph,
+    Span, Symbol, SynthesizedFunction, TokenType,
+};
+use serde::Serialize;
+use tower_lsp::jsonrpc::{Error, ErrorCode, Result};
 use tower_lsp::lsp_types::*;
 use tower_lsp::{Client, LanguageServer, LspService, Server};
 use tracing::{error, info, warn};
+use tree_sitter::{Parser, Point, Tree};
 
+mod index;
 pub mod kotlin;
+mod lsif;
+
+const MAX_WORKSPACE_SYMBOLS: usize = 100;
+
+// takes the uri of the file as its only argument
+const ORGANIZE_IMPORTS: &str = "kotlin-ls.organizeImports";
+
+// executed by the client with the uri of the file and the name of a test function or class
+const RUN_TEST: &str = "kotlin-ls.runTest";
+
+// RequestFailed from the LSP spec, tower-lsp has no constant for it
+const REQUEST_FAILED: i64 = -32803;
+
+// indexed by kotlin::TokenType
+const TOKEN_TYPES: [SemanticTokenType; 12] = [
+    SemanticTokenType::CLASS,
+    SemanticTokenType::INTERFACE,
+    SemanticTokenType::ENUM,
+    SemanticTokenType::FUNCTION,
+    SemanticTokenType::VARIABLE,
+    SemanticTokenType::PARAMETER,
+    SemanticTokenType::TYPE,
+    SemanticTokenType::KEYWORD,
+    SemanticTokenType::STRING,
+    SemanticTokenType::NUMBER,
+    SemanticTokenType::COMMENT,
+    SemanticTokenType::OPERATOR,
+];
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Metrics {
+    parse_cache_misses: u64,
+}
 
 struct Backend {
     client: Client,
     files: DashMap<PathBuf, KotlinFile>,
+    // last known content of each file, used by features that need the syntax tree
+    contents: DashMap<PathBuf, Vec<u8>>,
+    // latest diagnostics per file, an empty list clears them on the client
+    diagnostics: DashMap<PathBuf, Vec<Diagnostic>>,
+    index: RwLock<SymbolIndex>,
+    packages: RwLock<PackageIndex>,
+    modules: RwLock<ModuleGraph>,
+    parse_cache_misses: AtomicU64,
+    // lsp-types has no static capability for type hierarchies, so clients that support it
+    // get it registered once they are initialized
+    register_type_hierarchy: AtomicBool,
+}
+
+impl Backend {
+    fn new(client: Client) -> Backend {
+        Backend {
+            client,
+            files: DashMap::new(),
+            contents: DashMap::new(),
+            diagnostics: DashMap::new(),
+            index: RwLock::default(),
+            packages: RwLock::default(),
+            modules: RwLock::default(),
+            parse_cache_misses: AtomicU64::new(0),
+            register_type_hierarchy: AtomicBool::new(false),
+        }
+    }
+
+    fn reparse(&self, path: PathBuf, content: &[u8]) {
+        self.parse(path.clone(), content);
+        if let Some(file) = self.files.get(&path) {
+            self.index.write().unwrap().update(&path, &file);
+            self.packages.write().unwrap().update(&path, &file);
+        }
+        self.build_modules();
+        self.add_import_cycle_diagnostics(&path);
+    }
+
+    // the graph has no incremental updates, it is cheap enough to build again
+    fn build_modules(&self) {
+        let files: Vec<_> = self.files.iter().collect();
+        *self.modules.write().unwrap() = ModuleGraph::new(
+            files
+                .iter()
+                .map(|file| (file.key().as_path(), file.value())),
+        );
+    }
+
+    fn add_import_cycle_diagnostics(&self, path: &Path) {
+        let Some(file) = self.files.get(path) else {
+            return;
+        };
+        let package = file.package.name();
+        if package.is_empty() {
+            return;
+        }
+
+        let modules = self.modules.read().unwrap();
+        let warnings: Vec<Diagnostic> = file
+            .imports
+            .iter()
+            .filter_map(|import| {
+                let cycle = modules.import_cycle(package, &import.path)?;
+                Some(Diagnostic {
+                    severity: Some(DiagnosticSeverity::WARNING),
+                    ..error_diagnostic(
+                        self.range(path, import.span),
+                        format!("Circular import: {package} -> {}", cycle.join(" -> ")),
+                    )
+                })
+            })
+            .collect();
+        self.diagnostics
+            .entry(path.to_path_buf())
+            .or_default()
+            .extend(warnings);
+    }
+
+    // a failed analysis keeps the previous version of the file around
+    fn parse(&self, path: PathBuf, content: &[u8]) {
+        match kotlin::new_parser() {
+            Ok(mut parser) => self.parse_with(&mut parser, path, content),
+            Err(err) => error!("Failed to parse {:?}: {:?}", path, err),
+        }
+    }
+
+    fn parse_with(&self, parser: &mut Parser, path: PathBuf, content: &[u8]) {
+        let tree = parser
+            .parse(content, None)
+            .context(format!("failed to parse {path:?}"));
+        let tree = match tree {
+            Ok(tree) => tree,
+            Err(err) => {
+                error!("Failed to parse {:?}: {:?}", path, err);
+                return;
+            }
+        };
+
+        let mut diagnostics: Vec<Diagnostic> = kotlin::syntax_errors(&tree)
+            .into_iter()
+            .map(|(span, message)| error_diagnostic(span_to_range(content, span), message))
+            .collect();
+
+        match KotlinFile::new(&tree, content).context(format!("failed to analyze {path:?}")) {
+            Ok(f) => {
+                diagnostics.extend(f.null_safety_warnings().into_iter().map(|(span, message)| {
+                    Diagnostic {
+                        severity: Some(DiagnosticSeverity::WARNING),
+                        ..error_diagnostic(span_to_range(content, span), message)
+                    }
+                }));
+                self.files.insert(path.clone(), f);
+            }
+            Err(err) => {
+                error!("Failed to parse {:?}: {:?}", path, err);
+                diagnostics.push(error_diagnostic(Range::default(), format!("{err:#}")));
+            }
+        }
+
+        self.diagnostics.insert(path.clone(), diagnostics);
+        self.contents.insert(path, content.to_vec());
+    }
+
+    async fn publish_diagnostics(&self, path: &Path) {
+        let Ok(uri) = Url::from_file_path(path) else {
+            return;
+        };
+
+        let diagnostics = self
+            .diagnostics
+            .get(path)
+            .map(|diagnostics| diagnostics.clone())
+            .unwrap_or_default();
+        self.client
+            .publish_diagnostics(uri, diagnostics, None)
+            .await;
+    }
+
+    // reparsed from the last known content, only the analyzed file is kept around
+    fn syntax_tree(&self, uri: &Url) -> Option<(Vec<u8>, Tree)> {
+        let path = uri.to_file_path().ok()?;
+        let content = self.contents.get(&path)?.clone();
+
+        let mut parser = Parser::new();
+        if let Err(err) = parser.set_language(tree_sitter_kotlin::language()) {
+            error!("Failed to create kotlin parser: {:?}", err);
+            return None;
+        }
+        let tree = parser.parse(&content, None)?;
+
+        Some((content, tree))
+    }
+
+    fn call_hierarchy_item(
+        &self,
+        path: &Path,
+        file: &KotlinFile,
+        function: &Function,
+    ) -> Option<CallHierarchyItem> {
+        let declaration = Declaration::Function(function);
+        Some(CallHierarchyItem {
+            name: function.name.clone(),
+            kind: index::symbol_kind(file, declaration),
+            tags: None,
+            detail: Some(declaration.signature()),
+            uri: Url::from_file_path(path).ok()?,
+            range: self.range(path, function.span),
+            selection_range: self.range(path, function.name_span),
+            data: None,
+        })
+    }
+
+    fn type_hierarchy_item(
+        &self,
+        path: &Path,
+        file: &KotlinFile,
+        declaration: Declaration,
+    ) -> Option<TypeHierarchyItem> {
+        Some(TypeHierarchyItem {
+            name: declaration.name().to_string(),
+            kind: index::symbol_kind(file, declaration),
+            tags: None,
+            detail: Some(declaration.signature()),
+            uri: Url::from_file_path(path).ok()?,
+            range: self.range(path, declaration.span()),
+            selection_range: self.range(path, declaration.name_span()),
+            data: None,
+        })
+    }
+
+    // the range of a span in the last known content of the file
+    fn range(&self, path: &Path, span: Span) -> Range {
+        match self.contents.get(path) {
+            Some(content) => span_to_range(&content, span),
+            None => span_to_range(&[], span),
+        }
+    }
+
+    fn point(&self, path: &Path, position: Position) -> Point {
+        match self.contents.get(path) {
+            Some(content) => position_to_point(&content, position),
+            None => position_to_point(&[], position),
+        }
+    }
+
+    fn identifier_at(&self, position: &TextDocumentPositionParams) -> Option<(String, Span)> {
+        let path = position.text_document.uri.to_file_path().ok()?;
+        let point = self.point(&path, position.position);
+
+        self.files
+            .get(&path)?
+            .identifier_at(point)
+            .map(|(name, span)| (name.to_string(), span))
+    }
+
+    // whether a qualified name, or a bare one in files without a package, is declared at the
+    // top level of the workspace
+    fn declares_top_level(&self, qualified_name: &str) -> bool {
+        if qualified_name.contains('.') {
+            return !self.packages.read().unwrap().get(qualified_name).is_empty();
+        }
+
+        self.files
+            .iter()
+            .any(|file| file.package.name().is_empty() && file.declares_top_level(qualified_name))
+    }
+
+    // every occurrence of the name in the file and what each of them resolves to
+    fn occurrences(&self, uri: &Url, name: &str) -> Vec<(Span, Symbol)> {
+        let Some(file) = uri
+            .to_file_path()
+            .ok()
+            .and_then(|path| self.files.get(&path).map(|file| file.clone()))
+        else {
+            return Vec::new();
+        };
+        let Some((content, tree)) = self.syntax_tree(uri) else {
+            return Vec::new();
+        };
+
+        kotlin::occurrences(&file, &tree, &content, name, &|qualified_name| {
+            self.declares_top_level(qualified_name)
+        })
+    }
+
+    // the identifier under the cursor, its span and what it resolves to
+    fn symbol_at(&self, position: &TextDocumentPositionParams) -> Option<(String, Span, Symbol)> {
+        let uri = &position.text_document.uri;
+        let (content, tree) = self.syntax_tree(uri)?;
+        let point = position_to_point(&content, position.position);
+        let name = name_at(&tree, &content, point)?;
+
+        self.occurrences(uri, &name)
+            .into_iter()
+            .find(|(span, _)| span.contains(point) || span.end == point)
+            .map(|(span, symbol)| (name, span, symbol))
+    }
+
+    // only what is declared in the workspace can be renamed, usages under an import alias
+    // keep the alias
+    fn is_renameable(&self, symbol: &Symbol) -> bool {
+        match symbol {
+            Symbol::Local(_) => true,
+            Symbol::TopLevel(qualified_name) => self.declares_top_level(qualified_name),
+            Symbol::Member(name) => !self.index.read().unwrap().get(name).is_empty(),
+            Symbol::Alias(_) => false,
+        }
+    }
+
+    fn find_references(
+        &self,
+        name: &str,
+        include_declaration: bool,
+        include_aliases: bool,
+    ) -> Vec<(PathBuf, Vec<Span>)> {
+        let index = self.index.read().unwrap();
+        let mut references = Vec::new();
+        for file in self.files.iter() {
+            let mut spans = file.find_references(name);
+            if include_declaration {
+                spans.extend(
+                    index
+                        .get(name)
+                        .iter()
+                        .filter(|location| &location.path == file.key())
+                        .map(|location| location.span),
+                );
+            }
+            if include_aliases {
+                spans.extend(file.find_alias_references(name));
+            }
+            if !spans.is_empty() {
+                references.push((file.key().clone(), spans));
+            }
+        }
+
+        references
+    }
+
+    // members of a class and of the types it delegates to, members of subclasses come first
+    fn class_members(&self, name: &str) -> Vec<CompletionItem> {
+        let mut items: Vec<CompletionItem> = Vec::new();
+        let mut pending = vec![name.to_string()];
+        let mut visited = HashSet::new();
+        while let Some(name) = pending.pop() {
+            if !visited.insert(name.clone()) {
+                continue;
+            }
+
+            for file in self.files.iter() {
+                for class in file.find_class_by_name(&name) {
+                    for item in member_items(class) {
+                        if !items.iter().any(|known| known.label == item.label) {
+                            items.push(item);
+                        }
+                    }
+                    pending.extend(
+                        class
+                            .delegations
+                            .iter()
+                            .filter_map(|delegation| delegation.type_name())
+                            .map(str::to_string),
+                    );
+                }
+            }
+        }
+
+        items
+    }
+
+    // annotation classes for the name of an annotation, the arguments have to be
+    // compile-time constants so only `const val`s are offered there
+    fn annotation_items(&self, position: AnnotationPosition) -> Vec<CompletionItem> {
+        let mut items: Vec<CompletionItem> = Vec::new();
+        for file in self.files.iter() {
+            file.walk_declarations(&mut |declaration| {
+                let kind = match (position, declaration) {
+                    (AnnotationPosition::Name, Declaration::Class(class))
+                        if class.class_type == ClassType::Annotation =>
+                    {
+                        CompletionItemKind::CLASS
+                    }
+                    (AnnotationPosition::Arguments, Declaration::Property(property, _))
+                        if property.is_const() =>
+                    {
+                        CompletionItemKind::CONSTANT
+                    }
+                    _ => return,
+                };
+                if !items.iter().any(|known| known.label == declaration.name()) {
+                    items.push(CompletionItem {
+                        label: declaration.name().to_string(),
+                        kind: Some(kind),
+                        detail: Some(declaration.signature()),
+                        ..Default::default()
+                    });
+                }
+            });
+        }
+
+        items
+    }
+
+    fn search_symbols(&self, query: &str) -> Vec<SymbolInformation> {
+        let query = query.to_lowercase();
+        let mut symbols = Vec::new();
+        for (name, location) in self.index.read().unwrap().iter() {
+            let Some(quality) = match_quality(&name.to_lowercase(), &query) else {
+                continue;
+            };
+            let Ok(uri) = Url::from_file_path(&location.path) else {
+                continue;
+            };
+
+            #[allow(deprecated)]
+            let symbol = SymbolInformation {
+                name: name.to_string(),
+                kind: location.kind,
+                tags: None,
+                deprecated: None,
+                location: Location::new(uri, self.range(&location.path, location.span)),
+                container_name: None,
+            };
+            symbols.push((quality, symbol));
+        }
+
+        symbols.sort_by(|(a, x), (b, y)| a.cmp(b).then_with(|| x.name.cmp(&y.name)));
+        symbols
+            .into_iter()
+            .take(MAX_WORKSPACE_SYMBOLS)
+            .map(|(_, symbol)| symbol)
+            .collect()
+    }
+
+    // runs a test class or function with gradle, the debug variant waits for a jvm debugger
+    async fn run_test(&self, arguments: &[serde_json::Value]) -> Result<Option<serde_json::Value>> {
+        let (Some(uri), Some(name)) = (
+            arguments
+                .first()
+                .and_then(|argument| argument.as_str())
+                .and_then(|uri| Url::parse(uri).ok()),
+            arguments.get(1).and_then(|argument| argument.as_str()),
+        ) else {
+            return Err(Error::invalid_params(format!(
+                "{RUN_TEST} expects the uri of a file and the name of a test"
+            )));
+        };
+        let debug = arguments
+            .get(2)
+            .and_then(|argument| argument.as_bool())
+            .unwrap_or(false);
+
+        let Ok(path) = uri.to_file_path() else {
+            return Err(Error::invalid_params(format!("{uri} is not a file")));
+        };
+        let Some(filter) = self
+            .files
+            .get(&path)
+            .and_then(|file| test_filter(&file, name))
+        else {
+            return Err(Error::invalid_params(format!(
+                "no test named {name} in {uri}"
+            )));
+        };
+        let Some((root, gradle)) = gradle_project(&path) else {
+            self.client
+                .show_message(
+                    MessageType::ERROR,
+                    format!("No gradle project found for {uri}"),
+                )
+                .await;
+            return Ok(None);
+        };
+
+        let mut command = tokio::process::Command::new(gradle);
+        command
+            .current_dir(&root)
+            .args(["test", "--tests", &filter]);
+        if debug {
+            command.arg("--debug-jvm");
+            self.client
+                .show_message(
+                    MessageType::INFO,
+                    format!("Waiting for a debugger on port 5005 to run {filter}"),
+                )
+                .await;
+        }
+
+        let output = match command.output().await {
+            Ok(output) => output,
+            Err(err) => {
+                error!("Failed to run gradle in {:?}: {:?}", root, err);
+                self.client
+                    .show_message(MessageType::ERROR, format!("Failed to run gradle: {err}"))
+                    .await;
+                return Ok(None);
+            }
+        };
+        self.client
+            .log_message(
+                MessageType::LOG,
+                format!(
+                    "{}{}",
+                    String::from_utf8_lossy(&output.stdout),
+                    String::from_utf8_lossy(&output.stderr)
+                ),
+            )
+            .await;
+        if output.status.success() {
+            self.client
+                .show_message(MessageType::INFO, format!("{filter} passed"))
+                .await;
+        } else {
+            self.client
+                .show_message(MessageType::ERROR, format!("{filter} failed"))
+                .await;
+        }
+
+        Ok(None)
+    }
+
+    async fn metrics(&self) -> Result<Metrics> {
+        Ok(Metrics {
+            parse_cache_misses: self.parse_cache_misses.load(Ordering::Relaxed),
+        })
+    }
+}
+
+#[tower_lsp::async_trait]
+impl LanguageServer for Backend {
+    async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
+        info!("client-info: {:?}", params.client_info);
+        info!("root-uri: {:?}", params.root_uri);
+
+        let started = Instant::now();
+        let paths: Vec<PathBuf> = kotlin::kotlin_paths(params.root_uri.unwrap().path()).collect();
+        let parsed = kotlin::parallel_map(&paths, |parser, path| match std::fs::read(path) {
+            Ok(content) => self.parse_with(parser, path.clone(), &content),
+            Err(err) => error!("Failed to read {:?}: {:?}", path, err),
+        });
+        if let Err(err) = parsed {
+            error!("Failed to parse workspace: {:?}", err);
+        }
+
+        *self.index.write().unwrap() = SymbolIndex::build(&self.files);
+        *self.packages.write().unwrap() = PackageIndex::build(&self.files);
+        self.build_modules();
+        let paths: Vec<PathBuf> = self.files.iter().map(|file| file.key().clone()).collect();
+        for path in paths {
+            self.add_import_cycle_diagnostics(&path);
+        }
+        info!(
+            "parsed {} kotlin files in {:?}",
+            self.files.len(),
+            started.elapsed()
+        );
+
+        let type_hierarchy = params
+            .capabilities
+            .text_document
+            .as_ref()
+            .and_then(|text_document| text_document.type_hierarchy.as_ref())
+            .and_then(|type_hierarchy| type_hierarchy.dynamic_registration);
+        self.register_type_hierarchy
+            .store(type_hierarchy == Some(true), Ordering::Relaxed);
+
+        let capas = ServerCapabilities {
+            hover_provider: Some(HoverProviderCapability::Simple(true)),
+            definition_provider: Some(OneOf::Left(true)),
+            completion_provider: Some(CompletionOptions {
+                trigger_characters: Some(vec![".".to_string()]),
+                ..Default::default()
+            }),
+            references_provider: Some(OneOf::Left(true)),
+            document_highlight_provider: Some(OneOf::Left(true)),
+            workspace_symbol_provider: Some(OneOf::Left(true)),
+            document_symbol_provider: Some(OneOf::Left(true)),
+            semantic_tokens_provider: Some(
+                SemanticTokensServerCapabilities::SemanticTokensOptions(SemanticTokensOptions {
+                    legend: SemanticTokensLegend {
+                        token_types: TOKEN_TYPES.to_vec(),
+                        token_modifiers: Vec::new(),
+                    },
+                    full: Some(SemanticTokensFullOptions::Bool(true)),
+                    ..Default::default()
+                }),
+            ),
+            rename_provider: Some(OneOf::Right(RenameOptions {
+                prepare_provider: Some(true),
+                work_done_progress_options: WorkDoneProgressOptions::default(),
+            })),
+            inlay_hint_provider: Some(OneOf::Right(InlayHintServerCapabilities::Options(
+                InlayHintOptions {
+                    resolve_provider: Some(false),
+                    work_done_progress_options: WorkDoneProgressOptions::default(),
+                },
+            ))),
+            folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
+            selection_range_provider: Some(SelectionRangeProviderCapability::Simple(true)),
+            code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+            document_formatting_provider: Some(OneOf::Left(true)),
+            call_hierarchy_provider: Some(CallHierarchyServerCapability::Simple(true)),
+            code_lens_provider: Some(CodeLensOptions {
+                resolve_provider: Some(false),
+            }),
+            execute_command_provider: Some(ExecuteCommandOptions {
+                commands: vec![ORGANIZE_IMPORTS.to_string(), RUN_TEST.to_string()],
+                work_done_progress_options: WorkDoneProgressOptions::default(),
+            }),
+            signature_help_provider: Some(SignatureHelpOptions {
+                trigger_characters: Some(vec!["(".to_string(), ",".to_string()]),
+                retrigger_characters: None,
+                work_done_progress_options: WorkDoneProgressOptions::default(),
+            }),
+            text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+            ..Default::default()
+        };
+
+        let res = InitializeResult {
+            capabilities: capas,
+            ..Default::default()
+        };
+
+        Ok(res)
+    }
+
+    async fn initialized(&self, _: InitializedParams) {
+        self.client
+            .log_message(MessageType::INFO, "server initialized!")
+            .await;
+
+        // the client drops notifications sent before this point
+        let paths: Vec<PathBuf> = self
+            .diagnostics
+            .iter()
+            .filter(|diagnostics| !diagnostics.is_empty())
+            .map(|diagnostics| diagnostics.key().clone())
+            .collect();
+        for path in paths {
+            self.publish_diagnostics(&path).await;
+        }
+
+        if self.register_type_hierarchy.load(Ordering::Relaxed) {
+            let registration = Registration {
+                id: "type-hierarchy".to_string(),
+                method: "textDocument/prepareTypeHierarchy".to_string(),
+                register_options: Some(serde_json::json!({
+                    "documentSelector": [{ "language": "kotlin" }],
+                })),
+            };
+            if let Err(err) = self.client.register_capability(vec![registration]).await {
+                error!("Failed to register type hierarchy: {:?}", err);
+            }
+        }
+    }
+
+    async fn did_open(&self, _: DidOpenTextDocumentParams) {
+        warn!("Got a textDocument/didOpen notification, but it is not implemented");
+    }
+
+    async fn did_change(&self, params: DidChangeTextDocumentParams) {
+        let Ok(path) = params.text_document.uri.to_file_path() else {
+            warn!(
+                "ignoring change of non-file uri {}",
+                params.text_document.uri
+            );
+            return;
+        };
+
+        // full sync, so the last change holds the whole document
+        if let Some(change) = params.content_changes.last() {
+            self.parse_cache_misses.fetch_add(1, Ordering::Relaxed);
+            self.reparse(path.clone(), change.text.as_bytes());
+            self.publish_diagnostics(&path).await;
+        }
+    }
+
+    async fn did_save(&self, params: DidSaveTextDocumentParams) {
+        info!("file saved: {:?}", params);
+        self.client
+            .log_message(MessageType::INFO, format!("file saved: {:?}", params))
+            .await;
+
+        let Ok(path) = params.text_document.uri.to_file_path() else {
+            warn!("ignoring save of non-file uri {}", params.text_document.uri);
+            return;
+        };
+
+        let content = match params.text {
+            Some(text) => text.into_bytes(),
+            None => match std::fs::read(&path) {
+                Ok(content) => content,
+                Err(err) => {
+                    error!("Failed to read {:?}: {:?}", path, err);
+                    return;
+                }
+            },
+        };
+        self.parse_cache_misses.fetch_add(1, Ordering::Relaxed);
+        self.reparse(path.clone(), &content);
+        self.publish_diagnostics(&path).await;
+    }
+
+    // files on disk are reloaded so they stay available to the rest of the project, unsaved
+    // changes are dropped either way
+    async fn did_close(&self, params: DidCloseTextDocumentParams) {
+        let uri = params.text_document.uri;
+        let Ok(path) = uri.to_file_path() else {
+            warn!("ignoring close of non-file uri {}", uri);
+            return;
+        };
+
+        match std::fs::read(&path) {
+            Ok(content) => self.reparse(path.clone(), &content),
+            Err(_) => {
+                self.files.remove(&path);
+                self.contents.remove(&path);
+                self.index.write().unwrap().remove(&path);
+                self.packages.write().unwrap().remove(&path);
+                self.build_modules();
+            }
+        }
+        self.diagnostics.remove(&path);
+        self.client.publish_diagnostics(uri, Vec::new(), None).await;
+    }
+
+    async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
+        let position = &params.text_document_position_params;
+        let Some((name, span)) = self.identifier_at(position) else {
+            return Ok(None);
+        };
+        let Ok(path) = position.text_document.uri.to_file_path() else {
+            return Ok(None);
+        };
+
+        let markdown = self.files.iter().find_map(|file| {
+            let mut markdown = None;
+            file.walk_declarations(&mut |declaration| {
+                if markdown.is_none() && declaration.name() == name {
+                    markdown = Some(hover_markdown(declaration));
+                }
+            });
+            markdown
+        });
+
+        Ok(markdown.map(|value| Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value,
+            }),
+            range: Some(self.range(&path, span)),
+        }))
+    }
+
+    async fn goto_definition(
+        &self,
+        params: GotoDefinitionParams,
+    ) -> Result<Option<GotoDefinitionResponse>> {
+        let position = &params.text_document_position_params;
+        let Some((name, _)) = self.identifier_at(position) else {
+            return Ok(None);
+        };
+        let import = position
+            .text_document
+            .uri
+            .to_file_path()
+            .ok()
+            .and_then(|path| {
+                let file = self.files.get(&path)?;
+                let import = file.import_of(&name)?;
+                Some((import.path.clone(), import.imported_name().to_string()))
+            });
+        let declared_name = import.as_ref().map_or(name.as_str(), |(_, name)| name);
+
+        // an import that resolves to a file of the project only matches declarations in it
+        let declaring_files = import
+            .as_ref()
+            .map(|(path, _)| self.packages.read().unwrap().get(path).to_vec())
+            .unwrap_or_default();
+        let locations: Vec<Location> = self
+            .index
+            .read()
+            .unwrap()
+            .get(declared_name)
+            .iter()
+            .filter(|location| {
+                declaring_files.is_empty() || declaring_files.contains(&location.path)
+            })
+            .filter_map(|location| {
+                let uri = Url::from_file_path(&location.path).ok()?;
+                Some(Location::new(
+                    uri,
+                    self.range(&location.path, location.span),
+                ))
+            })
+            .collect();
+
+        if locations.is_empty() {
+            if let Some((path, _)) = import {
+                return Err(Error {
+                    code: ErrorCode::ServerError(REQUEST_FAILED),
+                    message: format!(
+                        "Definition of {path} is in a compiled dependency (no sources available)"
+                    )
+                    .into(),
+                    data: None,
+                });
+            }
+            return Ok(None);
+        }
+
+        Ok(Some(GotoDefinitionResponse::Array(locations)))
+    }
+
+    async fn references(&self, params: ReferenceParams) -> Result<Option<Vec<Location>>> {
+        let Some((name, _)) = self.identifier_at(&params.text_document_position) else {
+            return Ok(None);
+        };
+
+        let mut locations = Vec::new();
+        for (path, spans) in self.find_references(&name, params.context.include_declaration, true) {
+            let Ok(uri) = Url::from_file_path(&path) else {
+                continue;
+            };
+            for span in spans {
+                locations.push(Location::new(uri.clone(), self.range(&path, span)));
+            }
+        }
+
+        Ok(Some(locations))
+    }
+
+    // occurrences in the current file only, declarations and assignments count as writes
+    async fn document_highlight(
+        &self,
+        params: DocumentHighlightParams,
+    ) -> Result<Option<Vec<DocumentHighlight>>> {
+        let position = params.text_document_position_params;
+        let Some((name, _)) = self.identifier_at(&position) else {
+            return Ok(None);
+        };
+        let Ok(path) = position.text_document.uri.to_file_path() else {
+            return Ok(None);
+        };
+        let Some(file) = self.files.get(&path) else {
+            return Ok(None);
+        };
+
+        let writes: HashSet<Span> = file
+            .find_assignments(&name)
+            .into_iter()
+            .chain(file.find_declarations(&name))
+            .collect();
+        let reads = file
+            .find_references(&name)
+            .into_iter()
+            .filter(|span| !writes.contains(span));
+
+        let highlight = |span, kind| DocumentHighlight {
+            range: self.range(&path, span),
+            kind: Some(kind),
+        };
+        let mut highlights: Vec<DocumentHighlight> = writes
+            .iter()
+            .map(|span| highlight(*span, DocumentHighlightKind::WRITE))
+            .chain(reads.map(|span| highlight(span, DocumentHighlightKind::READ)))
+            .collect();
+        highlights.sort_by_key(|highlight| highlight.range.start);
+
+        Ok(Some(highlights))
+    }
+
+    async fn prepare_rename(
+        &self,
+        params: TextDocumentPositionParams,
+    ) -> Result<Option<PrepareRenameResponse>> {
+        let Ok(path) = params.text_document.uri.to_file_path() else {
+            return Ok(None);
+        };
+
+        Ok(self
+            .symbol_at(&params)
+            .filter(|(_, _, symbol)| self.is_renameable(symbol))
+            .map(|(_, span, _)| PrepareRenameResponse::Range(self.range(&path, span))))
+    }
+
+    async fn rename(&self, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
+        if !kotlin::is_valid_identifier(&params.new_name) {
+            return Err(Error::invalid_params(format!(
+                "'{}' is not a valid kotlin identifier",
+                params.new_name
+            )));
+        }
+
+        let position = &params.text_document_position;
+        let Some((name, _, symbol)) = self
+            .symbol_at(position)
+            .filter(|(_, _, symbol)| self.is_renameable(symbol))
+        else {
+            return Ok(None);
+        };
+
+        // locals can't be referred to from other files
+        let paths: Vec<PathBuf> = match symbol {
+            Symbol::Local(_) => position
+                .text_document
+                .uri
+                .to_file_path()
+                .into_iter()
+                .collect(),
+            _ => self
+                .contents
+                .iter()
+                .filter(|content| {
+                    content
+                        .windows(name.len())
+                        .any(|window| window == name.as_bytes())
+                })
+                .map(|content| content.key().clone())
+                .collect(),
+        };
+
+        let mut changes = HashMap::new();
+        for path in paths {
+            let Ok(uri) = Url::from_file_path(&path) else {
+                continue;
+            };
+            let edits: Vec<TextEdit> = self
+                .occurrences(&uri, &name)
+                .into_iter()
+                .filter(|(_, resolved)| *resolved == symbol)
+                .map(|(span, _)| TextEdit::new(self.range(&path, span), params.new_name.clone()))
+                .collect();
+            if !edits.is_empty() {
+                changes.insert(uri, edits);
+            }
+        }
+
+        Ok(Some(WorkspaceEdit::new(changes)))
+    }
+
+    async fn symbol(
+        &self,
+        params: WorkspaceSymbolParams,
+    ) -> Result<Option<Vec<SymbolInformation>>> {
+        Ok(Some(self.search_symbols(&params.query)))
+    }
+
+    async fn document_symbol(
+        &self,
+        params: DocumentSymbolParams,
+    ) -> Result<Option<DocumentSymbolResponse>> {
+        let Ok(path) = params.text_document.uri.to_file_path() else {
+            return Ok(None);
+        };
+        let Some(file) = self.files.get(&path) else {
+            return Ok(None);
+        };
+
+        // declarations are walked member kind by member kind, not in document order
+        let mut symbols = Vec::new();
+        file.walk_declarations(&mut |declaration| {
+            #[allow(deprecated)]
+            let symbol = DocumentSymbol {
+                name: declaration.name().to_string(),
+                detail: Some(declaration.signature()),
+                kind: index::symbol_kind(&file, declaration),
+                tags: None,
+                deprecated: None,
+                range: self.range(&path, declaration.span()),
+                selection_range: self.range(&path, declaration.name_span()),
+                children: None,
+            };
+            let container = matches!(declaration, Declaration::Class(_) | Declaration::Object(_));
+            symbols.push((declaration.span(), container, symbol));
+        });
+        symbols.sort_by_key(|(span, ..)| span.start);
+
+        Ok(Some(DocumentSymbolResponse::Nested(nest_symbols(
+            &mut symbols.into_iter().peekable(),
+            None,
+        ))))
+    }
+
+    async fn semantic_tokens_full(
+        &self,
+        params: SemanticTokensParams,
+    ) -> Result<Option<SemanticTokensResult>> {
+        let Some((content, tree)) = self.syntax_tree(&params.text_document.uri) else {
+            return Ok(None);
+        };
+
+        Ok(Some(SemanticTokensResult::Tokens(SemanticTokens {
+            result_id: None,
+            data: semantic_tokens(&content, kotlin::highlights(&tree)),
+        })))
+    }
+
+    async fn inlay_hint(&self, params: InlayHintParams) -> Result<Option<Vec<InlayHint>>> {
+        let Ok(path) = params.text_document.uri.to_file_path() else {
+            return Ok(None);
+        };
+
+        let mut functions = HashMap::new();
+        for file in self.files.iter() {
+            file.walk_declarations(&mut |declaration| {
+                if let Declaration::Function(function) = declaration {
+                    functions
+                        .entry(function.name.clone())
+                        .or_insert_with(Vec::new)
+                        .push((function.parameters.clone(), function.return_type.clone()));
+                }
+            });
+        }
+
+        let Some(file) = self.files.get(&path) else {
+            return Ok(None);
+        };
+
+        // overloaded functions are skipped, the arguments alone can't tell them apart
+        let parameter_hints = file
+            .parameter_hints(|name| match functions.get(name).map(Vec::as_slice) {
+                Some([(parameters, _)]) => Some(parameters.as_slice()),
+                _ => None,
+            })
+            .into_iter()
+            .map(|(point, label)| (point, label, InlayHintKind::PARAMETER));
+        let type_hints = file
+            .type_hints(|name| match functions.get(name).map(Vec::as_slice) {
+                Some([(_, return_type)]) => return_type.as_deref(),
+                _ => None,
+            })
+            .into_iter()
+            .map(|(point, label)| (point, label, InlayHintKind::TYPE));
+
+        let hints = parameter_hints
+            .chain(type_hints)
+            .map(|(point, label, kind)| {
+                let position = match self.contents.get(&path) {
+                    Some(content) => point_to_position(&content, point),
+                    None => point_to_position(&[], point),
+                };
+                (position, label, kind)
+            })
+            .filter(|(position, _, _)| {
+                params.range.start <= *position && *position <= params.range.end
+            })
+            .map(|(position, label, kind)| InlayHint {
+                position,
+                label: InlayHintLabel::String(label),
+                padding_right: (kind == InlayHintKind::PARAMETER).then_some(true),
+                kind: Some(kind),
+                text_edits: None,
+                tooltip: None,
+                padding_left: None,
+                data: None,
+            })
+            .collect();
+
+        Ok(Some(hints))
+    }
+
+    async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
+        let position = &params.text_document_position;
+        let Ok(path) = position.text_document.uri.to_file_path() else {
+            return Ok(None);
+        };
+        let Some(content) = self.contents.get(&path).map(|content| content.clone()) else {
+            return Ok(None);
+        };
+        let point = position_to_point(&content, position.position);
+        let Some(file) = self.files.get(&path) else {
+            return Ok(None);
+        };
+
+        let items = match (
+            super_before(&content, point),
+            receiver_before(&content, point),
+            annotation_before(&content, point),
+        ) {
+            // `super.` offers the members of every supertype, `super<Base>.` those of Base
+            (Some(qualifier), ..) => {
+                let supertypes: Vec<String> = match qualifier {
+                    Some(qualifier) => vec![qualifier.to_string()],
+                    None => file
+                        .type_at(point)
+                        .map(|declaration| {
+                            declaration
+                                .supertypes()
+                                .into_iter()
+                                .map(str::to_string)
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                };
+                drop(file);
+
+                let mut items: Vec<CompletionItem> = Vec::new();
+                for supertype in supertypes {
+                    for item in self.class_members(&supertype) {
+                        if !items.iter().any(|known| known.label == item.label) {
+                            items.push(item);
+                        }
+                    }
+                }
+                items
+            }
+            (None, Some(receiver), _) => {
+                let Some(type_name) = file.type_name_at(receiver, point).map(str::to_string) else {
+                    return Ok(None);
+                };
+                drop(file);
+                self.class_members(&type_name)
+            }
+            (None, None, Some(position)) => {
+                drop(file);
+                self.annotation_items(position)
+            }
+            (None, None, None) => {
+                let context = file.keyword_context(point);
+                let mut items: Vec<CompletionItem> = kotlin::keywords(context)
+                    .into_iter()
+                    .map(|(keyword, snippet)| CompletionItem {
+                        label: keyword.to_string(),
+                        kind: Some(CompletionItemKind::KEYWORD),
+                        insert_text: snippet.map(str::to_string),
+                        insert_text_format: snippet.map(|_| InsertTextFormat::SNIPPET),
+                        ..Default::default()
+                    })
+                    .collect();
+
+                // the class of a reified type parameter is what makes it useful
+                if let (KeywordContext::FunctionBody, Some(function)) =
+                    (context, file.function_at(point))
+                {
+                    for type_parameter in &function.type_parameters {
+                        if type_parameter.is_reified() {
+                            let label = format!("{}::class", type_parameter.name());
+                            items.push(CompletionItem {
+                                insert_text: Some(label.clone()),
+                                label,
+                                kind: Some(CompletionItemKind::SNIPPET),
+                                insert_text_format: Some(InsertTextFormat::SNIPPET),
+                                ..Default::default()
+                            });
+                        }
+                    }
+                }
+
+                items
+            }
+        };
+
+        Ok(Some(CompletionResponse::List(CompletionList {
+            is_incomplete: false,
+            items,
+        })))
+    }
+
+    async fn signature_help(&self, params: SignatureHelpParams) -> Result<Option<SignatureHelp>> {
+        let position = &params.text_document_position_params;
+        let Some((content, tree)) = self.syntax_tree(&position.text_document.uri) else {
+            return Ok(None);
+        };
+        let point = position_to_point(&content, position.position);
+        let Some((name, index)) = kotlin::call_at(&tree, &content, point) else {
+            return Ok(None);
+        };
+
+        let mut signatures = Vec::new();
+        for file in self.files.iter() {
+            file.walk_declarations(&mut |declaration| {
+                if declaration.name() != name {
+                    return;
+                }
+                if let Some((label, parameters)) = declaration.call_signature() {
+                    signatures.push(SignatureInformation {
+                        label,
+                        documentation: declaration.kdoc().map(|kdoc| {
+                            Documentation::MarkupContent(MarkupContent {
+                                kind: MarkupKind::Markdown,
+                                value: kdoc.to_markdown(),
+                            })
+                        }),
+                        parameters: Some(
+                            parameters
+                                .into_iter()
+                                .map(|(parameter, optional)| ParameterInformation {
+                                    label: ParameterLabel::Simple(parameter),
+                                    documentation: optional.then(|| {
+                                        Documentation::String(
+                                            "Optional, has a default value".to_string(),
+                                        )
+                                    }),
+                                })
+                                .collect(),
+                        ),
+                        active_parameter: None,
+                    });
+                }
+            });
+        }
+
+        if signatures.is_empty() {
+            return Ok(None);
+        }
+
+        // the first overload that has enough parameters for the current argument
+        let active_signature = signatures
+            .iter()
+            .position(|signature| {
+                signature
+                    .parameters
+                    .as_ref()
+                    .is_some_and(|parameters| index < parameters.len())
+            })
+            .unwrap_or_default();
+
+        Ok(Some(SignatureHelp {
+            signatures,
+            active_signature: Some(active_signature as u32),
+            active_parameter: Some(index as u32),
+        }))
+    }
+
+    // offers an import for a name that is neither declared nor imported in the file,
+    // one action per package that declares it at the top level
+    async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
+        let uri = params.text_document.uri;
+        let position = TextDocumentPositionParams::new(
+            TextDocumentIdentifier::new(uri.clone()),
+            params.range.start,
+        );
+        let Some((name, _)) = self.identifier_at(&position) else {
+            return Ok(None);
+        };
+        let Ok(path) = uri.to_file_path() else {
+            return Ok(None);
+        };
+        let (package, wildcards) = match self.files.get(&path) {
+            Some(file) if file.import_of(&name).is_none() && !file.declares_top_level(&name) => (
+                file.package.name().to_string(),
+                file.imports
+                    .iter()
+                    .filter_map(|import| import.path.strip_suffix(".*"))
+                    .map(str::to_string)
+                    .collect::<Vec<String>>(),
+            ),
+            _ => return Ok(None),
+        };
+
+        let mut candidates = BTreeSet::new();
+        for qualified_name in self.packages.read().unwrap().qualified_names(&name) {
+            let Some((candidate, _)) = qualified_name.rsplit_once('.') else {
+                continue;
+            };
+            if candidate == package {
+                continue;
+            }
+            // already visible through a star import
+            if wildcards.iter().any(|wildcard| wildcard == candidate) {
+                return Ok(None);
+            }
+            candidates.insert(qualified_name.to_string());
+        }
+
+        let Some(file) = self.files.get(&path) else {
+            return Ok(None);
+        };
+        let actions = candidates
+            .into_iter()
+            .map(|candidate| {
+                let (point, text) = file.import_edit(&candidate);
+                let position = Position::new(point.row as u32, point.column as u32);
+                let edit = TextEdit::new(Range::new(position, position), text);
+                CodeActionOrCommand::CodeAction(CodeAction {
+                    title: format!("Import {candidate}"),
+                    kind: Some(CodeActionKind::QUICKFIX),
+                    edit: Some(WorkspaceEdit::new(HashMap::from([(
+                        uri.clone(),
+                        vec![edit],
+                    )]))),
+                    ..Default::default()
+                })
+            })
+            .collect();
+
+        Ok(Some(actions))
+    }
+
+    async fn formatting(&self, params: DocumentFormattingParams) -> Result<Option<Vec<TextEdit>>> {
+        let Some((content, tree)) = self.syntax_tree(&params.text_document.uri) else {
+            return Ok(None);
+        };
+
+        let edits = kotlin::format_edits(
+            &tree,
+            &content,
+            params.options.tab_size as usize,
+            params.options.insert_spaces,
+        )
+        .into_iter()
+        .map(|(span, text)| TextEdit::new(span_to_range(&content, span), text))
+        .collect();
+
+        Ok(Some(edits))
+    }
+
+    async fn prepare_call_hierarchy(
+        &self,
+        params: CallHierarchyPrepareParams,
+    ) -> Result<Option<Vec<CallHierarchyItem>>> {
+        let position = params.text_document_position_params;
+        let Ok(path) = position.text_document.uri.to_file_path() else {
+            return Ok(None);
+        };
+        let Some(file) = self.files.get(&path) else {
+            return Ok(None);
+        };
+        let point = self.point(&path, position.position);
+
+        Ok(file
+            .function_at(point)
+            .and_then(|function| self.call_hierarchy_item(&path, &file, function))
+            .map(|item| vec![item]))
+    }
+
+    // callers are matched by the name of the called function, calls outside of
+    // functions, e.g. in property initializers, have no caller to show
+    async fn incoming_calls(
+        &self,
+        params: CallHierarchyIncomingCallsParams,
+    ) -> Result<Option<Vec<CallHierarchyIncomingCall>>> {
+        let mut incoming: Vec<CallHierarchyIncomingCall> = Vec::new();
+        for file in self.files.iter() {
+            for span in file.find_calls(&params.item.name) {
+                let Some(caller) = file.function_at(span.start) else {
+                    continue;
+                };
+                let Some(from) = self.call_hierarchy_item(file.key(), &file, caller) else {
+                    continue;
+                };
+
+                let range = self.range(file.key(), span);
+                match incoming.iter_mut().find(|call| call.from == from) {
+                    Some(call) => call.from_ranges.push(range),
+                    None => incoming.push(CallHierarchyIncomingCall {
+                        from,
+                        from_ranges: vec![range],
+                    }),
+                }
+            }
+        }
+
+        Ok(Some(incoming))
+    }
+
+    // only callees declared in the workspace are listed
+    async fn outgoing_calls(
+        &self,
+        params: CallHierarchyOutgoingCallsParams,
+    ) -> Result<Option<Vec<CallHierarchyOutgoingCall>>> {
+        let Ok(path) = params.item.uri.to_file_path() else {
+            return Ok(None);
+        };
+        let mut calls: BTreeMap<String, Vec<Range>> = BTreeMap::new();
+        {
+            let Some(file) = self.files.get(&path) else {
+                return Ok(None);
+            };
+            let Some(function) =
+                file.function_at(self.point(&path, params.item.selection_range.start))
+            else {
+                return Ok(None);
+            };
+            for (name, span) in function.calls() {
+                calls
+                    .entry(name.to_string())
+                    .or_default()
+                    .push(self.range(&path, span));
+            }
+        }
+
+        let locations: Vec<(String, SymbolLocation)> = {
+            let index = self.index.read().unwrap();
+            calls
+                .keys()
+                .filter_map(|name| {
+                    let location = index.get(name).iter().find(|location| {
+                        matches!(location.kind, SymbolKind::FUNCTION | SymbolKind::METHOD)
+                    })?;
+                    Some((name.clone(), location.clone()))
+                })
+                .collect()
+        };
+
+        let mut outgoing = Vec::new();
+        for (name, location) in locations {
+            let Some(file) = self.files.get(&location.path) else {
+                continue;
+            };
+            let Some(to) = file
+                .function_at(location.span.start)
+                .and_then(|function| self.call_hierarchy_item(&location.path, &file, function))
+            else {
+                continue;
+            };
+
+            outgoing.push(CallHierarchyOutgoingCall {
+                to,
+                from_ranges: calls.remove(&name).unwrap_or_default(),
+            });
+        }
+
+        Ok(Some(outgoing))
+    }
+
+    async fn prepare_type_hierarchy(
+        &self,
+        params: TypeHierarchyPrepareParams,
+    ) -> Result<Option<Vec<TypeHierarchyItem>>> {
+        let position = params.text_document_position_params;
+        let Ok(path) = position.text_document.uri.to_file_path() else {
+            return Ok(None);
+        };
+        let Some(file) = self.files.get(&path) else {
+            return Ok(None);
+        };
+        let point = self.point(&path, position.position);
+
+        Ok(file
+            .type_at(point)
+            .and_then(|declaration| self.type_hierarchy_item(&path, &file, declaration))
+            .map(|item| vec![item]))
+    }
+
+    // supertypes are resolved by name, sealed classes and interfaces come first when a
+    // name is declared more than once
+    async fn supertypes(
+        &self,
+        params: TypeHierarchySupertypesParams,
+    ) -> Result<Option<Vec<TypeHierarchyItem>>> {
+        let Ok(path) = params.item.uri.to_file_path() else {
+            return Ok(None);
+        };
+        let supertypes: Vec<String> = {
+            let Some(file) = self.files.get(&path) else {
+                return Ok(None);
+            };
+            let Some(declaration) =
+                file.type_at(self.point(&path, params.item.selection_range.start))
+            else {
+                return Ok(None);
+            };
+            declaration
+                .supertypes()
+                .into_iter()
+                .map(str::to_string)
+                .collect()
+        };
+
+        let locations: Vec<SymbolLocation> = {
+            let index = self.index.read().unwrap();
+            supertypes
+                .iter()
+                .flat_map(|name| index.get(name))
+                .filter(|location| {
+                    matches!(
+                        location.kind,
+                        SymbolKind::CLASS | SymbolKind::INTERFACE | SymbolKind::ENUM
+                    )
+                })
+                .cloned()
+                .collect()
+        };
+
+        let mut items = Vec::new();
+        for location in locations {
+            let Some(file) = self.files.get(&location.path) else {
+                continue;
+            };
+            let Some(declaration) = file.type_at(location.span.start) else {
+                continue;
+            };
+            let preferred = matches!(
+                declaration,
+                Declaration::Class(class) if class.is_sealed() || class.class_type == ClassType::Interface
+            );
+            if let Some(item) = self.type_hierarchy_item(&location.path, &file, declaration) {
+                items.push((!preferred, item));
+            }
+        }
+        items.sort_by_key(|(preferred, _)| *preferred);
+
+        Ok(Some(items.into_iter().map(|(_, item)| item).collect()))
+    }
+
+    // direct subtypes in all files, matched by the name of the supertype
+    async fn subtypes(
+        &self,
+        params: TypeHierarchySubtypesParams,
+    ) -> Result<Option<Vec<TypeHierarchyItem>>> {
+        let mut items = Vec::new();
+        for file in self.files.iter() {
+            for declaration in file.subtypes_of(&params.item.name) {
+                items.extend(self.type_hierarchy_item(file.key(), &file, declaration));
+            }
+        }
+
+        Ok(Some(items))
+    }
+
+    // run and debug lenses on test functions and the classes that contain them
+    async fn code_lens(&self, params: CodeLensParams) -> Result<Option<Vec<CodeLens>>> {
+        let uri = params.text_document.uri;
+        let Ok(path) = uri.to_file_path() else {
+            return Ok(None);
+        };
+        let Some(file) = self.files.get(&path) else {
+            return Ok(None);
+        };
+
+        let mut tests = Vec::new();
+        file.walk_declarations(&mut |declaration| match declaration {
+            Declaration::Function(function) if function.is_test() => {
+                tests.push((function.name.clone(), function.name_span));
+            }
+            Declaration::Class(class)
+                if class
+                    .body
+                    .as_ref()
+                    .is_some_and(|body| body.functions().iter().any(|f| f.is_test())) =>
+            {
+                tests.push((class.name.clone(), class.name_span));
+            }
+            _ => {}
+        });
+
+        let mut lenses = Vec::new();
+        for (name, span) in tests {
+            let range = self.range(&path, span);
+            for (title, debug) in [("▶ Run", false), ("⬢ Debug", true)] {
+                lenses.push(CodeLens {
+                    range,
+                    command: Some(Command {
+                        title: title.to_string(),
+                        command: RUN_TEST.to_string(),
+                        arguments: Some(vec![
+                            serde_json::json!(uri.clone()),
+                            serde_json::json!(name.clone()),
+                            serde_json::json!(debug),
+                        ]),
+                    }),
+                    data: None,
+                });
+            }
+        }
+
+        Ok(Some(lenses))
+    }
+
+    async fn execute_command(
+        &self,
+        params: ExecuteCommandParams,
+    ) -> Result<Option<serde_json::Value>> {
+        if params.command == RUN_TEST {
+            return self.run_test(&params.arguments).await;
+        }
+        if params.command != ORGANIZE_IMPORTS {
+            return Err(Error::invalid_params(format!(
+                "unknown command {}",
+                params.command
+            )));
+        }
+        let Some(uri) = params
+            .arguments
+            .first()
+            .and_then(|argument| argument.as_str())
+            .and_then(|uri| Url::parse(uri).ok())
+        else {
+            return Err(Error::invalid_params(format!(
+                "{ORGANIZE_IMPORTS} expects the uri of a file"
+            )));
+        };
+
+        let Some((content, tree)) = self.syntax_tree(&uri) else {
+            return Ok(None);
+        };
+        // the analyzed file can be older than the last known content
+        let imports = match kotlin::get_imports(&tree, &content) {
+            Ok(imports) => imports,
+            Err(err) => {
+                error!("Failed to organize imports: {:?}", err);
+                return Ok(None);
+            }
+        };
+        let Some((span, text)) = kotlin::organize_imports(&tree, &content, &imports) else {
+            return Ok(None);
+        };
+
+        let edit = WorkspaceEdit::new(HashMap::from([(
+            uri,
+            vec![TextEdit::new(span_to_range(&content, span), text)],
+        )]));
+        if let Err(err) = self.client.apply_edit(edit).await {
+            error!("Failed to organize imports: {:?}", err);
+        }
+
+        Ok(None)
+    }
+
+    async fn folding_range(&self, params: FoldingRangeParams) -> Result<Option<Vec<FoldingRange>>> {
+        let Some((content, tree)) = self.syntax_tree(&params.text_document.uri) else {
+            return Ok(None);
+        };
+
+        let ranges = kotlin::folding_ranges(&tree)
+            .into_iter()
+            .map(|(span, kind)| (span_to_range(&content, span), kind))
+            .map(|(range, kind)| FoldingRange {
+                start_line: range.start.line,
+                start_character: Some(range.start.character),
+                end_line: range.end.line,
+                end_character: Some(range.end.character),
+                kind: Some(match kind {
+                    FoldingKind::Region => FoldingRangeKind::Region,
+                    FoldingKind::Imports => FoldingRangeKind::Imports,
+                    FoldingKind::Comment => FoldingRangeKind::Comment,
+                }),
+                collapsed_text: None,
+            })
+            .collect();
+
+        Ok(Some(ranges))
+    }
+
+    async fn selection_range(
+        &self,
+        params: SelectionRangeParams,
+    ) -> Result<Option<Vec<SelectionRange>>> {
+        let Some((content, tree)) = self.syntax_tree(&params.text_document.uri) else {
+            return Ok(None);
+        };
+
+        // the chain is built from the outermost node inwards, each range points to its parent
+        let ranges = params
+            .positions
+            .into_iter()
+            .map(|position| {
+                let point = position_to_point(&content, position);
+                let mut spans = kotlin::selection_spans(&tree, point);
+                let mut range = SelectionRange {
+                    range: span_to_range(
+                        &content,
+                        spans.pop().unwrap_or(Span {
+                            start: point,
+                            end: point,
+                        }),
+                    ),
+                    parent: None,
+                };
+                while let Some(span) = spans.pop() {
+                    range = SelectionRange {
+                        range: span_to_range(&content, span),
+                        parent: Some(Box::new(range)),
+                    };
+                }
+                range
+            })
+            .collect();
+
+        Ok(Some(ranges))
+    }
+
+    async fn shutdown(&self) -> Result<()> {
+        Ok(())
+    }
 }
 
-impl Backend {
-    fn new(client: Client) -> Backend {
-        Backend {
-            client,
-            files: DashMap::new(),
+// the identifier in front of a `.` or `?.`, followed by the partially typed member
+fn receiver_before(content: &[u8], point: Point) -> Option<&str> {
+    let line = std::str::from_utf8(content).ok()?.lines().nth(point.row)?;
+    let before = line
+        .get(..point.column)?
+        .trim_end_matches(|c: char| c.is_alphanumeric() || c == '_')
+        .strip_suffix('.')?;
+    let before = before.strip_suffix('?').unwrap_or(before);
+
+    before
+        .rsplit(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .next()
+        .filter(|receiver| !receiver.is_empty())
+}
+
+// `super.` or `super<Base>.` followed by the partially typed member, with the qualifier
+fn super_before(content: &[u8], point: Point) -> Option<Option<&str>> {
+    let is_identifier = |c: char| c.is_alphanumeric() || c == '_';
+    let line = std::str::from_utf8(content).ok()?.lines().nth(point.row)?;
+    let before = line
+        .get(..point.column)?
+        .trim_end_matches(is_identifier)
+        .strip_suffix('.')?;
+    let (before, qualifier) = match before.strip_suffix('>') {
+        Some(before) => {
+            let (before, qualifier) = before.rsplit_once("super<")?;
+            (before, Some(qualifier.trim()))
+        }
+        None => (before.strip_suffix("super")?, None),
+    };
+
+    (!before.ends_with(is_identifier)
+        && qualifier.is_none_or(|qualifier| qualifier.chars().all(is_identifier)))
+    .then_some(qualifier)
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum AnnotationPosition {
+    Name,
+    Arguments,
+}
+
+// `@` followed by the partially typed annotation name, or inside the parentheses after it
+fn annotation_before(content: &[u8], point: Point) -> Option<AnnotationPosition> {
+    let is_name = |c: char| c.is_alphanumeric() || c == '_' || c == '.';
+    let follows_at = |before: &str| {
+        before
+            .trim_end_matches(is_name)
+            .strip_suffix('@')
+            .is_some_and(|before| !before.ends_with(|c: char| c.is_alphanumeric() || c == '_'))
+    };
+    let line = std::str::from_utf8(content).ok()?.lines().nth(point.row)?;
+    let before = line.get(..point.column)?;
+    if follows_at(before) {
+        return Some(AnnotationPosition::Name);
+    }
+
+    let mut depth = 0;
+    for (i, c) in before.char_indices().rev() {
+        match c {
+            ')' => depth += 1,
+            '(' if depth > 0 => depth -= 1,
+            '(' => {
+                return follows_at(&before[..i]).then_some(AnnotationPosition::Arguments);
+            }
+            _ => {}
         }
     }
+
+    None
 }
 
-#[tower_lsp::async_trait]
-impl LanguageServer for Backend {
-    async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
-        info!("client-info: {:?}", params.client_info);
-        info!("root-uri: {:?}", params.root_uri);
+fn member_items(class: &Class) -> Vec<CompletionItem> {
+    let mut items = Vec::new();
+    for parameter in class.constructor.iter().flat_map(|c| &c.parameters) {
+        if parameter.mutability.is_some() {
+            items.push(CompletionItem {
+                label: parameter.name.clone(),
+                kind: Some(CompletionItemKind::FIELD),
+                detail: Some(format!("{}: {}", parameter.name, parameter.data_type)),
+                ..Default::default()
+            });
+        }
+    }
 
-        for file in kotlin::from_path(params.root_uri.unwrap().path()).unwrap() {
-            match file.1 {
-                Ok(f) => {
-                    self.files.insert(file.0, f);
-                }
-                Err(err) => error!("Failed to parse {:?}: {:?}", file.0, err),
+    if let Some(body) = &class.body {
+        for property in body.properties() {
+            for declaration in property.variable_declarations() {
+                items.push(CompletionItem {
+                    label: declaration.identifier.clone(),
+                    kind: Some(CompletionItemKind::FIELD),
+                    detail: Some(Declaration::Property(property, declaration).signature()),
+                    ..Default::default()
+                });
             }
         }
+        for function in body.functions() {
+            // shown next to the name, a vararg takes any number of values
+            let vararg = function
+                .parameters
+                .iter()
+                .find(|parameter| parameter.vararg)
+                .map(|parameter| CompletionItemLabelDetails {
+                    detail: Some(format!("(vararg {}...)", parameter.name)),
+                    description: None,
+                });
+            items.push(CompletionItem {
+                label: function.name.clone(),
+                label_details: vararg,
+                kind: Some(CompletionItemKind::METHOD),
+                detail: Declaration::Function(function)
+                    .call_signature()
+                    .map(|(label, _)| label),
+                ..Default::default()
+            });
+        }
+    }
 
-        info!("parsed {} kotlin files", self.files.len());
+    // data classes may override what the compiler would generate
+    for function in class.synthesized_functions() {
+        if !items.iter().any(|item| item.label == function.name) {
+            items.push(CompletionItem {
+                label: function.name.clone(),
+                kind: Some(CompletionItemKind::METHOD),
+                detail: Some(function.signature()),
+                ..Default::default()
+            });
+        }
+    }
 
-        let capas = ServerCapabilities {
-            hover_provider: Some(HoverProviderCapability::Simple(true)),
-            text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
-            ..Default::default()
-        };
+    items
+}
 
-        let res = InitializeResult {
-            capabilities: capas,
-            ..Default::default()
-        };
+// symbols sorted by start, the ones after a class or object that lie within it become its
+// children
+fn nest_symbols(
+    symbols: &mut Peekable<impl Iterator<Item = (Span, bool, DocumentSymbol)>>,
+    parent: Option<Span>,
+) -> Vec<DocumentSymbol> {
+    let mut nested = Vec::new();
+    while let Some((span, container, mut symbol)) =
+        symbols.next_if(|(span, ..)| parent.is_none_or(|parent| parent.contains(span.start)))
+    {
+        if container {
+            let children = nest_symbols(symbols, Some(span));
+            if !children.is_empty() {
+                symbol.children = Some(children);
+            }
+        }
+        nested.push(symbol);
+    }
 
-        Ok(res)
+    nested
+}
+
+fn hover_markdown(declaration: Declaration) -> String {
+    let mut markdown = format!("```kotlin\n{}\n```", declaration.signature());
+    if let Some(kdoc) = declaration.kdoc() {
+        markdown.push_str(&format!("\n\n---\n\n{}", kdoc.to_markdown()));
     }
 
-    async fn initialized(&self, _: InitializedParams) {
-        self.client
-            .log_message(MessageType::INFO, "server initialized!")
-            .await;
+    if let Declaration::Class(class) = declaration {
+        let synthesized: Vec<String> = class
+            .synthesized_functions()
+            .iter()
+            .map(SynthesizedFunction::signature)
+            .collect();
+        if !synthesized.is_empty() {
+            markdown.push_str(&format!(
+                "\n\n---\n\nSynthesized members\n\n```kotlin\n{}\n```",
+                synthesized.join("\n")
+            ));
+        }
     }
 
-    async fn did_open(&self, _: DidOpenTextDocumentParams) {
-        warn!("Got a textDocument/didOpen notification, but it is not implemented");
+    markdown
+}
+
+// lower is better: exact match, then prefix, then anywhere in the name
+fn match_quality(name: &str, query: &str) -> Option<u8> {
+    if name == query {
+        Some(0)
+    } else if name.starts_with(query) {
+        Some(1)
+    } else if name.contains(query) {
+        Some(2)
+    } else {
+        None
     }
+}
 
-    async fn did_save(&self, params: DidSaveTextDocumentParams) {
-        info!("file saved: {:?}", params);
-        self.client
-            .log_message(MessageType::INFO, format!("file saved: {:?}", params))
-            .await;
+// tokens may not span lines, so multiline comments and strings are split up per line
+// lengths and offsets are counted in UTF-16 code units like every LSP position
+fn semantic_tokens(content: &[u8], highlights: Vec<(Span, TokenType)>) -> Vec<SemanticToken> {
+    let lines: Vec<&[u8]> = content.split(|&b| b == b'\n').collect();
+    let mut tokens = Vec::new();
+    let (mut previous_line, mut previous_start) = (0, 0);
+    for (span, token_type) in highlights {
+        for line in span.start.row..=span.end.row {
+            let text = lines.get(line).copied().unwrap_or_default();
+            let start = if line == span.start.row {
+                span.start.column
+            } else {
+                0
+            };
+            let end = if line == span.end.row {
+                span.end.column
+            } else {
+                text.len()
+            };
+            if end <= start {
+                continue;
+            }
+            let (start, end) = (utf16_column(text, start), utf16_column(text, end));
+
+            let delta_line = (line - previous_line) as u32;
+            let delta_start = if delta_line == 0 {
+                start - previous_start
+            } else {
+                start
+            };
+            tokens.push(SemanticToken {
+                delta_line,
+                delta_start: delta_start as u32,
+                length: (end - start) as u32,
+                token_type: token_type as u32,
+                token_modifiers_bitset: 0,
+            });
+            (previous_line, previous_start) = (line, start);
+        }
     }
 
-    async fn hover(&self, _: HoverParams) -> Result<Option<Hover>> {
-        Ok(None)
+    tokens
+}
+
+// UTF-16 length of a line up to a byte column
+pub fn utf16_column(line: &[u8], column: usize) -> usize {
+    String::from_utf8_lossy(&line[..column.min(line.len())])
+        .encode_utf16()
+        .count()
+}
+
+// byte column of a UTF-16 offset into a line, the inverse of utf16_column. Offsets past the
+// end of the line stay past it, as do columns in lines that aren't valid UTF-8
+fn byte_column(line: &[u8], character: usize) -> usize {
+    let Ok(line) = std::str::from_utf8(line) else {
+        return character;
+    };
+
+    let mut units = 0;
+    for (column, c) in line.char_indices() {
+        if units >= character {
+            return column;
+        }
+        units += c.len_utf16();
     }
+    line.len() + character.saturating_sub(units)
+}
 
-    async fn shutdown(&self) -> Result<()> {
-        Ok(())
+fn error_diagnostic(range: Range, message: String) -> Diagnostic {
+    Diagnostic {
+        range,
+        severity: Some(DiagnosticSeverity::ERROR),
+        source: Some("kotlin-ls".to_string()),
+        message,
+        ..Default::default()
     }
 }
 
-pub fn panic_hook(panic_info: &PanicInfo) {
+// gradle test filter of a test class or function, nested classes use their binary name
+fn test_filter(file: &KotlinFile, name: &str) -> Option<String> {
+    let prefix = match file.package.name() {
+        "" => String::new(),
+        package => format!("{package}."),
+    };
+
+    class_test_filter(&file.classes, &prefix, name)
+}
+
+fn class_test_filter(classes: &[Class], prefix: &str, name: &str) -> Option<String> {
+    classes.iter().find_map(|class| {
+        let qualified = format!("{prefix}{}", class.name);
+        let body = class.body.as_ref()?;
+        let mut tests = body.functions().iter().filter(|f| f.is_test());
+        if class.name == name && tests.clone().next().is_some() {
+            return Some(qualified);
+        }
+        if tests.any(|f| f.name == name) {
+            return Some(format!("{qualified}.{name}"));
+        }
+
+        class_test_filter(body.classes(), &format!("{qualified}$"), name)
+    })
+}
+
+// closest directory above the file with a gradle wrapper or build
+fn gradle_project(path: &Path) -> Option<(PathBuf, PathBuf)> {
+    path.ancestors().skip(1).find_map(|dir| {
+        if dir.join("gradlew").is_file() {
+            return Some((dir.to_path_buf(), dir.join("gradlew")));
+        }
+        ["settings.gradle.kts", "settings.gradle"]
+            .iter()
+            .any(|settings| dir.join(settings).is_file())
+            .then(|| (dir.to_path_buf(), PathBuf::from("gradle")))
+    })
+}
+
+// the identifier the cursor is on or right after
+fn name_at(tree: &Tree, content: &[u8], point: Point) -> Option<String> {
+    let before = Point::new(point.row, point.column.saturating_sub(1));
+    [point, before].into_iter().find_map(|point| {
+        let node = tree.root_node().descendant_for_point_range(point, point)?;
+        matches!(node.kind(), "simple_identifier" | "type_identifier")
+            .then(|| node.utf8_text(content).ok().map(str::to_string))
+            .flatten()
+    })
+}
+
+// tree-sitter columns count bytes while lsp characters count UTF-16 code units, lines
+// are looked up in the content the span was parsed from
+fn span_to_range(content: &[u8], span: Span) -> Range {
+    Range::new(
+        point_to_position(content, span.start),
+        point_to_position(content, span.end),
+    )
+}
+
+fn point_to_position(content: &[u8], point: Point) -> Position {
+    let line = content
+        .split(|&b| b == b'\n')
+        .nth(point.row)
+        .unwrap_or_default();
+    let character = utf16_column(line, point.column) + point.column.saturating_sub(line.len());
+    Position::new(point.row as u32, character as u32)
+}
+
+fn position_to_point(content: &[u8], position: Position) -> Point {
+    let line = content
+        .split(|&b| b == b'\n')
+        .nth(position.line as usize)
+        .unwrap_or_default();
+    Point::new(
+        position.line as usize,
+        byte_column(line, position.character as usize),
+    )
+}
+
+pub fn panic_hook(panic_info: &PanicHookInfo) {
     let payload = panic_info.payload();
 
     #[allow(clippy::manual_map)]
@@ -109,6 +2076,21 @@ async fn main() {
         std::panic::set_hook(Box::new(panic_hook));
         panic!("This is a static panic message");
     });
+
+    if let Some(root) = std::env::args()
+        .skip_while(|arg| arg != "--dump-lsif")
+        .nth(1)
+    {
+        tracing_subscriber::fmt()
+            .with_writer(std::io::stderr)
+            .init();
+        if let Err(err) = lsif::dump(&root, BufWriter::new(std::io::stdout().lock())) {
+            error!("Failed to dump lsif for {:?}: {:?}", root, err);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let log_file = OpenOptions::new()
         .append(true)
         .create(true)
@@ -119,7 +2101,922 @@ async fn main() {
     let stdin = tokio::io::stdin();
     let stdout = tokio::io::stdout();
 
-    let (service, socket) = LspService::new(Backend::new);
+    let (service, socket) = LspService::build(Backend::new)
+        .custom_method("kotlin-ls/metrics", Backend::metrics)
+        .finish();
     info!("starting server");
     Server::new(stdin, stdout, socket).serve(service).await;
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use tower_lsp::lsp_types::*;
+    use tower_lsp::{LanguageServer, LspService};
+    use tree_sitter::Point;
+
+    use crate::index::SymbolIndex;
+    use crate::kotlin::{self, test_utils::parse, KotlinFile};
+
+    use super::{
+        annotation_before, byte_column, receiver_before, semantic_tokens, test_filter,
+        utf16_column, AnnotationPosition, Backend,
+    };
+
+    fn backend(files: &[(&str, &str)]) -> LspService<Backend> {
+        let (service, _) = LspService::new(Backend::new);
+        for (path, content) in files {
+            service
+                .inner()
+                .reparse(PathBuf::from(path), content.as_bytes());
+        }
+        *service.inner().index.write().unwrap() = SymbolIndex::build(&service.inner().files);
+
+        service
+    }
+
+    #[test]
+    fn receiver_before_non_ascii() {
+        let line = "// see \u{201c}foo.";
+        assert_eq!(
+            receiver_before(line.as_bytes(), Point::new(0, line.len())),
+            Some("foo")
+        );
+
+        let line = "val s = \"\u{e9}\u{e9}\".len";
+        assert_eq!(
+            receiver_before(line.as_bytes(), Point::new(0, line.len())),
+            None
+        );
+        let line = "  \u{1f600}x?.le";
+        assert_eq!(
+            receiver_before(line.as_bytes(), Point::new(0, line.len())),
+            Some("x")
+        );
+    }
+
+    #[test]
+    fn test_filters() {
+        let file = r#"
+            package com.example
+
+            class FooTest {
+                @Test
+                fun works() {}
+
+                class Inner {
+                    @Test
+                    fun nested() {}
+                }
+            }
+
+            class Helper {
+                fun works() {}
+            }
+            "#
+        .parse::<KotlinFile>()
+        .unwrap();
+
+        assert_eq!(
+            test_filter(&file, "FooTest").as_deref(),
+            Some("com.example.FooTest")
+        );
+        assert_eq!(
+            test_filter(&file, "works").as_deref(),
+            Some("com.example.FooTest.works")
+        );
+        assert_eq!(
+            test_filter(&file, "nested").as_deref(),
+            Some("com.example.FooTest$Inner.nested")
+        );
+        assert_eq!(test_filter(&file, "Helper"), None);
+    }
+
+    #[test]
+    fn class_members_of_same_named_classes() {
+        let service = backend(&[
+            (
+                "/a.kt",
+                "class A {\n    class Builder {\n        fun a() {}\n    }\n}\n",
+            ),
+            (
+                "/b.kt",
+                "class B {\n    class Builder {\n        fun b() {}\n    }\n}\n",
+            ),
+        ]);
+
+        let mut labels: Vec<String> = service
+            .inner()
+            .class_members("Builder")
+            .into_iter()
+            .map(|item| item.label)
+            .collect();
+        labels.sort();
+        assert_eq!(labels, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn utf16_columns() {
+        let line = "val s = \"\u{e9}\u{1f600}\" // x".as_bytes();
+
+        assert_eq!(utf16_column(line, 8), 8);
+        // two code units for the emoji, one for the accent, which take up six bytes
+        assert_eq!(utf16_column(line, 15), 12);
+        assert_eq!(utf16_column(line, 100), 18);
+
+        assert_eq!(byte_column(line, 8), 8);
+        assert_eq!(byte_column(line, 12), 15);
+        assert_eq!(byte_column(line, 20), 23);
+    }
+
+    #[test]
+    fn semantic_tokens_in_utf16() {
+        let content = "val s = \"\u{e9}\u{1f600}\" // x\nval t = 1\n";
+        let tree = parse(content);
+
+        let tokens: Vec<(u32, u32, u32)> =
+            semantic_tokens(content.as_bytes(), kotlin::highlights(&tree))
+                .into_iter()
+                .map(|token| (token.delta_line, token.delta_start, token.length))
+                .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                (0, 0, 3),
+                (0, 4, 1),
+                (0, 2, 1),
+                (0, 2, 5),
+                (0, 6, 4),
+                (1, 0, 3),
+                (0, 4, 1),
+                (0, 2, 1),
+                (0, 2, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn import_cycle_diagnostics() {
+        let service = backend(&[
+            ("/a.kt", "package a\n\nimport b.B\n\nclass A\n"),
+            ("/b.kt", "package b\n\nimport a.A\n\nclass B\n"),
+        ]);
+
+        let diagnostics = service
+            .inner()
+            .diagnostics
+            .get(Path::new("/b.kt"))
+            .unwrap()
+            .clone();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "Circular import: b -> a -> b");
+        assert_eq!(diagnostics[0].range.start, Position::new(2, 0));
+    }
+
+    #[test]
+    fn annotation_positions() {
+        let position = |line: &str| annotation_before(line.as_bytes(), Point::new(0, line.len()));
+
+        assert_eq!(position("    @"), Some(AnnotationPosition::Name));
+        assert_eq!(position("@Dep"), Some(AnnotationPosition::Name));
+        assert_eq!(position("@kotlin.Dep"), Some(AnnotationPosition::Name));
+        assert_eq!(position("@Foo("), Some(AnnotationPosition::Arguments));
+        assert_eq!(
+            position("@Foo(f(1), MA"),
+            Some(AnnotationPosition::Arguments)
+        );
+        assert_eq!(position("return@"), None);
+        assert_eq!(position("foo(MA"), None);
+        assert_eq!(position("@Foo(x) fun f(a"), None);
+    }
+
+    async fn completion_labels(
+        service: &LspService<Backend>,
+        path: &str,
+        position: Position,
+    ) -> Vec<String> {
+        let params = CompletionParams {
+            text_document_position: TextDocumentPositionParams::new(
+                TextDocumentIdentifier::new(Url::from_file_path(path).unwrap()),
+                position,
+            ),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+            context: None,
+        };
+        let Some(CompletionResponse::List(list)) =
+            service.inner().completion(params).await.unwrap()
+        else {
+            panic!("expected a completion list");
+        };
+
+        list.items.into_iter().map(|item| item.label).collect()
+    }
+
+    #[tokio::test]
+    async fn annotation_completion() {
+        let service = backend(&[
+            ("/a.kt", "annotation class Route(val path: String)\nclass NotAnnotation\nconst val HOME = \"/\"\nval notConst = \"/\"\n"),
+            ("/b.kt", "class C {\n    fun f() {}\n    @Route()\n    fun g() {}\n}\n"),
+        ]);
+        // the last analysis that worked is kept while typing
+        service.inner().reparse(
+            PathBuf::from("/b.kt"),
+            b"class C {\n    @\n    fun f() {}\n    @Route()\n    fun g() {}\n}\n",
+        );
+
+        assert_eq!(
+            completion_labels(&service, "/b.kt", Position::new(1, 5)).await,
+            vec!["Route"]
+        );
+        assert_eq!(
+            completion_labels(&service, "/b.kt", Position::new(3, 11)).await,
+            vec!["HOME"]
+        );
+    }
+
+    fn position(path: &str, line: u32, character: u32) -> TextDocumentPositionParams {
+        TextDocumentPositionParams::new(
+            TextDocumentIdentifier::new(Url::from_file_path(path).unwrap()),
+            Position::new(line, character),
+        )
+    }
+
+    // start of every edit as (path, line, character)
+    async fn renamed(
+        service: &LspService<Backend>,
+        path: &str,
+        position: Position,
+    ) -> Vec<(String, u32, u32)> {
+        let params = RenameParams {
+            text_document_position: self::position(path, position.line, position.character),
+            new_name: "Renamed".to_string(),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        };
+        let Some(edit) = service.inner().rename(params).await.unwrap() else {
+            return Vec::new();
+        };
+
+        let mut edits: Vec<(String, u32, u32)> = edit
+            .changes
+            .unwrap()
+            .into_iter()
+            .flat_map(|(uri, edits)| {
+                let path = uri.path().to_string();
+                edits.into_iter().map(move |edit| {
+                    (
+                        path.clone(),
+                        edit.range.start.line,
+                        edit.range.start.character,
+                    )
+                })
+            })
+            .collect();
+        edits.sort();
+        edits
+    }
+
+    #[tokio::test]
+    async fn rename_resolves_the_declaration() {
+        let service = backend(&[
+            ("/a/Foo.kt", "package a\n\nclass Foo\n"),
+            ("/b/Foo.kt", "package b\n\nclass Foo\n"),
+            (
+                "/c.kt",
+                "package c\n\nimport a.Foo\n\n@Foo\nclass C : Foo(), List<Foo> {\n    fun f(): Foo = Foo()\n}\n",
+            ),
+            ("/d.kt", "package d\n\nimport b.Foo\n\nval foo: Foo = Foo()\n"),
+        ]);
+        let expected = vec![
+            ("/a/Foo.kt".to_string(), 2, 6),
+            ("/c.kt".to_string(), 2, 9),
+            ("/c.kt".to_string(), 4, 1),
+            ("/c.kt".to_string(), 5, 10),
+            ("/c.kt".to_string(), 5, 22),
+            ("/c.kt".to_string(), 6, 13),
+            ("/c.kt".to_string(), 6, 19),
+        ];
+
+        assert_eq!(
+            renamed(&service, "/c.kt", Position::new(6, 14)).await,
+            expected
+        );
+        assert_eq!(
+            renamed(&service, "/a/Foo.kt", Position::new(2, 6)).await,
+            expected
+        );
+        assert_eq!(
+            renamed(&service, "/d.kt", Position::new(4, 9)).await,
+            vec![
+                ("/b/Foo.kt".to_string(), 2, 6),
+                ("/d.kt".to_string(), 2, 9),
+                ("/d.kt".to_string(), 4, 9),
+                ("/d.kt".to_string(), 4, 15),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn rename_locals() {
+        let content = "fun f(x: Int): Int {\n    val y = x + 1\n    return y\n}\nfun g(x: Int) = x\nval y = 0\n";
+        let service = backend(&[("/a.kt", content)]);
+
+        let prepared = service
+            .inner()
+            .prepare_rename(position("/a.kt", 1, 8))
+            .await
+            .unwrap();
+        assert_eq!(
+            prepared,
+            Some(PrepareRenameResponse::Range(Range::new(
+                Position::new(1, 8),
+                Position::new(1, 9)
+            )))
+        );
+        assert_eq!(
+            renamed(&service, "/a.kt", Position::new(1, 8)).await,
+            vec![("/a.kt".to_string(), 1, 8), ("/a.kt".to_string(), 2, 11)]
+        );
+        assert_eq!(
+            renamed(&service, "/a.kt", Position::new(0, 6)).await,
+            vec![("/a.kt".to_string(), 0, 6), ("/a.kt".to_string(), 1, 12)]
+        );
+        assert_eq!(
+            renamed(&service, "/a.kt", Position::new(5, 4)).await,
+            vec![("/a.kt".to_string(), 5, 4)]
+        );
+    }
+
+    #[tokio::test]
+    async fn rename_after_non_ascii() {
+        let service = backend(&[("/a.kt", "fun foo() = 1\nval s = \"\u{e9}\" + foo()\n")]);
+
+        // `foo` starts at byte 15 but at UTF-16 offset 14
+        assert_eq!(
+            service
+                .inner()
+                .prepare_rename(position("/a.kt", 1, 14))
+                .await
+                .unwrap(),
+            Some(PrepareRenameResponse::Range(Range::new(
+                Position::new(1, 14),
+                Position::new(1, 17)
+            )))
+        );
+        assert_eq!(
+            renamed(&service, "/a.kt", Position::new(1, 15)).await,
+            vec![("/a.kt".to_string(), 0, 4), ("/a.kt".to_string(), 1, 14)]
+        );
+    }
+
+    #[tokio::test]
+    async fn prepare_rename_outside_the_workspace() {
+        let service = backend(&[("/a.kt", "fun f() = println(listOf(1).size)\n")]);
+
+        for character in [10, 28] {
+            assert_eq!(
+                service
+                    .inner()
+                    .prepare_rename(position("/a.kt", 0, character))
+                    .await
+                    .unwrap(),
+                None
+            );
+        }
+    }
+
+    #[test]
+    fn parse_diagnostics() {
+        let service = backend(&[("/a.kt", "class A\n")]);
+        let backend = service.inner();
+        let path = PathBuf::from("/a.kt");
+        assert!(backend.diagnostics.get(&path).unwrap().is_empty());
+
+        backend.reparse(path.clone(), b"class C {\n    @\n    fun f( {}\n}\n");
+        let messages: Vec<String> = backend
+            .diagnostics
+            .get(&path)
+            .unwrap()
+            .iter()
+            .map(|diagnostic| diagnostic.message.clone())
+            .collect();
+        assert_eq!(messages[0], "syntax error");
+        assert!(
+            messages[1].starts_with("failed to analyze \"/a.kt\""),
+            "{messages:?}"
+        );
+        // the last analysis that worked is kept
+        assert_eq!(backend.files.get(&path).unwrap().classes[0].name, "A");
+
+        backend.reparse(path.clone(), b"class B\n");
+        assert!(backend.diagnostics.get(&path).unwrap().is_empty());
+        assert_eq!(backend.files.get(&path).unwrap().classes[0].name, "B");
+    }
+
+    #[tokio::test]
+    async fn metrics() {
+        let path =
+            std::env::temp_dir().join(format!("kotlin-ls-metrics-{}.kt", std::process::id()));
+        std::fs::write(&path, "class A\n").unwrap();
+        let service = backend(&[]);
+        let backend = service.inner();
+        let uri = Url::from_file_path(&path).unwrap();
+        let misses = || async { backend.metrics().await.unwrap().parse_cache_misses };
+
+        backend
+            .did_change(DidChangeTextDocumentParams {
+                text_document: VersionedTextDocumentIdentifier::new(uri.clone(), 1),
+                content_changes: vec![TextDocumentContentChangeEvent {
+                    range: None,
+                    range_length: None,
+                    text: "class B\n".to_string(),
+                }],
+            })
+            .await;
+        assert_eq!(misses().await, 1);
+
+        backend
+            .did_save(DidSaveTextDocumentParams {
+                text_document: TextDocumentIdentifier::new(uri.clone()),
+                text: Some("class C\n".to_string()),
+            })
+            .await;
+        assert_eq!(misses().await, 2);
+
+        // closing reloads the file from disk, which isn't an edit
+        backend
+            .did_close(DidCloseTextDocumentParams {
+                text_document: TextDocumentIdentifier::new(uri),
+            })
+            .await;
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(misses().await, 2);
+        assert_eq!(backend.files.get(&path).unwrap().classes[0].name, "A");
+    }
+
+    #[tokio::test]
+    async fn hover() {
+        let service = backend(&[(
+            "/a.kt",
+            "/** Adds one. */\nfun inc(x: Int): Int = x + 1\nval y = inc(1)\n",
+        )]);
+
+        let hover = service
+            .inner()
+            .hover(HoverParams {
+                text_document_position_params: position("/a.kt", 2, 9),
+                work_done_progress_params: WorkDoneProgressParams::default(),
+            })
+            .await
+            .unwrap()
+            .unwrap();
+        let HoverContents::Markup(markup) = hover.contents else {
+            panic!("expected markdown");
+        };
+        assert_eq!(
+            markup.value,
+            "```kotlin\nfun inc(x: Int): Int\n```\n\n---\n\nAdds one."
+        );
+        assert_eq!(
+            hover.range,
+            Some(Range::new(Position::new(2, 8), Position::new(2, 11)))
+        );
+    }
+
+    #[tokio::test]
+    async fn document_symbols() {
+        let service = backend(&[(
+            "/a.kt",
+            "class A {\n    fun String.shout() = uppercase()\n    class B\n}\nfun Int.twice(): Int = this * 2\n",
+        )]);
+
+        let Some(DocumentSymbolResponse::Nested(symbols)) = service
+            .inner()
+            .document_symbol(DocumentSymbolParams {
+                text_document: TextDocumentIdentifier::new(Url::from_file_path("/a.kt").unwrap()),
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+            })
+            .await
+            .unwrap()
+        else {
+            panic!("expected nested symbols");
+        };
+        fn flatten(symbols: &[DocumentSymbol], depth: usize) -> Vec<(usize, String, String)> {
+            symbols
+                .iter()
+                .flat_map(|symbol| {
+                    let children = symbol.children.as_deref().unwrap_or_default();
+                    std::iter::once((depth, symbol.name.clone(), symbol.detail.clone().unwrap()))
+                        .chain(flatten(children, depth + 1))
+                })
+                .collect()
+        }
+
+        assert_eq!(
+            flatten(&symbols, 0),
+            vec![
+                (0, "A".to_string(), "class A".to_string()),
+                (1, "shout".to_string(), "fun String.shout()".to_string()),
+                (1, "B".to_string(), "class B".to_string()),
+                (0, "twice".to_string(), "fun Int.twice(): Int".to_string()),
+            ]
+        );
+        assert_eq!(
+            symbols[0].selection_range,
+            Range::new(Position::new(0, 6), Position::new(0, 7))
+        );
+    }
+
+    #[tokio::test]
+    async fn type_alias() {
+        let service = backend(&[
+            (
+                "/a.kt",
+                "/** Many of one kind. */\ntypealias Many<T> = ArrayList<T>\n",
+            ),
+            ("/b.kt", "val names = Many<String>()\n"),
+        ]);
+
+        let hover = service
+            .inner()
+            .hover(HoverParams {
+                text_document_position_params: position("/b.kt", 0, 12),
+                work_done_progress_params: WorkDoneProgressParams::default(),
+            })
+            .await
+            .unwrap()
+            .unwrap();
+        let HoverContents::Markup(markup) = hover.contents else {
+            panic!("expected markdown");
+        };
+        assert_eq!(
+            markup.value,
+            "```kotlin\ntypealias Many<T> = ArrayList<T>\n```\n\n---\n\nMany of one kind."
+        );
+
+        let definition = service
+            .inner()
+            .goto_definition(GotoDefinitionParams {
+                text_document_position_params: position("/b.kt", 0, 12),
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            definition,
+            Some(GotoDefinitionResponse::Array(vec![Location::new(
+                Url::from_file_path("/a.kt").unwrap(),
+                Range::new(Position::new(1, 10), Position::new(1, 14))
+            )]))
+        );
+
+        let Some(DocumentSymbolResponse::Nested(symbols)) = service
+            .inner()
+            .document_symbol(DocumentSymbolParams {
+                text_document: TextDocumentIdentifier::new(Url::from_file_path("/a.kt").unwrap()),
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+            })
+            .await
+            .unwrap()
+        else {
+            panic!("expected nested symbols");
+        };
+        assert_eq!(symbols.len(), 1);
+        assert_eq!(symbols[0].name, "Many");
+        assert_eq!(symbols[0].kind, SymbolKind::CLASS);
+    }
+
+    #[tokio::test]
+    async fn signature_help_with_defaults() {
+        let service = backend(&[(
+            "/a.kt",
+            "fun greet(name: String, greeting: String = \"Hi\") = greeting + name\nval s = greet(\"a\", )\n",
+        )]);
+
+        let help = service
+            .inner()
+            .signature_help(SignatureHelpParams {
+                context: None,
+                text_document_position_params: position("/a.kt", 1, 19),
+                work_done_progress_params: WorkDoneProgressParams::default(),
+            })
+            .await
+            .unwrap()
+            .unwrap();
+        let signature = &help.signatures[0];
+        assert_eq!(
+            signature.label,
+            "fun greet(name: String, greeting: String = …)"
+        );
+        assert_eq!(
+            signature.parameters,
+            Some(vec![
+                ParameterInformation {
+                    label: ParameterLabel::Simple("name: String".to_string()),
+                    documentation: None,
+                },
+                ParameterInformation {
+                    label: ParameterLabel::Simple("greeting: String = …".to_string()),
+                    documentation: Some(Documentation::String(
+                        "Optional, has a default value".to_string()
+                    )),
+                },
+            ])
+        );
+        assert_eq!(help.active_parameter, Some(1));
+    }
+
+    #[tokio::test]
+    async fn vararg_completion() {
+        let service = backend(&[(
+            "/a.kt",
+            "class Log {\n    fun write(vararg lines: String) {}\n}\nfun f(log: Log) {\n    log.write()\n}\n",
+        )]);
+        service.inner().reparse(
+            PathBuf::from("/a.kt"),
+            b"class Log {\n    fun write(vararg lines: String) {}\n}\nfun f(log: Log) {\n    log.\n}\n",
+        );
+
+        let params = CompletionParams {
+            text_document_position: position("/a.kt", 4, 8),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+            context: None,
+        };
+        let Some(CompletionResponse::List(list)) =
+            service.inner().completion(params).await.unwrap()
+        else {
+            panic!("expected a completion list");
+        };
+        let item = &list.items[0];
+        assert_eq!(item.label, "write");
+        assert_eq!(
+            item.detail.as_deref(),
+            Some("fun write(vararg lines: String)")
+        );
+        assert_eq!(
+            item.label_details
+                .as_ref()
+                .and_then(|details| details.detail.as_deref()),
+            Some("(vararg lines...)")
+        );
+    }
+
+    #[tokio::test]
+    async fn supertypes_hover() {
+        let service = backend(&[(
+            "/a.kt",
+            "interface Bar\nopen class Base<T>\nobject Foo : Bar\nclass Impl : Base<Int>(), Bar\nval f = Foo\nval i = Impl()\n",
+        )]);
+        let hover = |line, character| {
+            let service = &service;
+            async move {
+                let hover = service
+                    .inner()
+                    .hover(HoverParams {
+                        text_document_position_params: position("/a.kt", line, character),
+                        work_done_progress_params: WorkDoneProgressParams::default(),
+                    })
+                    .await
+                    .unwrap()
+                    .unwrap();
+                let HoverContents::Markup(markup) = hover.contents else {
+                    panic!("expected markdown");
+                };
+                markup.value
+            }
+        };
+
+        assert_eq!(hover(4, 8).await, "```kotlin\nobject Foo : Bar\n```");
+        assert_eq!(
+            hover(5, 8).await,
+            "```kotlin\nclass Impl : Base<Int>, Bar\n```"
+        );
+    }
+
+    #[tokio::test]
+    async fn data_class_members() {
+        let service = backend(&[(
+            "/a.kt",
+            "data class P(val x: Int) {\n    override fun toString() = \"P\"\n}\nval q = P(1)\nfun f(p: P) {\n    p.x\n}\n",
+        )]);
+
+        let hover = service
+            .inner()
+            .hover(HoverParams {
+                text_document_position_params: position("/a.kt", 3, 8),
+                work_done_progress_params: WorkDoneProgressParams::default(),
+            })
+            .await
+            .unwrap()
+            .unwrap();
+        let HoverContents::Markup(markup) = hover.contents else {
+            panic!("expected markdown");
+        };
+        assert_eq!(
+            markup.value,
+            "```kotlin\nclass P\n```\n\n---\n\nSynthesized members\n\n```kotlin\nfun component1(): Int\nfun copy(x: Int): P\nfun equals(other: Any?): Boolean\nfun hashCode(): Int\nfun toString(): String\n```"
+        );
+
+        // the declared `toString` wins over the synthesized one
+        service.inner().reparse(
+            PathBuf::from("/a.kt"),
+            b"data class P(val x: Int) {\n    override fun toString() = \"P\"\n}\nval q = P(1)\nfun f(p: P) {\n    p.\n}\n",
+        );
+        assert_eq!(
+            completion_labels(&service, "/a.kt", Position::new(5, 6)).await,
+            vec!["x", "toString", "component1", "copy", "equals", "hashCode"]
+        );
+    }
+
+    #[tokio::test]
+    async fn references() {
+        let service = backend(&[
+            ("/a.kt", "fun f() = 1\nval x = f()\n"),
+            ("/b.kt", "val y = f() + f()\nval z = x\n"),
+        ]);
+        let references = |include_declaration| {
+            let params = ReferenceParams {
+                text_document_position: position("/a.kt", 1, 8),
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+                context: ReferenceContext {
+                    include_declaration,
+                },
+            };
+            let service = &service;
+            async move {
+                let mut locations: Vec<(String, u32, u32)> = service
+                    .inner()
+                    .references(params)
+                    .await
+                    .unwrap()
+                    .unwrap()
+                    .into_iter()
+                    .map(|location| {
+                        (
+                            location.uri.path().to_string(),
+                            location.range.start.line,
+                            location.range.start.character,
+                        )
+                    })
+                    .collect();
+                locations.sort();
+                locations
+            }
+        };
+
+        assert_eq!(
+            references(false).await,
+            vec![
+                ("/a.kt".to_string(), 1, 8),
+                ("/b.kt".to_string(), 0, 8),
+                ("/b.kt".to_string(), 0, 14),
+            ]
+        );
+        assert_eq!(
+            references(true).await,
+            vec![
+                ("/a.kt".to_string(), 0, 4),
+                ("/a.kt".to_string(), 1, 8),
+                ("/b.kt".to_string(), 0, 8),
+                ("/b.kt".to_string(), 0, 14),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn references_after_non_ascii() {
+        let service = backend(&[
+            ("/a.kt", "val t = \"\u{e9}\u{e9}\"; fun f() = 1\n"),
+            ("/b.kt", "val s = \"\u{e9}\" + f()\n"),
+        ]);
+        let params = ReferenceParams {
+            text_document_position: position("/b.kt", 0, 14),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+            context: ReferenceContext {
+                include_declaration: true,
+            },
+        };
+
+        let mut ranges: Vec<(String, Range)> = service
+            .inner()
+            .references(params)
+            .await
+            .unwrap()
+            .unwrap()
+            .into_iter()
+            .map(|location| (location.uri.path().to_string(), location.range))
+            .collect();
+        ranges.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            ranges,
+            vec![
+                (
+                    "/a.kt".to_string(),
+                    Range::new(Position::new(0, 18), Position::new(0, 19))
+                ),
+                (
+                    "/b.kt".to_string(),
+                    Range::new(Position::new(0, 14), Position::new(0, 15))
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn import_code_action() {
+        let service = backend(&[
+            ("/a/Foo.kt", "package a\n\nclass Foo\n"),
+            ("/c/Foo.kt", "package c\n\nclass Foo\n"),
+            ("/b.kt", "package b\n\nimport z.Bar\n\nval x = Foo()\n"),
+            ("/d.kt", "package d\n\nimport a.*\n\nval x = Foo()\n"),
+        ]);
+        let code_action = |path: &str| {
+            let params = CodeActionParams {
+                text_document: TextDocumentIdentifier::new(Url::from_file_path(path).unwrap()),
+                range: Range::new(Position::new(4, 9), Position::new(4, 9)),
+                context: CodeActionContext::default(),
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+            };
+            let service = &service;
+            async move { service.inner().code_action(params).await.unwrap() }
+        };
+
+        let actions: Vec<(String, Vec<TextEdit>)> = code_action("/b.kt")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|action| {
+                let CodeActionOrCommand::CodeAction(action) = action else {
+                    panic!("expected a code action");
+                };
+                let edits = action
+                    .edit
+                    .unwrap()
+                    .changes
+                    .unwrap()
+                    .into_values()
+                    .flatten();
+                (action.title, edits.collect())
+            })
+            .collect();
+        let insert = |text: &str| {
+            vec![TextEdit::new(
+                Range::new(Position::new(2, 0), Position::new(2, 0)),
+                text.to_string(),
+            )]
+        };
+        assert_eq!(
+            actions,
+            vec![
+                ("Import a.Foo".to_string(), insert("import a.Foo\n")),
+                ("Import c.Foo".to_string(), insert("import c.Foo\n")),
+            ]
+        );
+        // already visible through the star import
+        assert_eq!(code_action("/d.kt").await, None);
+    }
+
+    #[tokio::test]
+    async fn positions_after_non_ascii() {
+        let service = backend(&[
+            ("/a.kt", "annotation class Route(val path: String)\n"),
+            (
+                "/b.kt",
+                "fun inc(x: Int) = x + 1\nval s = \"\u{e9}\u{e9}\" + inc(1)\n",
+            ),
+            ("/c.kt", "class C {\n    fun g() {}\n}\n"),
+        ]);
+        service.inner().reparse(
+            PathBuf::from("/c.kt"),
+            "class C {\n    /* \u{e9}\u{e9} */ @\n    fun g() {}\n}\n".as_bytes(),
+        );
+
+        // `@` starts at byte 15, which is UTF-16 offset 13
+        assert_eq!(
+            completion_labels(&service, "/c.kt", Position::new(1, 14)).await,
+            vec!["Route"]
+        );
+
+        let hover = service
+            .inner()
+            .hover(HoverParams {
+                text_document_position_params: position("/b.kt", 1, 15),
+                work_done_progress_params: WorkDoneProgressParams::default(),
+            })
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            hover.range,
+            Some(Range::new(Position::new(1, 15), Position::new(1, 18)))
+        );
+    }
+}
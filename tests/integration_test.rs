@@ -7,9 +7,17 @@ use tracing::{debug, error};
 fn test_dankchat() {
     tracing_subscriber::fmt().init();
 
-    let mut failures = Vec::new();
+    let files = kotlin::from_path("DankChat").unwrap();
+    // the fixture is a submodule that isn't checked out by default
+    if files.is_empty() {
+        eprintln!(
+            "skipping, no kotlin files in DankChat, check out the fixture with `git submodule update --init`"
+        );
+        return;
+    }
 
-    for (path, file) in kotlin::from_path("DankChat").unwrap() {
+    let mut failures = Vec::new();
+    for (path, file) in files {
         match file {
             Ok(f) => {
                 if path.file_name().unwrap().to_str().unwrap() == "DankChatApplication.kt" {